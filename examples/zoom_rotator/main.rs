@@ -1,12 +1,12 @@
 
-use std::time::{Instant, Duration};
+use std::time::Instant;
 
-use gemgui::graphics::bitmap_transform::BitmapTransform;
-use gemgui::graphics::canvas::{Canvas, DrawNotify};
+use gemgui::graphics::bitmap_transform::{BitmapTransform, Filter};
+use gemgui::graphics::canvas::Canvas;
 use gemgui::graphics::bitmap::Bitmap;
 use gemgui::ui::Ui;
 use gemgui::ui_ref::UiRef;
-use gemgui::{self, GemGuiError, Value};
+use gemgui::{self, GemGuiError};
 use gemgui::graphics::color as Color;
 
 include!(concat!(env!("OUT_DIR"), "/generated.rs"));
@@ -15,6 +15,7 @@ async fn amain(ui: UiRef) {
   let image = ui.resource("widgets.jpeg").unwrap();
   let bitmap = Bitmap::from_image_bytes(&image).unwrap();
   let mut transformer = BitmapTransform::new(&bitmap);
+  transformer.set_filter(Filter::Bilinear);
 
   let mut t:f64 = 0.0; 
 
@@ -29,40 +30,18 @@ async fn amain(ui: UiRef) {
   let mut int = Instant::now();
   let fps_element = ui.element("fps");
 
-  let scale_value = Value::new(1.0);
-  let scaled_value = scale_value.clone();
-  let enlarge_value = Value::new(true);
-
-  ui.periodic(Duration::from_millis(100), move |_, _| {
-    let mut scale = scale_value.cloned();
-    let mut enlarge = enlarge_value.cloned();
-    if scale > 2.0 {
-      enlarge = false;
-      enlarge_value.assign(enlarge);
-    }
-    if scale < 0.5 {
-      enlarge = true;
-      enlarge_value.assign(enlarge);
-    }    
-    if enlarge {
-        scale += 0.1;
-      } else {
-        scale -= 0.1;
-      }
-      scale_value.assign(scale);     
-  });
-
   let canvas = Canvas::from(canvas);
-  canvas.on_draw(move |ui| {
+
+  // painting follows display refresh, scale swings in between 0.5 and 2.0
+  ui.on_animation_frame(move |_, timestamp| {
     let a = (t * 0.20).sin() * std::f64::consts::PI / 0.5;
     t += 0.2;
     transformer.restore(); // read initial 
     transformer.rotate(a); //  rotate  a angles
     transformer.resize(ww, wh).unwrap(); // scale to window size
     transformer.center(ww, wh).unwrap(); // center in
-    let canvas = Canvas::from(ui.element("canvas"));
 
-    let scale = scaled_value.cloned();
+    let scale = 1.25 + 0.75 * (timestamp.as_secs_f64() * 0.5).sin();
   
     transformer.scale(scale, scale).unwrap();
     transformer.center(ww, wh).unwrap();
@@ -83,8 +62,7 @@ async fn amain(ui: UiRef) {
     }
 
     count += 1;
-    
-  }, DrawNotify::Kick);
+  });
 
 }
 
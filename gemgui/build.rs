@@ -7,5 +7,5 @@ fn main() {
     const PYGUI_PATH: &str = "res/pyclient.py";
     println!("cargo:rerun-if-changed={GEMGUI_PATH}");
     println!("cargo:rerun-if-changed={PYGUI_PATH}");
-    pack("res", false);
+    pack_with("res", &PackOptions::new().compression(Compression::Gzip));
     }
\ No newline at end of file
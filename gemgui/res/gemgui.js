@@ -16,6 +16,8 @@ var sys_error = console.error;
 
 const event_notifiers = new Set(); // For non-JS nottifiers
 
+const tracked_elements = new Set(); // Elements that has subscriptions on Rust side
+
 var last_msg_id = -1;
 
 function g_log(msg) {
@@ -111,6 +113,7 @@ function createElement(parent, tag, id) {
             return;
         }
         el.setAttribute('id', id);
+        tracked_elements.add(id);
         if(parent)
             parent.appendChild(el);
         else
@@ -121,6 +124,128 @@ function createElement(parent, tag, id) {
     }
 }
 
+function buildElement(tree, created) {
+    const el = document.createElement(tree.tag);
+    if(!el) {
+        errlog("buildElement", "Cannot create " + tree.id);
+        return null;
+    }
+    el.setAttribute('id', tree.id);
+    for(const c of tree.classes || [])
+        el.classList.add(c);
+    for(const [attr, value] of Object.entries(tree.attributes || {}))
+        el.setAttribute(attr, value);
+    for(const [style, value] of Object.entries(tree.styles || {}))
+        el.style[style] = value;
+    if(tree.text)
+        el.textContent = tree.text;
+    for(const e of tree.events || []) {
+        if(e.event === 'created')
+            created.push(tree.id);
+        else
+            addEvent(el, tree.id, e.event, e.properties, e.throttle);
+    }
+    for(const c of tree.children || []) {
+        const child = buildElement(c, created);
+        if(!child)
+            return null;
+        el.appendChild(child);
+    }
+    return el;
+}
+
+function treeIds(tree, ids) {
+    ids.push(tree.id);
+    for(const c of tree.children || [])
+        treeIds(c, ids);
+    return ids;
+}
+
+function createElements(el, tree, position) {
+    const ids = treeIds(tree, []);
+    // nothing is inserted if any id is not unique, and Rust side drops subscriptions of the tree
+    const failed = (reason) => {
+        errlog("createElements", reason);
+        socket.send(JSON.stringify({'type': 'create_failed', 'elements': ids}));
+    };
+    const duplicate = ids.find((id, index) => ids.indexOf(id) !== index || document.getElementById(id) != null);
+    if(duplicate !== undefined) {
+        failed("Element exists " + duplicate);
+        return;
+    }
+    const created = [];
+    const newElement = buildElement(tree, created);
+    if(!newElement) {
+        failed("Cannot build " + tree.id);
+        return;
+    }
+    switch(position) {
+    case 'before':
+        el.parentNode.insertBefore(newElement, el);
+        break;
+    case 'after':
+        el.parentNode.insertBefore(newElement, el.nextSibling);
+        break;
+    default:
+        el.appendChild(newElement);
+    }
+    for(const id of ids)
+        tracked_elements.add(id);
+    log("created", tree.id, position, el.id);
+    for(const id of created)
+        socket.send(JSON.stringify({'type': 'event', 'element': id, 'event': 'created', 'properties': {}}));
+}
+
+const animations = new Map(); // Animation handles that are alive
+
+function animate(el, id, keyframes, options) {
+    if(options.iterations === 'Infinity')
+        options.iterations = Infinity;
+    try {
+        animations.set(id, el.animate(keyframes, options));
+    } catch(error) {
+        errlog("animate", error);
+    }
+}
+
+function controlAnimation(id, command) {
+    const animation = animations.get(id);
+    if(!animation) {
+        if(command !== 'release') // already dropped with its element
+            errlog("animation", "Not found " + id);
+        return;
+    }
+    switch(command) {
+    case 'play': animation.play(); break;
+    case 'pause': animation.pause(); break;
+    case 'reverse': animation.reverse(); break;
+    case 'cancel': animation.cancel(); break;
+    case 'finish': animation.finish(); break;
+    case 'release': animations.delete(id); break;
+    default:
+        errlog("animation", "Unknown command " + command);
+    }
+}
+
+function sendAnimationFinished(query_id, id) {
+    const send = value => socket.send(JSON.stringify({'type': 'query', 'query_id': query_id, 'query_value': 'animation_finished', 'animation_finished': value}));
+    const animation = animations.get(id);
+    if(!animation) {
+        errlog("animation", "Not found " + id);
+        send(false);
+        return;
+    }
+    switch(animation.playState) {
+    case 'running':
+    case 'paused':
+    case 'finished':
+        animation.finished.then(() => send(true), () => send(false));
+        break;
+    default: // idle, i.e. cancelled or never started: finished would stay pending until played again
+        send(false);
+    }
+}
+
 function removeElement(el, id) {
     if(el.id !== id) {
         const element = document.getElementById(id);
@@ -155,7 +280,49 @@ function throttled(delay, fn) {
   }
 }
 
+// Tell Rust when subscribed elements are gone, so their subscriptions can be dropped
+const removalObserver = new MutationObserver((mutationList) => {
+    const removed = [];
+    let elementsRemoved = false;
+    for (const mutation of mutationList) {
+        for (const node of mutation.removedNodes) {
+            if (node.nodeType !== 1)
+                continue;
+            elementsRemoved = true;
+            for (const n of [node, ...node.querySelectorAll('[id]')]) {
+                // moved elements are still there
+                if (n.id && tracked_elements.has(n.id) && !document.getElementById(n.id)) {
+                    tracked_elements.delete(n.id);
+                    removed.push(n.id);
+                }
+            }
+        }
+    }
+    // animations of removed elements are cancelled, i.e. their pending finished queries fail
+    if(elementsRemoved) {
+        for (const [id, animation] of animations) {
+            const target = animation.effect ? animation.effect.target : null;
+            if(!target || !target.isConnected) {
+                animation.cancel();
+                animations.delete(id);
+            }
+        }
+    }
+    if(removed.length > 0 && socket.readyState === 1) {
+        log("removed", removed);
+        socket.send(JSON.stringify({'type': 'removed', 'elements': removed}));
+    }
+});
+
+removalObserver.observe(document.documentElement, {childList: true, subtree: true});
+
 function addEvent(el, source, eventname, properties, throttle) {
+    if(source.length > 0)
+        tracked_elements.add(source);
+
+    if(eventname === 'removed') // removalObserver takes care of it
+        return;
+
     handler = (event) => {
 
         if(socket.readyState !== 1)
@@ -250,6 +417,20 @@ function id(el) {
 }
 
 
+// binary query response: type, id length, id padded to words and data
+function sendBinaryQuery(query_id, data) {
+    const id = new TextEncoder().encode(query_id);
+    const idBytes = Math.ceil(id.length / 4) * 4;
+    const bytes = data instanceof Uint8Array ? data : new Uint8Array(data);
+    const buffer = new Uint8Array(8 + idBytes + bytes.length);
+    const view = new DataView(buffer.buffer);
+    view.setUint32(0, 0xBBB, true);
+    view.setUint32(4, id.length, true);
+    buffer.set(id, 8);
+    buffer.set(bytes, 8 + idBytes);
+    socket.send(buffer);
+}
+
 function serveQuery(element, query_id, query, query_params) {
     const el = element.length > 0 ? document.getElementById(element) : document.body;
     if(!el) {
@@ -320,6 +501,63 @@ function serveQuery(element, query_id, query, query_params) {
                'query_value': 'bounding_rect',
                'bounding_rect': {'x':r.left, 'y':r.top, 'width': r.right - r.left, 'height': r.bottom - r.top}}));
            break;
+        case 'animation_finished':
+            sendAnimationFinished(query_id, query_params[0]);
+            break;
+        case 'measure_text': {
+            const ctx = el.getContext("2d");
+            ctx.save();
+            ctx.font = query_params[0];
+            const m = ctx.measureText(query_params[1]);
+            ctx.restore();
+            socket.send(JSON.stringify({
+                'type': 'query',
+                'query_id': query_id,
+                'query_value': 'measure_text',
+                'measure_text': {
+                    'width': m.width,
+                    'actualBoundingBoxLeft': m.actualBoundingBoxLeft,
+                    'actualBoundingBoxRight': m.actualBoundingBoxRight,
+                    'actualBoundingBoxAscent': m.actualBoundingBoxAscent,
+                    'actualBoundingBoxDescent': m.actualBoundingBoxDescent,
+                    'fontBoundingBoxAscent': m.fontBoundingBoxAscent,
+                    'fontBoundingBoxDescent': m.fontBoundingBoxDescent}}));
+            } break;
+        case 'canvas_size':
+            socket.send(JSON.stringify({
+                'type': 'query',
+                'query_id': query_id,
+                'query_value': 'canvas_size',
+                'canvas_size': {'width': el.width, 'height': el.height}}));
+            break;
+        case 'image_data': {
+            const [x, y, w, h] = query_params.map(Number);
+            let data = new ArrayBuffer(0);
+            try {
+                const image = el.getContext("2d").getImageData(x, y, w, h);
+                data = new Uint8Array(8 + image.data.length);
+                const view = new DataView(data.buffer);
+                view.setUint32(0, image.width, true);
+                view.setUint32(4, image.height, true);
+                data.set(image.data, 8);
+            } catch(ex) {
+                errlog(query_id, "Cannot read image data " + ex);
+            }
+            sendBinaryQuery(query_id, data);
+            } break;
+        case 'canvas_png':
+            if(!el.toBlob) {
+                errlog(query_id, "Not a canvas");
+                sendBinaryQuery(query_id, new ArrayBuffer(0));
+                break;
+            }
+            el.toBlob(blob => {
+                if(blob)
+                    blob.arrayBuffer().then(data => sendBinaryQuery(query_id, data));
+                else
+                    sendBinaryQuery(query_id, new ArrayBuffer(0));
+            }, 'image/png');
+            break;
         case 'devicePixelRatio':
             socket.send(JSON.stringify({
                                            'type': 'query',
@@ -343,14 +581,202 @@ function sendCollection(name, query_id, query, collectionFunction) {
     socket.send(JSON.stringify({'type': 'query', 'query_id': query_id, 'query_value': 'children', 'children': children}));   
 }
 
+// compressed tiles are decoded asynchronously, all following messages wait them to keep the order
+let binaryQueue = Promise.resolve();
+let binaryPending = 0;
+
+function enqueueMessage(handler) {
+    ++binaryPending;
+    binaryQueue = binaryQueue
+        .then(handler)
+        .catch(error => catchLog(error))
+        .finally(() => --binaryPending);
+}
+
 function handleBinary(buffer) {
     const bytes = new Uint32Array(buffer);
     if(!bytes || bytes.length === 0) {
         errlog("Binary", "Invalid buffer", buffer);
         return;
     }
+    if(binaryPending > 0 || bytes[0] === 0xAAB) {
+        enqueueMessage(() => bytes[0] === 0xAAB ? handleCompressedTile(buffer) : handleBinaryMessage(buffer));
+        return;
+    }
+    handleBinaryMessage(buffer);
+}
+
+async function decodeTile(encoding, payload, w, h) {
+    switch(encoding) {
+    case 1: { // RLE pairs of count and pixel
+        const runs = new Uint32Array(payload.buffer, payload.byteOffset, payload.byteLength / 4);
+        const pixels = new Uint32Array(w * h);
+        let pos = 0;
+        for(let i = 0; i + 1 < runs.length; i += 2) {
+            pixels.fill(runs[i + 1], pos, pos + runs[i]);
+            pos += runs[i];
+        }
+        return new ImageData(new Uint8ClampedArray(pixels.buffer), w, h);
+    }
+    case 2: { // zlib deflate
+        const stream = new Blob([payload]).stream().pipeThrough(new DecompressionStream('deflate'));
+        const data = await new Response(stream).arrayBuffer();
+        return new ImageData(new Uint8ClampedArray(data), w, h);
+    }
+    case 3: // PNG
+        return await createImageBitmap(new Blob([payload], {type: 'image/png'}));
+    default:
+        errlog("Binary", "Unknown tile encoding " + encoding);
+        return null;
+    }
+}
+
+async function handleCompressedTile(buffer) {
+    const bytes = new Uint32Array(buffer);
+    const datalen = bytes[1] * 4;
+    const idLen = bytes[2];
+    const headerLen = bytes[3];
+    const dataOffset = 4 * 4; //id, datalen, idlen, headerlen, data<datalen>, header<headerlen>, id<idlen>
+    const headerOffset = bytes[1] + 4;
+    const x = bytes[headerOffset];
+    const y = bytes[headerOffset + 1];
+    const w = bytes[headerOffset + 2];
+    const h = bytes[headerOffset + 3];
+    const as_draw = bytes[headerOffset + 4];
+    const encoding = bytes[headerOffset + 5];
+    const payloadLen = bytes[headerOffset + 6];
+    const idOffset = (headerLen * 4) + dataOffset + datalen;
+    const words = new Uint16Array(buffer, idOffset, idLen);
+    let id = "";
+    for(let i = 0 ; i < words.length && words[i] > 0; i++)
+        id += String.fromCharCode(words[i]);
+    const element = canvasTarget(id);
+    if(!element) {
+        errlog(id, "Canvas not found '" + id + "'");
+        return;
+    }
+    const ctx = element.getContext("2d", {alpha:false});
+    if(!ctx) {
+        errlog(id, "has no graphics context");
+        return;
+    }
+    const image = await decodeTile(encoding, new Uint8Array(buffer, dataOffset, payloadLen), w, h);
+    if(image instanceof ImageData)
+        ctx.putImageData(image, x, y);
+    else if(image) {
+        ctx.clearRect(x, y, w, h); // putImageData replaces pixels, keep the same for bitmaps
+        ctx.drawImage(image, x, y);
+        image.close();
+    }
+    if ((as_draw != 0) && event_notifiers.has("canvas_draw")) 
+        socket.send(JSON.stringify({'type': 'event', 'element': id, 'event': 'event_notify', 'properties': {'name': "canvas_draw", 'msgid': 0}}));
+}
+
+// must match to OPCODES in context.rs
+const canvasOpcodes = [
+    'strokeRect', 'clearRect', 'fillRect', 'fillText', 'strokeText', 'arc', 'ellipse', 'beginPath',
+    'closePath', 'lineTo', 'moveTo', 'bezierCurveTo', 'quadraticCurveTo', 'arcTo', 'rect', 'stroke',
+    'fill', 'fillStyle', 'strokeStyle', 'lineWidth', 'font', 'textAlign', 'save', 'restore',
+    'rotate', 'translate', 'scale', 'drawImage', 'drawImageRect', 'drawImageClip', 'textBaseline', 'fillGradient',
+    'strokeGradient', 'fillPattern', 'strokePattern', 'setLineDash', 'lineDashOffset', 'lineCap', 'lineJoin', 'miterLimit',
+    'globalAlpha', 'globalCompositeOperation', 'shadowColor', 'shadowBlur', 'shadowOffset', 'clip', 'setTransform', 'transform',
+    'resetTransform', 'roundRect', 'filter', 'imageSmoothingEnabled', 'imageSmoothingQuality'];
+
+const textDecoder = new TextDecoder();
+
+// binary encoded commands to canvasDraw commands
+function decodeCanvasCommands(buffer, offset, length, count) {
+    const view = new DataView(buffer, offset, length);
+    const commands = [];
+    let pos = 0;
+    for(let c = 0; c < count; ++c) {
+        const header = view.getUint32(pos, true);
+        pos += 4;
+        commands.push(canvasOpcodes[header & 0xFF]);
+        const argc = header >>> 16;
+        const masks = [];
+        if(header & 0x100) {
+            for(let m = 0; m < Math.ceil(argc / 32); ++m) {
+                masks.push(view.getUint32(pos, true));
+                pos += 4;
+            }
+        }
+        for(let a = 0; a < argc; ++a) {
+            const isText = masks.length > 0 && (masks[a >>> 5] & (1 << (a & 31))) != 0;
+            if(isText) {
+                const len = view.getUint32(pos, true);
+                pos += 4;
+                commands.push(textDecoder.decode(new Uint8Array(buffer, offset + pos, len)));
+                pos += (len + 3) & ~3;
+            } else {
+                commands.push(view.getFloat32(pos, true));
+                pos += 4;
+            }
+        }
+    }
+    return commands;
+}
+
+// offscreen layers by layer id
+const canvasLayers = new Map();
+
+// layer or element where to draw
+function canvasTarget(id) {
+    return canvasLayers.get(id) || document.getElementById(id);
+}
+
+function createLayer(el, layerId) {
+    const layer = new OffscreenCanvas(el.width, el.height);
+    layer.owner = el.id;
+    // context is created here with alpha, bitmaps painted later on do not override that
+    layer.getContext("2d").setTransform(el.getContext("2d").getTransform());
+    canvasLayers.set(layerId, layer);
+}
+
+function compositeLayers(el, layers) {
+    const ctx = el.getContext("2d");
+    ctx.save();
+    ctx.setTransform(1, 0, 0, 1, 0, 0);
+    ctx.clearRect(0, 0, el.width, el.height);
+    for(const l of layers) {
+        const layer = canvasLayers.get(l.layer);
+        if(!layer) {
+            errlog(el.id, "Layer not found '" + l.layer + "'");
+            continue;
+        }
+        ctx.globalCompositeOperation = l.operation;
+        ctx.drawImage(layer, 0, 0);
+    }
+    ctx.restore();
+    if(event_notifiers.has("canvas_draw"))
+        socket.send(JSON.stringify({'type': 'event', 'element': el.id, 'event': 'event_notify', 'properties': {'name': "canvas_draw", 'msgid': 0}}));
+}
+
+function handleBinaryMessage(buffer) {
+    const bytes = new Uint32Array(buffer);
     const type = bytes[0];
-    if(type === 0xAAA) {
+    if(type === 0xAAC) {
+        const datalen = bytes[1] * 4;
+        const idLen = bytes[2];
+        const headerLen = bytes[3];
+        const dataOffset = 4 * 4;
+        const headerOffset = bytes[1] + 4;
+        const count = bytes[headerOffset];
+        const as_draw = bytes[headerOffset + 1];
+        const idOffset = (headerLen * 4) + dataOffset + datalen;
+        const words = new Uint16Array(buffer, idOffset, idLen);
+        let id = "";
+        for(let i = 0 ; i < words.length && words[i] > 0; i++)
+            id += String.fromCharCode(words[i]);
+        const element = canvasTarget(id);
+        if(!element) {
+            errlog(id, "Canvas not found '" + id + "'");
+            return;
+        }
+        canvasDraw(element, decodeCanvasCommands(buffer, dataOffset, datalen, count));
+        if((as_draw != 0) && !canvasLayers.has(id) && event_notifiers.has("canvas_draw"))
+            socket.send(JSON.stringify({'type': 'event', 'element': id, 'event': 'event_notify', 'properties': {'name': "canvas_draw", 'msgid': 0}}));
+    } else if(type === 0xAAA) {
         const datalen = bytes[1] * 4;
         const idLen = bytes[2];
         const headerLen = bytes[3];
@@ -367,7 +793,7 @@ function handleBinary(buffer) {
         let id = "";
         for(let i = 0 ; i < words.length && words[i] > 0; i++)
             id += String.fromCharCode(words[i]);
-        const element = document.getElementById(id);
+        const element = canvasTarget(id);
 
         if(!element) {
             errlog(id, "Canvas not found '" + id + "'" + " at: ", idOffset, " len: ", idLen);
@@ -529,7 +955,7 @@ function canvasDraw(element, commands) {
             ctx.scale(commands[cmdpos++], commands[cmdpos++]);
             break;
         case 'drawImage':
-            const image = document.getElementById(commands[cmdpos++]);
+            const image = canvasTarget(commands[cmdpos++]);
             if(!image) {
                 errlog("drawImage", commands[cmdpos - 1] + " image not found");
                 return;
@@ -537,7 +963,7 @@ function canvasDraw(element, commands) {
             ctx.drawImage(image, commands[cmdpos++], commands[cmdpos++]);
             break;
         case 'drawImageRect':
-            const image1 = document.getElementById(commands[cmdpos++]);
+            const image1 = canvasTarget(commands[cmdpos++]);
             if(!image1) {
                 errlog("drawImageRect", commands[cmdpos - 1] + " image not found");
                 return;
@@ -549,7 +975,7 @@ function canvasDraw(element, commands) {
                           commands[cmdpos++]);
             break;
         case 'drawImageClip':
-            const image2 = document.getElementById(commands[cmdpos++]);
+            const image2 = canvasTarget(commands[cmdpos++]);
             if(!image2) {
                 errlog("drawImageClip", commands[cmdpos - 1] + " image not found");
                 return;
@@ -568,6 +994,101 @@ function canvasDraw(element, commands) {
         case 'textBaseline':
             ctx.textBaseline = commands[cmdpos++];
             break;    
+        case 'fillGradient':
+        case 'strokeGradient': {
+            const type = commands[cmdpos++];
+            const next = () => Number(commands[cmdpos++]);
+            let gradient = null;
+            switch(type) {
+            case 'linear': gradient = ctx.createLinearGradient(next(), next(), next(), next()); break;
+            case 'radial': gradient = ctx.createRadialGradient(next(), next(), next(), next(), next(), next()); break;
+            case 'conic': gradient = ctx.createConicGradient(next(), next(), next()); break;
+            default:
+                errlog(cmd, "unknown gradient " + type);
+                return;
+            }
+            const stops = next();
+            for(let i = 0; i < stops; ++i)
+                gradient.addColorStop(next(), commands[cmdpos++]);
+            if(cmd === 'fillGradient')
+                ctx.fillStyle = gradient;
+            else
+                ctx.strokeStyle = gradient;
+            } break;
+        case 'fillPattern':
+        case 'strokePattern': {
+            const patternImage = document.getElementById(commands[cmdpos++]);
+            const repetition = commands[cmdpos++];
+            if(!patternImage) {
+                errlog(cmd, commands[cmdpos - 2] + " image not found");
+                return;
+            }
+            const pattern = ctx.createPattern(patternImage, repetition);
+            if(cmd === 'fillPattern')
+                ctx.fillStyle = pattern;
+            else
+                ctx.strokeStyle = pattern;
+            } break;
+        case 'setLineDash': {
+            const count = Number(commands[cmdpos++]);
+            ctx.setLineDash(commands.slice(cmdpos, cmdpos + count).map(Number));
+            cmdpos += count;
+            } break;
+        case 'lineDashOffset':
+            ctx.lineDashOffset = commands[cmdpos++];
+            break;
+        case 'lineCap':
+            ctx.lineCap = commands[cmdpos++];
+            break;
+        case 'lineJoin':
+            ctx.lineJoin = commands[cmdpos++];
+            break;
+        case 'miterLimit':
+            ctx.miterLimit = commands[cmdpos++];
+            break;
+        case 'globalAlpha':
+            ctx.globalAlpha = commands[cmdpos++];
+            break;
+        case 'globalCompositeOperation':
+            ctx.globalCompositeOperation = commands[cmdpos++];
+            break;
+        case 'shadowColor':
+            ctx.shadowColor = commands[cmdpos++];
+            break;
+        case 'shadowBlur':
+            ctx.shadowBlur = commands[cmdpos++];
+            break;
+        case 'shadowOffset':
+            ctx.shadowOffsetX = commands[cmdpos++];
+            ctx.shadowOffsetY = commands[cmdpos++];
+            break;
+        case 'clip':
+            ctx.clip();
+            break;
+        case 'setTransform':
+            ctx.setTransform(commands[cmdpos++], commands[cmdpos++], commands[cmdpos++], commands[cmdpos++], commands[cmdpos++], commands[cmdpos++]);
+            break;
+        case 'transform':
+            ctx.transform(commands[cmdpos++], commands[cmdpos++], commands[cmdpos++], commands[cmdpos++], commands[cmdpos++], commands[cmdpos++]);
+            break;
+        case 'resetTransform':
+            ctx.resetTransform();
+            break;
+        case 'roundRect': {
+            const x = commands[cmdpos++], y = commands[cmdpos++], w = commands[cmdpos++], h = commands[cmdpos++];
+            const count = Number(commands[cmdpos++]);
+            ctx.roundRect(x, y, w, h, commands.slice(cmdpos, cmdpos + count).map(Number));
+            cmdpos += count;
+            } break;
+        case 'filter':
+            ctx.filter = commands[cmdpos++];
+            break;
+        case 'imageSmoothingEnabled':
+            ctx.imageSmoothingEnabled = String(commands[cmdpos++]) === 'true';
+            break;
+        case 'imageSmoothingQuality':
+            ctx.imageSmoothingQuality = commands[cmdpos++];
+            break;
         default:
             errlog(cmd, "is not supported command:" + cmdpos + ", in commands:" + commands);
             return;
@@ -619,6 +1140,9 @@ function handleJsonCommand(msg) {
             return;
         case 'extension':
             return;
+        case 'animation_frame':
+            window.requestAnimationFrame(timestamp => socket.send(JSON.stringify({'type': 'animation_frame', 'timestamp': timestamp})));
+            return;
         case 'nil':
             return;
         case 'exit_request':
@@ -661,7 +1185,7 @@ function handleJsonCommand(msg) {
             window.open(msg.url, msg.view && msg.view.length > 0 ? msg.view : '_blank');
             return;
         case 'create':
-            if(msg.element == undefined || msg.element.length == 0) {
+            if(!msg.elements && (msg.element == undefined || msg.element.length == 0)) {
                 createElement(null, msg.html_element, msg.new_id);
                 return;
             }
@@ -720,8 +1244,12 @@ function handleJsonCommand(msg) {
                 el[msg.attribute] = val;              //...and this in some :-D
                 break;
             case 'create':
-                log("create", el, msg.html_element, msg.new_id);
-                createElement(el, msg.html_element, msg.new_id);
+                if(msg.elements) {
+                    createElements(el, msg.elements, msg.position);
+                } else {
+                    log("create", el, msg.html_element, msg.new_id);
+                    createElement(el, msg.html_element, msg.new_id);
+                }
                 break;
             case 'remove':
                 removeElement(el, msg.remove);
@@ -744,6 +1272,37 @@ function handleJsonCommand(msg) {
             case 'remove_style':
                 el.style.removeProperty(msg.style);
                 break;
+            case 'animate':
+                animate(el, msg.name, msg.keyframes, msg.options);
+                break;
+            case 'canvas_size': {
+                const ratio = window.devicePixelRatio || 1;
+                const w = msg.rect[2], h = msg.rect[3];
+                el.style.width = w + 'px';
+                el.style.height = h + 'px';
+                el.width = Math.round(w * ratio);
+                el.height = Math.round(h * ratio);
+                el.getContext("2d").setTransform(ratio, 0, 0, ratio, 0, 0);
+                for(const layer of canvasLayers.values()) {
+                    if(layer.owner === el.id) {
+                        layer.width = el.width;
+                        layer.height = el.height;
+                        layer.getContext("2d").setTransform(ratio, 0, 0, ratio, 0, 0);
+                    }
+                }
+                } break;
+            case 'create_layer':
+                createLayer(el, msg.name);
+                break;
+            case 'remove_layer':
+                canvasLayers.delete(msg.name);
+                break;
+            case 'composite':
+                compositeLayers(el, msg.layers);
+                break;
+            case 'animation':
+                controlAnimation(msg.name, msg.value);
+                break;
             case 'tag_name':
                 sendCollection(msg.element, msg.query_id, msg.query, function(name){return el.getElementsByTagName(name);});
                 return;        
@@ -770,7 +1329,11 @@ socket.onmessage =
             return;
         }
         const msg = JSON.parse(event.data);
-
+        // e.g. queries and canvas commands must see tiles painted before them
+        if(binaryPending > 0) {
+            enqueueMessage(() => handleJson(msg));
+            return;
+        }
         handleJson(msg);
     } catch(error) {
         catchLog(error);
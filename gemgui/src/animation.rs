@@ -0,0 +1,351 @@
+use std::time::Duration;
+
+use crate::GemGuiError;
+use crate::JSMap;
+use crate::JSMessageTx;
+use crate::JSType;
+use crate::Result;
+use crate::element::Element;
+use crate::event::Event;
+
+/// Animation playback direction
+pub enum Direction {
+    /// Run forwards
+    Normal,
+    /// Run backwards
+    Reverse,
+    /// Switch direction after each iteration, start forwards
+    Alternate,
+    /// Switch direction after each iteration, start backwards
+    AlternateReverse,
+}
+
+impl Direction {
+    fn as_str(&self) -> &str {
+        match self {
+            Self::Normal => "normal",
+            Self::Reverse => "reverse",
+            Self::Alternate => "alternate",
+            Self::AlternateReverse => "alternate-reverse",
+        }
+    }
+}
+
+/// How animation styles are applied when it is not playing
+pub enum Fill {
+    /// Styles are not applied
+    None,
+    /// Last keyframe is retained after the end
+    Forwards,
+    /// First keyframe is applied during the delay
+    Backwards,
+    /// Both forwards and backwards
+    Both,
+    /// Browser default
+    Auto,
+}
+
+impl Fill {
+    fn as_str(&self) -> &str {
+        match self {
+            Self::None => "none",
+            Self::Forwards => "forwards",
+            Self::Backwards => "backwards",
+            Self::Both => "both",
+            Self::Auto => "auto",
+        }
+    }
+}
+
+/// Animation keyframe
+///
+/// Style names are as in Javascript, e.g. "backgroundColor".
+///
+/// # Example
+///
+/// ```
+/// # use gemgui::animation::Keyframe;
+/// let keyframes = [
+///     Keyframe::new().style("opacity", "0"),
+///     Keyframe::new().style("opacity", "1").offset(0.2),
+///     Keyframe::new().style("opacity", "0")];
+/// ```
+#[derive(Default, Clone)]
+pub struct Keyframe {
+    values: JSMap,
+}
+
+impl Keyframe {
+
+    /// New keyframe
+    pub fn new() -> Keyframe {
+        Keyframe::default()
+    }
+
+    /// Set a style value
+    ///
+    /// # Arguments
+    ///
+    /// `style` - style name
+    ///
+    /// `value` - style value
+    pub fn style(mut self, style: &str, value: &str) -> Keyframe {
+        self.values.insert(style.to_string(), JSType::from(value));
+        self
+    }
+
+    /// Set keyframe position, if not set keyframes are evenly spaced
+    ///
+    /// # Arguments
+    ///
+    /// `offset` - position in between 0.0 and 1.0
+    pub fn offset(mut self, offset: f32) -> Keyframe {
+        self.values.insert("offset".to_string(), JSType::from(offset));
+        self
+    }
+
+    /// Set easing function used until the next keyframe
+    ///
+    /// # Arguments
+    ///
+    /// `easing` - CSS easing function, e.g. "ease-in"
+    pub fn easing(mut self, easing: &str) -> Keyframe {
+        self.values.insert("easing".to_string(), JSType::from(easing));
+        self
+    }
+
+    pub (crate) fn value(&self) -> JSType {
+        JSType::from(self.values.clone())
+    }
+}
+
+/// Animation timing options
+///
+/// # Example
+///
+/// ```
+/// # use std::time::Duration;
+/// # use gemgui::animation::{AnimationOptions, Direction};
+/// let options = AnimationOptions::new(Duration::from_millis(500))
+///     .direction(Direction::Alternate)
+///     .infinite();
+/// ```
+#[derive(Clone)]
+pub struct AnimationOptions {
+    values: JSMap,
+}
+
+impl AnimationOptions {
+
+    /// New options
+    ///
+    /// # Arguments
+    ///
+    /// `duration` - duration of a single iteration
+    pub fn new(duration: Duration) -> AnimationOptions {
+        let mut values = JSMap::new();
+        values.insert("duration".to_string(), JSType::from(duration.as_millis() as u64));
+        AnimationOptions{values}
+    }
+
+    /// Delay before start
+    ///
+    /// # Arguments
+    ///
+    /// `delay` - delay
+    pub fn delay(mut self, delay: Duration) -> AnimationOptions {
+        self.values.insert("delay".to_string(), JSType::from(delay.as_millis() as u64));
+        self
+    }
+
+    /// Number of iterations, default is 1
+    ///
+    /// # Arguments
+    ///
+    /// `iterations` - iterations, can be fractional
+    pub fn iterations(mut self, iterations: f32) -> AnimationOptions {
+        self.values.insert("iterations".to_string(), JSType::from(iterations));
+        self
+    }
+
+    /// Repeat forever
+    ///
+    /// [Animation::finished] does not return until the animation is cancelled.
+    pub fn infinite(mut self) -> AnimationOptions {
+        // Json has no infinity
+        self.values.insert("iterations".to_string(), JSType::from("Infinity"));
+        self
+    }
+
+    /// Set playback direction
+    ///
+    /// # Arguments
+    ///
+    /// `direction` - direction
+    pub fn direction(mut self, direction: Direction) -> AnimationOptions {
+        self.values.insert("direction".to_string(), JSType::from(direction.as_str()));
+        self
+    }
+
+    /// Set fill mode
+    ///
+    /// # Arguments
+    ///
+    /// `fill` - fill mode
+    pub fn fill(mut self, fill: Fill) -> AnimationOptions {
+        self.values.insert("fill".to_string(), JSType::from(fill.as_str()));
+        self
+    }
+
+    /// Set easing function applied over an iteration
+    ///
+    /// # Arguments
+    ///
+    /// `easing` - CSS easing function, e.g. "ease-in-out"
+    pub fn easing(mut self, easing: &str) -> AnimationOptions {
+        self.values.insert("easing".to_string(), JSType::from(easing));
+        self
+    }
+
+    pub (crate) fn values(&self) -> &JSMap {
+        &self.values
+    }
+}
+
+/// Handle to an animation running in UI, see [Element::animate]
+///
+/// Dropping the handle does not stop the animation, but it cannot be controlled anymore.
+pub struct Animation {
+    element: Element,
+    id: String,
+}
+
+impl Animation {
+
+    pub (crate) fn new(element: Element, id: String) -> Animation {
+        Animation{element, id}
+    }
+
+    /// Animated element
+    pub fn element(&self) -> &Element {
+        &self.element
+    }
+
+    /// Start or resume the animation
+    pub fn play(&self) {
+        self.control("play");
+    }
+
+    /// Pause the animation
+    pub fn pause(&self) {
+        self.control("pause");
+    }
+
+    /// Reverse playback direction
+    pub fn reverse(&self) {
+        self.control("reverse");
+    }
+
+    /// Cancel the animation and remove its effects
+    pub fn cancel(&self) {
+        self.control("cancel");
+    }
+
+    /// Seek to the end of the animation
+    pub fn finish(&self) {
+        self.control("finish");
+    }
+
+    /// Wait the animation to finish
+    ///
+    /// An [infinite](AnimationOptions::infinite) animation does not finish by itself, so this
+    /// returns only after the animation is cancelled, also when its element is removed.
+    ///
+    /// # Return
+    ///
+    /// Error if animation is cancelled, also when it was cancelled (and not played again) before this call
+    pub async fn finished(&self) -> Result<()> {
+        let value = self.element.ui().query(self.element.id(), "animation_finished", &vec![self.id.clone()]).await?;
+        match value.as_bool() {
+            Some(true) => Ok(()),
+            _ => GemGuiError::error(format!("Animation cancelled {value}")),
+        }
+    }
+
+    fn control(&self, command: &str) {
+        let msg =  JSMessageTx {
+            element: self.element.id(),
+            _type: "animation",
+            name: Some(&self.id),
+            value: Some(command),
+            ..Default::default()
+        };
+        self.element.send(msg);
+    }
+}
+
+impl Drop for Animation {
+    fn drop(&mut self) {
+        self.control("release");
+    }
+}
+
+/// CSS transition end, see [Element::subscribe_transition_end]
+pub struct TransitionEnd {
+    event: Event,
+}
+
+impl TransitionEnd {
+
+    pub (crate) fn new(event: Event) -> TransitionEnd {
+        TransitionEnd{event}
+    }
+
+    /// Element emit the event
+    pub fn element(&self) -> Element {
+        self.event.element()
+    }
+
+    /// Name of transitioned style
+    pub fn property_name(&self) -> &str {
+        self.event.property_str("propertyName").unwrap_or("")
+    }
+
+    /// Duration of the transition
+    pub fn elapsed(&self) -> Duration {
+        elapsed(&self.event)
+    }
+}
+
+/// CSS animation end, see [Element::subscribe_animation_end]
+pub struct AnimationEnd {
+    event: Event,
+}
+
+impl AnimationEnd {
+
+    pub (crate) fn new(event: Event) -> AnimationEnd {
+        AnimationEnd{event}
+    }
+
+    /// Element emit the event
+    pub fn element(&self) -> Element {
+        self.event.element()
+    }
+
+    /// Name of the CSS animation
+    pub fn animation_name(&self) -> &str {
+        self.event.property_str("animationName").unwrap_or("")
+    }
+
+    /// Duration of the animation
+    pub fn elapsed(&self) -> Duration {
+        elapsed(&self.event)
+    }
+}
+
+// elapsedTime is in seconds
+fn elapsed(event: &Event) -> Duration {
+    let secs = event.property_str("elapsedTime").and_then(|s| s.parse::<f64>().ok()).unwrap_or(0.0);
+    Duration::from_secs_f64(secs.max(0.0))
+}
@@ -5,6 +5,12 @@ use futures::Future;
 use crate::GemGuiError;
 use crate::Result;
 use crate::Rect;
+use crate::animation::Animation;
+use crate::animation::AnimationEnd;
+use crate::animation::AnimationOptions;
+use crate::animation::Keyframe;
+use crate::animation::TransitionEnd;
+use crate::event::ANIMATION_END;
 use crate::event::Event;
 use crate::event::MOUSE_CLICK;
 use crate::event::MOUSE_DBLCLICK;
@@ -12,6 +18,8 @@ use crate::event::MOUSE_DOWN;
 use crate::event::MOUSE_MOVE;
 use crate::event::MOUSE_UP;
 use crate::event::Properties;
+use crate::event::REMOVED;
+use crate::event::TRANSITION_END;
 use crate::msgsender::MsgSender;
 use crate::ui_data::UiData;
 use crate::ui_data::UiDataRef;
@@ -37,6 +45,9 @@ pub type Elements = Vec<Element>;
 /// Convenience for a properties parameter to express no properties applied.
 pub static NONE_PROPERTIES: Option<&[&str]> = None;
 
+// internal subscription that keeps element known until UI has removed it
+static TRACKED: &str = "tracked";
+
 
 /// HTML Element representation
 pub struct Element {
@@ -272,6 +283,83 @@ impl Element {
     self.subscribe_throttled(mouse.as_str(), UiData::as_sync_fn(async_func), Some(&properties), Duration::from_millis(0u64))
     }
 
+    /// Subscribe CSS transition end events
+    /// 
+    /// # Arguments
+    /// 
+    /// `callback` - Callback on transition end
+    /// 
+    /// # Callback
+    /// 
+    /// `UiRef`- Reference to UI
+    /// 
+    /// `TransitionEnd` - Transition information
+    pub fn subscribe_transition_end<CB>(&self, mut callback: CB)
+    where CB: FnMut(UiRef, TransitionEnd) + Send + 'static {
+        let properties = vec!("propertyName", "elapsedTime");
+        self.subscribe_properties(TRANSITION_END, move |ui, ev| callback(ui, TransitionEnd::new(ev)), &properties);
+    }
+
+    /// Subscribe CSS animation end events
+    /// 
+    /// # Arguments
+    /// 
+    /// `callback` - Callback on animation end
+    /// 
+    /// # Callback
+    /// 
+    /// `UiRef`- Reference to UI
+    /// 
+    /// `AnimationEnd` - Animation information
+    pub fn subscribe_animation_end<CB>(&self, mut callback: CB)
+    where CB: FnMut(UiRef, AnimationEnd) + Send + 'static {
+        let properties = vec!("animationName", "elapsedTime");
+        self.subscribe_properties(ANIMATION_END, move |ui, ev| callback(ui, AnimationEnd::new(ev)), &properties);
+    }
+
+    /// Animate element using Web Animations API
+    /// 
+    /// Interpolation is done by the browser. 
+    /// 
+    /// # Example
+    /// 
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use gemgui::element::Element;
+    /// # use gemgui::animation::{Keyframe, AnimationOptions};
+    /// async fn fade_out(element: Element) {
+    ///     let animation = element.animate(&[
+    ///         Keyframe::new().style("opacity", "1"),
+    ///         Keyframe::new().style("opacity", "0")],
+    ///         &AnimationOptions::new(Duration::from_secs(1)));
+    ///     animation.finished().await.unwrap();
+    /// }
+    /// ```
+    /// 
+    /// # Arguments
+    /// 
+    /// `keyframes` - animation keyframes
+    /// 
+    /// `options` - animation timing
+    /// 
+    /// # Return
+    /// 
+    /// Animation handle
+    pub fn animate(&self, keyframes: &[Keyframe], options: &AnimationOptions) -> Animation {
+        let id = UiData::random("animation");
+        let keyframes = keyframes.iter().map(|k| k.value()).collect();
+        let msg =  JSMessageTx {
+            element: self.id(),
+            _type: "animate",
+            name: Some(&id),
+            keyframes: Some(&keyframes),
+            options: Some(options.values()),
+            ..Default::default()
+        };
+        self.send(msg);
+        Animation::new(self.clone(), id)
+    }
+
     /// Get HTML content of the element
     /// 
     /// # Return
@@ -288,6 +376,8 @@ impl Element {
 
     /// Set inner HTML of the element
     /// 
+    /// Subscriptions of the replaced child elements are dropped.
+    /// 
     /// # Arguments
     /// 
     /// `html`- HTML applied to the element
@@ -469,6 +559,9 @@ impl Element {
     
 
     /// Remove this element
+    /// 
+    /// Subscriptions of the element and its child elements are dropped once the UI
+    /// has removed them, see [REMOVED](crate::event::REMOVED).
     pub fn remove(&self) {
         let msg =  JSMessageTx {
             element: self.id(),
@@ -476,9 +569,30 @@ impl Element {
             remove: Some(self.id()),
             ..Default::default()
         };
+        UiData::detach_element(&self.ui, self.id());
+        self.send(msg);
+    }
+
+    // Makes UI tell when this element is removed without taking the REMOVED subscription
+    pub (crate) fn track_removal(&self) {
+        UiData::add_subscription(&self.ui, &self.id, TRACKED, |_, _| {});
+        let properties = vec!(JSType::from(""));
+        let msg =  JSMessageTx {
+            element: self.id(),
+            _type: "event",
+            event: Some(REMOVED),
+            properties: Some(&properties),
+            throttle: Some("0"),
+            ..Default::default()
+        };
         self.send(msg);
     }
 
+    // False once this element is removed, see track_removal
+    pub (crate) fn is_tracked(&self) -> bool {
+        UiData::contains_element(&self.ui, &self.id)
+    }
+
     /// Get element type
     /// 
     /// # Return
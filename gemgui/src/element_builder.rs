@@ -0,0 +1,292 @@
+use std::collections::HashSet;
+use std::time::Duration;
+
+use futures::Future;
+
+use crate::GemGuiError;
+use crate::JSMap;
+use crate::JSType;
+use crate::Result;
+use crate::element::Element;
+use crate::element::Elements;
+use crate::event::Event;
+use crate::ui::SubscribeCallback;
+use crate::ui_data::ROOT_ID;
+use crate::ui_data::UiData;
+use crate::ui_data::UiDataRef;
+use crate::ui_ref::UiRef;
+
+/// Where a built element is inserted
+pub enum Insert<'a> {
+    /// As the last child of the element
+    Append(&'a Element),
+    /// As a previous sibling of the element
+    Before(&'a Element),
+    /// As a next sibling of the element
+    After(&'a Element),
+}
+
+impl Insert<'_> {
+    pub (crate) fn element(&self) -> &Element {
+        match self {
+            Self::Append(el) | Self::Before(el) | Self::After(el) => el,
+        }
+    }
+
+    pub (crate) fn value(&self) -> &str {
+        match self {
+            Self::Append(_) => "append",
+            Self::Before(_) => "before",
+            Self::After(_) => "after",
+        }
+    }
+}
+
+struct Subscription {
+    name: String,
+    callback: Box<SubscribeCallback>,
+    properties: Vec<JSType>,
+    throttle: Duration,
+}
+
+#[derive(serde::Serialize, Debug, Default)]
+struct ElementTree {
+    tag: String,
+    id: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    classes: Vec<String>,
+    #[serde(skip_serializing_if = "JSMap::is_empty")]
+    attributes: JSMap,
+    #[serde(skip_serializing_if = "JSMap::is_empty")]
+    styles: JSMap,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    events: Vec<JSType>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    children: Vec<JSType>,
+}
+
+/// Element description that is created in UI with a single request
+/// Use Builder pattern to describe the element
+///
+/// # Example
+///
+/// ```no_run
+/// # use gemgui::ui_ref::UiRef;
+/// # use gemgui::ui::Ui;
+/// # use gemgui::element_builder::{ElementBuilder, Insert};
+/// async fn some_function(ui: UiRef) {
+///     let list = ElementBuilder::new("ul")
+///         .class("menu")
+///         .child(ElementBuilder::new("li").text("Foo"))
+///         .child(ElementBuilder::new("li").text("Bar")
+///             .subscribe("click", |_, _| println!("Bar clicked")));
+///     let elements = ui.build_element_async(list, Insert::Append(&ui.root())).await.unwrap();
+///     assert_eq!(elements.len(), 3);
+/// }
+/// ```
+pub struct ElementBuilder {
+    tag: String,
+    id: Option<String>,
+    classes: Vec<String>,
+    attributes: Vec<(String, String)>,
+    styles: Vec<(String, String)>,
+    text: Option<String>,
+    children: Vec<ElementBuilder>,
+    subscriptions: Vec<Subscription>,
+}
+
+impl ElementBuilder {
+
+    /// Describe a new element
+    ///
+    /// # Arguments
+    ///
+    /// `html_element` - HTML tag name
+    ///
+    /// # Return
+    ///
+    /// ElementBuilder
+    pub fn new(html_element: &str) -> ElementBuilder {
+        ElementBuilder {
+            tag: html_element.to_string(),
+            id: None,
+            classes: Vec::new(),
+            attributes: Vec::new(),
+            styles: Vec::new(),
+            text: None,
+            children: Vec::new(),
+            subscriptions: Vec::new(),
+        }
+    }
+
+    /// Set element id, if not set a random id is generated
+    ///
+    /// # Arguments
+    ///
+    /// `id` - id of element - is expected to be unique in the application context
+    pub fn id(mut self, id: &str) -> ElementBuilder {
+        self.id = Some(id.to_string());
+        self
+    }
+
+    /// Add a class
+    ///
+    /// # Arguments
+    ///
+    /// `class_name` - class name
+    pub fn class(mut self, class_name: &str) -> ElementBuilder {
+        self.classes.push(class_name.to_string());
+        self
+    }
+
+    /// Set an attribute value
+    ///
+    /// # Arguments
+    ///
+    /// `attr` - attribute name
+    ///
+    /// `value` - attribute value
+    pub fn attribute(mut self, attr: &str, value: &str) -> ElementBuilder {
+        self.attributes.push((attr.to_string(), value.to_string()));
+        self
+    }
+
+    /// Set a style value
+    ///
+    /// # Arguments
+    ///
+    /// `style` - style name
+    ///
+    /// `value` - style value
+    pub fn style(mut self, style: &str, value: &str) -> ElementBuilder {
+        self.styles.push((style.to_string(), value.to_string()));
+        self
+    }
+
+    /// Set text content, children are added after the text
+    ///
+    /// # Arguments
+    ///
+    /// `text` - text content
+    pub fn text(mut self, text: &str) -> ElementBuilder {
+        self.text = Some(text.to_string());
+        self
+    }
+
+    /// Add a child element
+    ///
+    /// # Arguments
+    ///
+    /// `child` - child element description
+    pub fn child(mut self, child: ElementBuilder) -> ElementBuilder {
+        self.children.push(child);
+        self
+    }
+
+    /// Subscribe event
+    ///
+    /// See [Element::subscribe_throttled]
+    pub fn subscribe_throttled<CB, Str>(mut self, name: &str, callback: CB, properties: Option<&[Str]>, throttle: Duration) -> ElementBuilder
+    where
+        Str: Into<String> + std::clone::Clone,
+        CB: FnMut(UiRef, Event) + Send + 'static {
+        let properties =  match properties {
+            Some(p) => p.iter().cloned().map(|v| { JSType::String(v.into())}).collect(),
+            None =>  vec!(JSType::from("")),
+        };
+        self.subscriptions.push(Subscription {
+            name: name.to_string(),
+            callback: Box::new(callback),
+            properties,
+            throttle});
+        self
+    }
+
+    /// Subscribe event
+    ///
+    /// See [Element::subscribe_properties]
+    pub fn subscribe_properties<CB, Str>(self, name: &str, callback: CB, properties: &[Str]) -> ElementBuilder
+    where  Str: Into<String> + std::clone::Clone,
+        CB: FnMut(UiRef, Event) + Send + 'static {
+        self.subscribe_throttled(name, callback, Some(properties), Duration::from_millis(0u64))
+    }
+
+    /// Subscribe event
+    ///
+    /// See [Element::subscribe]
+    pub fn subscribe<CB>(self, name: &str, callback: CB) -> ElementBuilder
+    where CB: FnMut(UiRef, Event) + Send + 'static {
+        self.subscribe_throttled(name, callback, crate::element::NONE_PROPERTIES, Duration::from_millis(0u64))
+    }
+
+    /// See [subscribe](Self::subscribe)
+    pub fn subscribe_async<CB, Fut>(self, name: &str, async_func: CB) -> ElementBuilder
+    where CB: FnOnce(UiRef, Event)-> Fut + Send + Clone + 'static,
+    Fut: Future<Output =  ()> + Send +  'static {
+        self.subscribe_throttled(name, UiData::as_sync_fn(async_func), crate::element::NONE_PROPERTIES, Duration::from_millis(0u64))
+    }
+
+    // Reserve ids and check that they are unique
+    fn collect_ids(&mut self, ui: &UiDataRef, ids: &mut HashSet<String>) -> Result<()> {
+        let id = match &self.id {
+            Some(id) => id.clone(),
+            None => loop {
+                let id = UiData::random_element_id(ui);
+                if ! ids.contains(&id) {
+                    break id;
+                }
+            }
+        };
+        if id == ROOT_ID || ids.contains(&id) || UiData::contains_element(ui, &id) {
+            return GemGuiError::error(format!("Bad id {id}"));
+        }
+        ids.insert(id.clone());
+        self.id = Some(id);
+        for child in self.children.iter_mut() {
+            child.collect_ids(ui, ids)?;
+        }
+        Ok(())
+    }
+
+    // Apply subscriptions and compose tree, elements are listed in depth first order
+    fn compose(self, ui: &UiDataRef, elements: &mut Elements) -> JSType {
+        let id = self.id.unwrap();
+        elements.push(UiData::element(ui, &id));
+
+        let mut events = Vec::new();
+        for subscription in self.subscriptions {
+            // "created" is listed as well, UI notifies it once element tree is inserted
+            let event = serde_json::json!({
+                "event": subscription.name,
+                "properties": subscription.properties,
+                "throttle": subscription.throttle.as_millis().to_string(),
+            });
+            events.push(event);
+            UiData::add_subscription(ui, &id, subscription.name, subscription.callback);
+        }
+
+        let children = self.children.into_iter().map(|child| child.compose(ui, elements)).collect();
+
+        let tree = ElementTree {
+            tag: self.tag,
+            id,
+            classes: self.classes,
+            attributes: self.attributes.into_iter().map(|(k, v)| (k, JSType::from(v))).collect(),
+            styles: self.styles.into_iter().map(|(k, v)| (k, JSType::from(v))).collect(),
+            text: self.text,
+            events,
+            children,
+        };
+        serde_json::to_value(tree).unwrap()
+    }
+
+    pub (crate) fn build(mut self, ui: &UiDataRef) -> Result<(JSType, Elements)> {
+        let mut ids = HashSet::new();
+        self.collect_ids(ui, &mut ids)?;
+        let mut elements = Elements::new();
+        let tree = self.compose(ui, &mut elements);
+        Ok((tree, elements))
+    }
+}
@@ -38,6 +38,12 @@ pub static BLUR: &str = "blur";
 pub static FOCUS_IN: &str = "focusin";
 /// Focus Out
 pub static FOCUS_OUT: &str = "focusout";
+/// CSS transition completed, see [Element::subscribe_transition_end](`crate::element::Element::subscribe_transition_end`)
+pub static TRANSITION_END: &str = "transitionend";
+/// CSS animation completed, see [Element::subscribe_animation_end](`crate::element::Element::subscribe_animation_end`)
+pub static ANIMATION_END: &str = "animationend";
+/// Element is removed from UI, called just before element's subscriptions are dropped
+pub static REMOVED: &str = "removed";
 
 
 /// Ui Event
@@ -28,7 +28,7 @@
 //! # use gemgui::GemGuiError;
 //! # use gemgui::Result;
 //! # use gemgui::ui::{Gui, Ui};
-//! # const RESOURCES: &[(&'static str, &'static str)] = &[]; 
+//! # const RESOURCES: &[gemgui::respack::PackedResource] = &[]; 
 //! ##[tokio::main]
 //! async fn main() -> Result<()> { 
 //!    let fm = gemgui::filemap_from(RESOURCES);
@@ -43,7 +43,7 @@
 //! # use gemgui::GemGuiError;
 //! # use gemgui::Result;
 //! # use gemgui::ui_ref::UiRef;
-//! # const RESOURCES: &[(&'static str, &'static str)] = &[]; 
+//! # const RESOURCES: &[gemgui::respack::PackedResource] = &[]; 
 //! fn main() -> Result<()> { 
 //!     let fm = gemgui::filemap_from(RESOURCES);
 //!     gemgui::application(fm, "hello.html", 12345,
@@ -64,7 +64,7 @@
 //! # use gemgui::Result;
 //! # use gemgui::ui_ref::UiRef;
 //! # use gemgui::window::Menu;
-//! # const RESOURCES: &[(&'static str, &'static str)] = &[]; 
+//! # const RESOURCES: &[gemgui::respack::PackedResource] = &[]; 
 //! fn main() -> Result<()> { 
 //!     let fm = gemgui::filemap_from(RESOURCES);
 //!     let file_menu = Menu::new().
@@ -115,6 +115,10 @@
 pub mod ui;
 /// Element
 pub mod element;
+/// Element builder
+pub mod element_builder;
+/// Element animations
+pub mod animation;
 /// Event
 pub mod event;
 /// Ui reference
@@ -130,14 +134,19 @@ pub mod respack;
 mod msgsender;
 mod ui_data;
 
+// generated resources refer to crate by name
+extern crate self as gemgui;
 
 
+
+use std::borrow::Cow;
 use std::error::Error;
 use std::fmt;
+use std::io::Read;
 use std::path::Path;
 use std::str::FromStr;
-use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use std::collections::HashMap;
 
 use futures::Future;
@@ -145,10 +154,122 @@ use ui::Gui;
 use ui_ref::UiRef;
 use window::Menu;
 
+use crate::respack::PackedResource;
 use crate::ui::Ui;
 
 /// Resource file map
-pub type Filemap = HashMap<String, Vec<u8>>;
+pub type Filemap = HashMap<String, Resource>;
+
+/// Resource file
+///
+/// Packed resources are borrowed from the binary, and compressed ones
+/// are decompressed on their first use.
+#[derive(Clone)]
+pub struct Resource {
+    data: Cow<'static, [u8]>,
+    encoding: Option<&'static str>,
+    etag: OnceLock<String>,
+    decoded: OnceLock<std::result::Result<Vec<u8>, String>>,
+    modified: Option<SystemTime>,
+}
+
+impl Resource {
+    /// New resource
+    ///
+    /// # Arguments
+    ///
+    /// `data` - file content
+    pub fn new(data: Vec<u8>) -> Resource {
+        Resource {
+            data: Cow::Owned(data),
+            encoding: None,
+            etag: OnceLock::new(),
+            decoded: OnceLock::new(),
+            modified: None,
+        }
+    }
+
+    /// Set modification time, served as Last-Modified
+    ///
+    /// # Arguments
+    ///
+    /// `modified` - modification time
+    pub fn with_modified(mut self, modified: SystemTime) -> Resource {
+        self.modified = Some(modified);
+        self
+    }
+
+    /// Modification time, if known
+    pub fn modified(&self) -> Option<SystemTime> {
+        self.modified
+    }
+
+    /// File content
+    ///
+    /// # Return
+    ///
+    /// content, decompressed if stored compressed, or error if stored data cannot be decompressed
+    pub fn content(&self) -> Result<&[u8]> {
+        let Some(encoding) = self.encoding else {
+            return Ok(&self.data);
+        };
+        match self.decoded.get_or_init(|| decode(encoding, &self.data)) {
+            Ok(decoded) => Ok(decoded),
+            Err(e) => GemGuiError::error(format!("Resource decode error: {e}")),
+        }
+    }
+
+    /// Hash of the content
+    ///
+    /// Packed resources are hashed at build time, others on first use.
+    pub fn etag(&self) -> &str {
+        // only packed resources are encoded and they have a hash
+        self.etag.get_or_init(|| respack::content_hash(self.content().unwrap_or(&self.data)))
+    }
+
+    // stored data if encoded, e.g. ("gzip", data)
+    pub (crate) fn encoded(&self) -> Option<(&'static str, &[u8])> {
+        self.encoding.map(|encoding| (encoding, &self.data[..]))
+    }
+}
+
+fn decode(encoding: &str, data: &[u8]) -> std::result::Result<Vec<u8>, String> {
+    let mut decoded = Vec::new();
+    let result = match encoding {
+        "gzip" => flate2::read::GzDecoder::new(data).read_to_end(&mut decoded),
+        "br" => brotli::Decompressor::new(data, 4096).read_to_end(&mut decoded),
+        _ => return Err(format!("unsupported encoding {encoding:#?}")),
+    };
+    result.map(|_| decoded).map_err(|e| e.to_string())
+}
+
+impl fmt::Debug for Resource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Resource")
+            .field("len", &self.data.len())
+            .field("encoding", &self.encoding)
+            .field("modified", &self.modified)
+            .finish()
+    }
+}
+
+impl From<Vec<u8>> for Resource {
+    fn from(data: Vec<u8>) -> Resource {
+        Resource::new(data)
+    }
+}
+
+impl From<&PackedResource> for Resource {
+    fn from(packed: &PackedResource) -> Resource {
+        Resource {
+            data: Cow::Borrowed(packed.data()),
+            encoding: packed.encoding(),
+            etag: OnceLock::from(packed.etag().to_string()),
+            decoded: OnceLock::new(),
+            modified: packed.modified().map(|secs| UNIX_EPOCH + Duration::from_secs(secs)),
+        }
+    }
+}
 
 type JSType = serde_json::Value;
 type JSMap = serde_json::Map<String, JSType>;
@@ -289,21 +410,22 @@ macro_rules! filemap_from_resources {
 
 /// Read  a filemap from applied resources
 /// 
+/// Resources are not copied nor decompressed here, but on their use.
+/// 
 /// # Arguments
 /// 
 /// `resource_map`- Defines static resources
 /// 
 /// # Return
 /// Filemap
-pub fn filemap_from(resource_map: &[(&'static str, &'static str)]) -> Filemap {
+pub fn filemap_from(resource_map: &[PackedResource]) -> Filemap {
     let mut filemap = Filemap::new();
     for resource in resource_map {
-        let res = base64::decode(resource.1).unwrap();
-        let key =  resource.0.to_string();
+        let key =  resource.name().to_string();
         if filemap.contains_key(&key) {
             eprintln!("Warning: {:#?} already in resources", &key);
         }
-        filemap.insert(key, res);
+        filemap.insert(key, resource.into());
     }
     filemap
     }
@@ -329,6 +451,10 @@ struct JSMessageTx<'a> {
     #[serde(skip_serializing_if = "Option::is_none")]
     new_id: Option<&'a str>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    elements: Option<&'a JSType>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    position: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     query_id: Option<&'a str>,
     #[serde(skip_serializing_if = "Option::is_none")]
     query: Option<&'a str>,
@@ -355,6 +481,12 @@ struct JSMessageTx<'a> {
     #[serde(skip_serializing_if = "Option::is_none")]
     batches: Option<&'a Vec<JSType>>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    keyframes: Option<&'a Vec<JSType>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    options: Option<&'a JSMap>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    layers: Option<&'a Vec<JSType>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     add: Option<bool>,  
     #[serde(skip_serializing_if = "Option::is_none")]
     name: Option<&'a str>,
@@ -497,7 +629,12 @@ pub fn filemap_from_dir<DirName>(path: DirName) -> std::io::Result<Filemap>
         if file.file_type()?.is_file() {
             let contents = std::fs::read(file.path())?;
             let name = file.file_name().into_string().unwrap(); 
-            filemap.insert(name, contents);  
+            let resource = Resource::new(contents);
+            let resource = match file.metadata()?.modified() {
+                Ok(modified) => resource.with_modified(modified),
+                Err(_) => resource,
+            };
+            filemap.insert(name, resource);  
         }
     }
     Ok(filemap)
@@ -665,7 +802,7 @@ where CB: FnMut(UiRef)-> Fut + Send + Clone + 'static,
 /// # use gemgui::Result;
 /// # use gemgui::ui::{Gui, Ui};
 /// # use gemgui::ui_ref::UiRef;
-/// # const RESOURCES: &[(&'static str, &'static str)] = &[]; 
+/// # const RESOURCES: &[gemgui::respack::PackedResource] = &[]; 
 /// fn main() -> Result<()> {
 ///    let fm = gemgui::filemap_from(RESOURCES);
 ///    gemgui::window_application(fm,
@@ -1,5 +1,7 @@
 /// Drawing element
 pub mod canvas;
+/// Canvas surface that sends only changed areas
+pub mod surface;
 /// Colors
 pub mod color;
 /// Bitmaps
@@ -7,4 +9,11 @@ pub mod bitmap;
 /// Bitmap scale and rotation
 pub mod bitmap_transform;
 /// Graphics primitives
-pub mod context;
\ No newline at end of file
+pub mod context;
+/// Software rendering of Context2D and shapes on bitmaps
+pub mod raster;
+/// Image processing
+pub mod filter;
+/// Animated bitmaps and sprite sheets
+pub mod animated;
+mod font;
@@ -0,0 +1,470 @@
+use std::io::Cursor;
+use std::ops::Deref;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use image::{AnimationDecoder, Frames, ImageFormat, ImageResult};
+use image::codecs::gif::GifDecoder;
+use image::codecs::png::PngDecoder;
+use image::codecs::webp::WebPDecoder;
+
+use crate::{GemGuiError, Rect, Result};
+use crate::graphics::bitmap::{Bitmap, BitmapData};
+use crate::graphics::canvas::Canvas;
+use crate::ui::{TimerId, Ui};
+
+// as browsers, frames without a delay are shown this long
+static DEFAULT_DELAY: Duration = Duration::from_millis(100);
+// timer resolution of playback
+static MIN_TICK: Duration = Duration::from_millis(10);
+
+/// Sequence of bitmaps shown each for a delay
+///
+/// GIF, APNG and animated WebP files are decoded to full size frames,
+/// other image formats have a single frame. Cloning is cheap as frames are shared.
+///
+/// # Example
+///
+/// ```no_run
+/// # use gemgui::graphics::animated::AnimatedBitmap;
+/// # use gemgui::graphics::canvas::Canvas;
+/// fn spinner(canvas: &Canvas) {
+///     let animation = AnimatedBitmap::from_image_file("spinner.gif").unwrap();
+///     let playback = canvas.play(&animation, 10, 10, None);
+///     // ...
+///     playback.stop();
+/// }
+/// ```
+#[derive(Clone)]
+pub struct AnimatedBitmap {
+    frames: Arc<Vec<(Bitmap, Duration)>>,
+}
+
+impl AnimatedBitmap {
+
+    /// New animation from frames
+    ///
+    /// # Arguments
+    ///
+    /// `frames` - bitmaps and their delays
+    ///
+    /// # Return
+    ///
+    /// AnimatedBitmap
+    pub fn new(frames: Vec<(Bitmap, Duration)>) -> AnimatedBitmap {
+        AnimatedBitmap{frames: Arc::new(frames)}
+    }
+
+    /// New animation from an image file
+    ///
+    /// # Arguments
+    ///
+    /// `filename` - a path to image file, GIF, PNG and WebP animations are supported
+    ///
+    /// # Return
+    ///
+    /// AnimatedBitmap
+    pub fn from_image_file(filename: &str) -> Result<AnimatedBitmap> {
+        match std::fs::read(filename) {
+            Ok(bytes) => Self::from_image_bytes(&bytes),
+            Err(e) => GemGuiError::error(format!("Image file not found: {filename}, {e}")),
+        }
+    }
+
+    /// New animation from image file bytes
+    ///
+    /// # Arguments
+    ///
+    /// `bytes` - image file bytes, GIF, PNG and WebP animations are supported
+    ///
+    /// # Return
+    ///
+    /// AnimatedBitmap
+    pub fn from_image_bytes(bytes: &[u8]) -> Result<AnimatedBitmap> {
+        let format = match image::guess_format(bytes) {
+            Ok(format) => format,
+            Err(e) => return GemGuiError::error(format!("Expected an image format, {e}")),
+        };
+        let frames = match format {
+            ImageFormat::Gif => Some(decoded(GifDecoder::new(Cursor::new(bytes)))?.into_frames()),
+            ImageFormat::Png => {
+                let decoder = decoded(PngDecoder::new(Cursor::new(bytes)))?;
+                decoder.is_apng().then(|| decoder.apng().into_frames())
+            },
+            ImageFormat::WebP => {
+                let decoder = decoded(WebPDecoder::new(Cursor::new(bytes)))?;
+                decoder.has_animation().then(|| decoder.into_frames())
+            },
+            _ => None,
+        };
+        match frames {
+            Some(frames) => Self::from_frames(frames),
+            None => Ok(Self::new(vec!((Bitmap::from_image_bytes(bytes)?, DEFAULT_DELAY)))),
+        }
+    }
+
+    fn from_frames(frames: Frames) -> Result<AnimatedBitmap> {
+        let frames = decoded(frames.collect_frames())?;
+        if frames.is_empty() {
+            return GemGuiError::error("Bad format, no frames");
+        }
+        Ok(Self::new(frames.into_iter().map(|frame| {
+            let (numer, denom) = frame.delay().numer_denom_ms();
+            let delay = Duration::from_secs_f64(numer as f64 / denom.max(1) as f64 / 1000.);
+            let delay = if delay.is_zero() {DEFAULT_DELAY} else {delay};
+            (frame.into_buffer().into(), delay)
+        }).collect()))
+    }
+
+    /// Number of frames
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Has no frames
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Frame bitmap
+    ///
+    /// # Arguments
+    ///
+    /// `index` - frame index
+    pub fn frame(&self, index: usize) -> Option<&Bitmap> {
+        self.frames.get(index).map(|f| &f.0)
+    }
+
+    /// How long a frame is shown
+    ///
+    /// # Arguments
+    ///
+    /// `index` - frame index
+    pub fn delay(&self, index: usize) -> Option<Duration> {
+        self.frames.get(index).map(|f| f.1)
+    }
+
+    /// Duration of all frames
+    pub fn duration(&self) -> Duration {
+        self.frames.iter().map(|f| f.1).sum()
+    }
+
+    /// Iterate over frames and their delays
+    pub fn iter(&self) -> impl Iterator<Item = (&Bitmap, Duration)> {
+        self.frames.iter().map(|f| (&f.0, f.1))
+    }
+}
+
+/// Bitmap sliced into frames
+///
+/// # Example
+///
+/// ```
+/// # use std::time::Duration;
+/// # use gemgui::graphics::animated::SpriteSheet;
+/// # use gemgui::graphics::bitmap::{Bitmap, BitmapData};
+/// let sheet = SpriteSheet::new(Bitmap::new(128, 64), 32, 32).unwrap();
+/// assert_eq!(sheet.len(), 8);
+/// let walk = sheet.animation(0..4, Duration::from_millis(80)).unwrap();
+/// assert_eq!(walk.frame(1).unwrap().width(), 32);
+/// ```
+#[derive(Clone)]
+pub struct SpriteSheet {
+    bitmap: Bitmap,
+    rects: Vec<Rect<u32>>,
+}
+
+impl SpriteSheet {
+
+    /// New sprite sheet of equal size frames
+    ///
+    /// Frames are indexed left to right, top to bottom. Partial frames
+    /// on the right and bottom edges are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// `bitmap` - sheet bitmap
+    ///
+    /// `frame_width` - frame width
+    ///
+    /// `frame_height` - frame height
+    ///
+    /// # Return
+    ///
+    /// SpriteSheet
+    pub fn new(bitmap: Bitmap, frame_width: u32, frame_height: u32) -> Result<SpriteSheet> {
+        if frame_width == 0 || frame_height == 0 || frame_width > bitmap.width() || frame_height > bitmap.height() {
+            return GemGuiError::error(format!("Bad frame size {frame_width}x{frame_height}"));
+        }
+        let rects = (0..bitmap.height() / frame_height).flat_map(|row|
+            (0..bitmap.width() / frame_width).map(move |col|
+                Rect::new(col * frame_width, row * frame_height, frame_width, frame_height)))
+            .collect();
+        Ok(SpriteSheet{bitmap, rects})
+    }
+
+    /// New sprite sheet of given frame areas
+    ///
+    /// # Arguments
+    ///
+    /// `bitmap` - sheet bitmap
+    ///
+    /// `rects` - frame areas in index order
+    ///
+    /// # Return
+    ///
+    /// SpriteSheet, error if a frame is not inside of the bitmap
+    pub fn from_rects(bitmap: Bitmap, rects: &[Rect<u32>]) -> Result<SpriteSheet> {
+        for r in rects {
+            if r.width() == 0 || r.height() == 0 || r.x() + r.width() > bitmap.width() || r.y() + r.height() > bitmap.height() {
+                return GemGuiError::error(format!("Bad frame {r:?}"));
+            }
+        }
+        Ok(SpriteSheet{bitmap, rects: rects.to_vec()})
+    }
+
+    /// Sheet bitmap
+    pub fn bitmap(&self) -> &Bitmap {
+        &self.bitmap
+    }
+
+    /// Number of frames
+    pub fn len(&self) -> usize {
+        self.rects.len()
+    }
+
+    /// Has no frames
+    pub fn is_empty(&self) -> bool {
+        self.rects.is_empty()
+    }
+
+    /// Frame area in the sheet
+    ///
+    /// # Arguments
+    ///
+    /// `index` - frame index
+    pub fn rect(&self, index: usize) -> Option<Rect<u32>> {
+        self.rects.get(index).copied()
+    }
+
+    /// Frame bitmap
+    ///
+    /// # Arguments
+    ///
+    /// `index` - frame index
+    ///
+    /// # Return
+    ///
+    /// A copy of the frame area
+    pub fn frame(&self, index: usize) -> Option<Bitmap> {
+        self.rects.get(index).and_then(|r| Bitmap::clip(r, &self.bitmap).ok())
+    }
+
+    /// Animation of frames
+    ///
+    /// # Arguments
+    ///
+    /// `indices` - frame indices in playing order
+    ///
+    /// `delay` - how long each frame is shown
+    ///
+    /// # Return
+    ///
+    /// AnimatedBitmap, error if an index is out of range
+    pub fn animation<I>(&self, indices: I, delay: Duration) -> Result<AnimatedBitmap>
+    where I: IntoIterator<Item = usize> {
+        let mut frames = Vec::new();
+        for index in indices {
+            match self.frame(index) {
+                Some(bitmap) => frames.push((bitmap, delay)),
+                None => return GemGuiError::error(format!("Bad frame index {index}")),
+            }
+        }
+        Ok(AnimatedBitmap::new(frames))
+    }
+}
+
+/// Handle to an animation playing on a canvas, see [Canvas::play]
+///
+/// Playback stops when the canvas is removed. Dropping the handle does not stop
+/// the playback, see [stop_on_drop](Self::stop_on_drop).
+pub struct Playback {
+    canvas: Canvas,
+    timer: TimerId,
+    playing: Arc<AtomicBool>,
+}
+
+impl Playback {
+
+    /// Stop the playback, the current frame remains on canvas
+    pub fn stop(&self) {
+        if self.playing.swap(false, Ordering::SeqCst) {
+            // timer is gone already if playback ended meanwhile
+            self.canvas.ui().cancel_timer(self.timer).unwrap_or(());
+        }
+    }
+
+    /// Whether frames are still drawn
+    pub fn is_playing(&self) -> bool {
+        self.playing.load(Ordering::SeqCst)
+    }
+
+    /// Stop the playback when the returned guard is dropped
+    ///
+    /// # Return
+    ///
+    /// Guard that derefs to this playback
+    pub fn stop_on_drop(self) -> PlaybackGuard {
+        PlaybackGuard{playback: self}
+    }
+}
+
+/// Playback that stops when dropped, see [Playback::stop_on_drop]
+pub struct PlaybackGuard {
+    playback: Playback,
+}
+
+impl Deref for PlaybackGuard {
+    type Target = Playback;
+    fn deref(&self) -> &Self::Target {
+        &self.playback
+    }
+}
+
+impl Drop for PlaybackGuard {
+    fn drop(&mut self) {
+        self.playback.stop();
+    }
+}
+
+impl Canvas {
+
+    /// Play an animation
+    ///
+    /// Frames are painted with a periodic timer, see [Ui::periodic]. Frame delays are
+    /// kept on average, a late frame shortens the next ones. Draw callbacks are not called.
+    /// Playback stops when the canvas element is removed.
+    ///
+    /// # Arguments
+    ///
+    /// `animation` - frames played
+    ///
+    /// `x` - x coordinate, does not have to be in canvas
+    ///
+    /// `y` - y coordinate, does not have to be in canvas
+    ///
+    /// `loops` - how many times the animation is played, None repeats forever
+    ///
+    /// # Return
+    ///
+    /// Playback to stop the animation
+    pub fn play(&self, animation: &AnimatedBitmap, x: i32, y: i32, loops: Option<u32>) -> Playback {
+        let playing = Arc::new(AtomicBool::new(!animation.is_empty() && loops != Some(0)));
+        if !playing.load(Ordering::SeqCst) {
+            return Playback{canvas: self.clone(), timer: 0, playing};
+        }
+        let tick = animation.iter().map(|f| f.1).min().unwrap_or(DEFAULT_DELAY).max(MIN_TICK);
+        let canvas = self.clone();
+        let animation = animation.clone();
+        let is_playing = playing.clone();
+        let mut index = 0;
+        let mut played = 0;
+        let mut shown = Instant::now();
+        canvas.paint(x, y, animation.frame(0).unwrap());
+        canvas.track_removal();
+        let timer = self.ui().periodic(tick, move |ui, id| {
+            if !is_playing.load(Ordering::SeqCst) {
+                return;
+            }
+            if !canvas.is_tracked() {
+                is_playing.store(false, Ordering::SeqCst);
+                ui.cancel_timer(id).unwrap_or(());
+                return;
+            }
+            let mut changed = false;
+            while let Some(delay) = animation.delay(index).filter(|delay| shown.elapsed() >= *delay) {
+                shown += delay;
+                index += 1;
+                changed = true;
+                if index == animation.len() {
+                    index = 0;
+                    played += 1;
+                    if loops.is_some_and(|loops| played >= loops) {
+                        is_playing.store(false, Ordering::SeqCst);
+                        ui.cancel_timer(id).unwrap_or(());
+                        return;
+                    }
+                }
+            }
+            if changed {
+                canvas.paint(x, y, animation.frame(index).unwrap());
+            }
+        });
+        Playback{canvas: self.clone(), timer, playing}
+    }
+}
+
+fn decoded<T>(result: ImageResult<T>) -> Result<T> {
+    match result {
+        Ok(value) => Ok(value),
+        Err(e) => GemGuiError::error(format!("Bad format, {e}")),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use image::{Delay, Frame, RgbaImage};
+    use image::codecs::gif::GifEncoder;
+
+    use crate::graphics::color as Color;
+
+    use super::*;
+
+    #[test]
+    fn test_sprite_sheet() {
+        let mut bitmap = Bitmap::new(100, 40);
+        bitmap.put(35, 5, Color::RED);
+        let sheet = SpriteSheet::new(bitmap.clone(), 30, 20).unwrap();
+        assert_eq!(sheet.len(), 6);
+        let r = sheet.rect(4).unwrap();
+        assert_eq!((r.x(), r.y(), r.width(), r.height()), (30, 20, 30, 20));
+        assert_eq!(sheet.frame(1).unwrap().get(5, 5), Color::RED);
+        assert!(sheet.frame(6).is_none());
+        assert!(SpriteSheet::new(bitmap.clone(), 0, 20).is_err());
+        assert!(SpriteSheet::from_rects(bitmap.clone(), &[Rect::new(90, 0, 20, 20)]).is_err());
+
+        let animation = sheet.animation([1, 0, 1], Duration::from_millis(50)).unwrap();
+        assert_eq!(animation.len(), 3);
+        assert_eq!(animation.duration(), Duration::from_millis(150));
+        assert_eq!(animation.frame(2).unwrap().get(5, 5), Color::RED);
+        assert!(sheet.animation([7], Duration::from_millis(50)).is_err());
+    }
+
+    #[test]
+    fn test_animated_gif() {
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = GifEncoder::new(&mut bytes);
+            for (color, ms) in [([255, 0, 0, 255], 50), ([0, 0, 255, 255], 0)] {
+                let image = RgbaImage::from_pixel(4, 4, image::Rgba(color));
+                encoder.encode_frame(Frame::from_parts(image, 0, 0, Delay::from_numer_denom_ms(ms, 1))).unwrap();
+            }
+        }
+        let animation = AnimatedBitmap::from_image_bytes(&bytes).unwrap();
+        assert_eq!(animation.len(), 2);
+        assert_eq!(animation.frame(0).unwrap().get(1, 1), Color::RED);
+        assert_eq!(animation.frame(1).unwrap().get(1, 1), Color::BLUE);
+        assert_eq!(animation.delay(0), Some(Duration::from_millis(50)));
+        assert_eq!(animation.delay(1), Some(DEFAULT_DELAY));
+
+        // still images have a single frame
+        let mut png = Vec::new();
+        image::DynamicImage::ImageRgba8(RgbaImage::new(2, 2))
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png).unwrap();
+        assert_eq!(AnimatedBitmap::from_image_bytes(&png).unwrap().len(), 1);
+        assert!(AnimatedBitmap::from_image_bytes(&[1, 2, 3]).is_err());
+    }
+}
@@ -1,9 +1,9 @@
-use std::{io::Cursor, path::Path};
+use std::{borrow::Cow, io::Cursor, ops::{Deref, DerefMut}, path::Path};
 
 use crate::{graphics::color as Color, GemGuiError, Result, Rect};
-use image::{io::Reader, DynamicImage, RgbImage, RgbaImage, Pixel, ImageBuffer, Rgba};
+use image::{io::Reader, ColorType, DynamicImage, RgbImage, RgbaImage, ImageBuffer, Rgba};
 
-use super::color::{rgba, rgb};
+use super::color::rgb;
 
 pub (crate) static BITMAP_MAX: u32 = 16000;
 
@@ -24,19 +24,209 @@ pub trait BitmapData {
 }
 
 /// Byte owning bitmap
+///
+/// Pixels are stored row by row as [Color::Pixel] values. Viewed as bytes, see [AsRef],
+/// a pixel is red, green, blue and alpha on little-endian platforms as in [RgbaImage].
+///
+/// Anti-aliased drawing of lines, shapes and text, e.g. [draw_line](Bitmap::draw_line) and
+/// [fill_polygon](Bitmap::fill_polygon), is implemented with the renderer in [raster](crate::graphics::raster).
+/// Points that are not finite are skipped.
+///
+/// # Example
+///
+/// ```
+/// # use gemgui::graphics::bitmap::{Bitmap, BitmapData};
+/// # use gemgui::graphics::color as Color;
+/// # use image::RgbaImage;
+/// let image = RgbaImage::from_pixel(4, 4, image::Rgba([255, 0, 0, 255]));
+/// let bitmap: Bitmap = image.into();
+/// assert_eq!(bitmap.get(1, 1), Color::RED);
+/// let image: RgbaImage = bitmap.into();
+/// assert_eq!(image.get_pixel(1, 1).0, [255, 0, 0, 255]);
+/// ```
 #[derive(Clone)]
 pub struct Bitmap {
     width: u32,
     height: u32,
-    bytes: Bytes,
+    bytes: Pixels,
+}
+
+// Bitmap pixels, an RGBA image buffer is adopted as is when it can be used as pixels in place
+enum Pixels {
+    Owned(Bytes),
+    Image(Vec<u8>),
+}
+
+impl Pixels {
+    fn from_image(bytes: Vec<u8>) -> Pixels {
+        if cfg!(target_endian = "little") && bytemuck::try_cast_slice::<u8, Color::Pixel>(&bytes).is_ok() {
+            Pixels::Image(bytes)
+        } else {
+            Pixels::Owned(from_rgba_bytes(&bytes))
+        }
+    }
+}
+
+impl Clone for Pixels {
+    // a cloned byte buffer is not guaranteed to be aligned for pixels
+    fn clone(&self) -> Self {
+        Pixels::Owned(self.to_vec())
+    }
+}
+
+impl Deref for Pixels {
+    type Target = [Color::Pixel];
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Pixels::Owned(pixels) => pixels,
+            Pixels::Image(bytes) => bytemuck::cast_slice(bytes),
+        }
+    }
+}
+
+impl DerefMut for Pixels {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        match self {
+            Pixels::Owned(pixels) => pixels,
+            Pixels::Image(bytes) => bytemuck::cast_slice_mut(bytes),
+        }
+    }
 }
 
 ///Byte borrowing bitmap
+///
+/// Also borrows pixels of an RGBA image, see [From]. If the image bytes cannot be used as
+/// pixels in place, i.e. on big-endian platforms or when not aligned, they are copied.
 #[derive(Clone)]
 pub struct BitmapRef<'a> {
     width: u32,
     height: u32,
-    bytes: &'a Bytes,
+    bytes: Cow<'a, [Color::Pixel]>,
+}
+
+/// How pixels are combined in [Bitmap::merge_with]
+///
+/// Porter-Duff operators and separable blend modes as in CSS compositing.
+/// Results are computed with premultiplied alpha.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum BlendMode {
+    /// Both source and destination are cleared.
+    Clear,
+    /// Source replaces destination.
+    Copy,
+    /// Destination is kept.
+    Destination,
+    /// Default. Source is drawn over destination.
+    #[default]
+    SourceOver,
+    /// Destination is drawn over source.
+    DestinationOver,
+    /// Source is drawn where destination is.
+    SourceIn,
+    /// Destination is kept where source is.
+    DestinationIn,
+    /// Source is drawn where destination is not.
+    SourceOut,
+    /// Destination is kept where source is not.
+    DestinationOut,
+    /// Source is drawn over destination only where destination is.
+    SourceAtop,
+    /// Destination is drawn over source only where source is.
+    DestinationAtop,
+    /// Source and destination are kept where they do not overlap.
+    Xor,
+    /// Colors and alphas are added.
+    Add,
+    /// Colors are multiplied, the result is darker.
+    Multiply,
+    /// Inverted colors are multiplied, the result is lighter.
+    Screen,
+    /// Multiply or screen upon the destination color.
+    Overlay,
+    /// Darker of the colors.
+    Darken,
+    /// Lighter of the colors.
+    Lighten,
+    /// Multiply or screen upon the source color.
+    HardLight,
+    /// Absolute difference of the colors.
+    Difference,
+}
+
+impl BlendMode {
+
+    // source and destination factors of Porter-Duff operators for source and destination alphas
+    fn factors(&self, sa: f32, da: f32) -> Option<(f32, f32)> {
+        Some(match self {
+            Self::Clear => (0., 0.),
+            Self::Copy => (1., 0.),
+            Self::Destination => (0., 1.),
+            Self::SourceOver => (1., 1. - sa),
+            Self::DestinationOver => (1. - da, 1.),
+            Self::SourceIn => (da, 0.),
+            Self::DestinationIn => (0., sa),
+            Self::SourceOut => (1. - da, 0.),
+            Self::DestinationOut => (0., 1. - sa),
+            Self::SourceAtop => (da, 1. - sa),
+            Self::DestinationAtop => (1. - da, sa),
+            Self::Xor => (1. - da, 1. - sa),
+            _ => return None,
+        })
+    }
+
+    // blend function of straight source and destination color components
+    fn mix(&self, cs: f32, cb: f32) -> f32 {
+        let multiply = |a: f32, b: f32| a * b;
+        let screen = |a: f32, b: f32| a + b - a * b;
+        let hard_light = |s: f32, b: f32| if s <= 0.5 {multiply(b, 2. * s)} else {screen(b, 2. * s - 1.)};
+        match self {
+            Self::Multiply => multiply(cs, cb),
+            Self::Screen => screen(cs, cb),
+            Self::Overlay => hard_light(cb, cs),
+            Self::Darken => cs.min(cb),
+            Self::Lighten => cs.max(cb),
+            Self::HardLight => hard_light(cs, cb),
+            Self::Difference => (cs - cb).abs(),
+            _ => cs,
+        }
+    }
+
+    fn blend(&self, src: Color::Pixel, dst: Color::Pixel, opacity: f32) -> Color::Pixel {
+        let src_alpha = Color::alpha(src);
+        // common cases are kept exact
+        if *self == Self::SourceOver && opacity >= 1. && src_alpha == 0xFF {
+            return src;
+        }
+        if *self == Self::SourceOver && (opacity <= 0. || src_alpha == 0) {
+            return dst;
+        }
+        let s = premultiplied(src, opacity);
+        let d = premultiplied(dst, 1.);
+        let (sa, da) = (s[3], d[3]);
+        let out = match self.factors(sa, da) {
+            Some((fa, fb)) => [0, 1, 2, 3].map(|i| s[i] * fa + d[i] * fb),
+            None if *self == Self::Add => [0, 1, 2, 3].map(|i| s[i] + d[i]),
+            None => {
+                let straight = |c: f32, a: f32| if a > 0. {c / a} else {0.};
+                let out = [0, 1, 2].map(|i| s[i] * (1. - da) + d[i] * (1. - sa)
+                    + sa * da * self.mix(straight(s[i], sa), straight(d[i], da)));
+                [out[0], out[1], out[2], sa + da - sa * da]
+            }
+        };
+        let a = out[3].clamp(0., 1.);
+        if a <= 0. {
+            return Color::TRANSPARENT;
+        }
+        let c = |v: f32| ((v / a).clamp(0., 1.) * 255.).round() as u8;
+        Color::rgba(c(out[0]), c(out[1]), c(out[2]), (a * 255.).round() as u8)
+    }
+}
+
+// components in between 0.0 and 1.0, alpha is multiplied with opacity
+fn premultiplied(pixel: Color::Pixel, opacity: f32) -> [f32; 4] {
+    let a = Color::alpha(pixel) as f32 / 255. * opacity;
+    let c = |v: Color::Pixel| v as f32 / 255. * a;
+    [c(Color::red(pixel)), c(Color::green(pixel)), c(Color::blue(pixel)), a]
 }
 
 impl Bitmap {
@@ -58,7 +248,7 @@ impl Bitmap {
         Bitmap {
             width,
             height,
-            bytes,
+            bytes: Pixels::Owned(bytes),
         }
     } 
 
@@ -69,39 +259,11 @@ impl Bitmap {
         data
     }
 
-    fn from_rgba(image: &RgbaImage) -> Bitmap {
-        let mut data = Self::alloc((image.width() * image.height()) as i32); 
-        let mut ii = 0;
-        for y in 0..image.height() {
-            for x in 0..image.width() {
-                    let pixel = image.get_pixel(x, y).channels();
-                    data[ii]= rgba(pixel[0], pixel[1], pixel[2], pixel[3]);
-                    ii += 1; 
-            }
-        }
-        Bitmap { 
-            width: image.width(),
-            height: image.height(),
-            bytes: data,
-        }
-    }
-
     fn from_rgb(image: &RgbImage) -> Bitmap {
-        let mut data = Self::alloc((image.width() * image.height()) as i32); 
-        let mut ii = 0;
-
-        for y in 0..image.height() {
-            for x in 0..image.width() {
-                    let pixel = image.get_pixel(x, y).channels();         
-                    data[ii] = rgb(pixel[0], pixel[1], pixel[2]);
-                    ii += 1; 
-            }
-        }
-
         Bitmap {
             width: image.width(),
             height: image.height(),
-            bytes: data,
+            bytes: Pixels::Owned(image.as_raw().chunks_exact(3).map(|c| rgb(c[0], c[1], c[2])).collect()),
         }
     }
 
@@ -124,21 +286,15 @@ impl Bitmap {
         Bitmap {
             width,
             height,
-            bytes,
+            bytes: Pixels::Owned(bytes),
         }
     }
 
     fn from_image(image: DynamicImage) -> Result<Bitmap> {
-        let result = image.as_rgba8();
-        match result {
-            Some(im) => Ok(Self::from_rgba(im)),
-            None => {
-                let result = image.as_rgb8();
-                match result {
-                    Some(im) => Ok(Self::from_rgb(im)),
-                    None => GemGuiError::error("Bad image".to_string()),
-                }
-            }
+        match image {
+            DynamicImage::ImageRgba8(im) => Ok(im.into()),
+            DynamicImage::ImageRgb8(im) => Ok(Self::from_rgb(&im)),
+            _ => GemGuiError::error("Bad image".to_string()),
         }
     }
 
@@ -195,13 +351,7 @@ impl Bitmap {
     /// 
     /// Bitmap  
     pub fn from(bitmap: &dyn BitmapData) -> Bitmap {
-        let mut new_bitmap = Bitmap::new(bitmap.width(), bitmap.height());
-        for y in 0..new_bitmap.height {
-            for x in 0..new_bitmap.width {    
-            new_bitmap.put(x,  y,  bitmap.get(x, y));
-            }
-        }
-        new_bitmap
+        Bitmap::from_bytes(bitmap.width(), bitmap.height(), bitmap.iter().as_slice().to_vec())
     }
 
     /// Clone a portion of bitmap to another
@@ -236,7 +386,7 @@ impl Bitmap {
             }
         }
         assert_eq!(width * height, bytes.len() as u32);
-        Ok(Bitmap { width, height, bytes }) 
+        Ok(Bitmap { width, height, bytes: Pixels::Owned(bytes) }) 
     }
 
     /// Create a solid color bitmap
@@ -247,13 +397,14 @@ impl Bitmap {
     /// 
     /// `height` - bitmap height
     /// 
-    /// `pixel`- color applied
+    /// `pixel`- color applied, a Pixel or a Color
     ///
     /// # Return
     /// 
     /// Bitmap  
-    pub fn rect(width: u32, height: u32, pixel: Color::Pixel) -> Bitmap {
+    pub fn rect(width: u32, height: u32, pixel: impl Into<Color::Pixel>) -> Bitmap {
         assert!(width < BITMAP_MAX && height < BITMAP_MAX);
+        let pixel = pixel.into();
         let sz = (width * height) as usize;
         let mut bytes: Vec<u32> = Vec::with_capacity(sz);
         bytes.resize(sz, pixel);
@@ -270,15 +421,16 @@ impl Bitmap {
     /// 
     /// `y` - y coordinate
     /// 
-    /// `pixel`- color applied
-    pub fn put(&mut self, x: u32, y: u32, pixel: Color::Pixel) {
+    /// `pixel`- color applied, a Pixel or a Color
+    pub fn put(&mut self, x: u32, y: u32, pixel: impl Into<Color::Pixel>) {
+        let pixel = pixel.into();
         debug_assert!(x < self.width && y < self.height && y * self.width + x < self.bytes.len() as u32, "{}x{} out from {}x{}", x, y, self.width, self.height);
         self.bytes[(y * self.width + x) as usize] = pixel; 
     }
 
     /// Merge another bitmap on this bitmap
     /// 
-    /// Pixels are either replaced or meld upon the pixel alpha channel.
+    /// Pixels are drawn over upon the pixel alpha channel, see [Bitmap::merge_with].
     /// 
     /// x_pos, y_pos or source bitmap extents does not have to be inside of this bitmap.
     /// 
@@ -290,78 +442,44 @@ impl Bitmap {
     /// 
     /// `bitmap`- source bitmap
     pub fn merge(&mut self, x_pos: i32, y_pos: i32, bitmap: &dyn BitmapData) {
-      
-        let mut width = bitmap.width();
-        let mut height = bitmap.height();
-        
-        if x_pos >= self.width() as i32 || (x_pos + bitmap.width() as i32) < 0 {
-            return;
-        } 
+        self.merge_with(x_pos, y_pos, bitmap, BlendMode::SourceOver, 1.);
+    }
 
-        if y_pos >= self.height() as i32 || (y_pos + bitmap.height() as i32) < 0 {
+    /// Composite another bitmap on this bitmap
+    /// 
+    /// Only the area under the source bitmap is affected, e.g. [BlendMode::Clear]
+    /// clears a rectangle of the source bitmap size.
+    /// 
+    /// x_pos, y_pos or source bitmap extents does not have to be inside of this bitmap.
+    /// 
+    /// # Arguments
+    /// 
+    /// `x_pos` - x coordinate
+    /// 
+    /// `y_pos` - y coordinate
+    /// 
+    /// `bitmap`- source bitmap
+    /// 
+    /// `mode` - how source and destination pixels are combined
+    /// 
+    /// `opacity` - source alpha multiplier in between 0.0 and 1.0
+    pub fn merge_with(&mut self, x_pos: i32, y_pos: i32, bitmap: &dyn BitmapData, mode: BlendMode, opacity: f32) {
+        let opacity = opacity.clamp(0., 1.);
+        let x0 = x_pos.max(0);
+        let y0 = y_pos.max(0);
+        let x1 = (x_pos + bitmap.width() as i32).min(self.width as i32);
+        let y1 = (y_pos + bitmap.height() as i32).min(self.height as i32);
+        if x0 >= x1 || y0 >= y1 {
             return;
         }
-        
-        let x: u32;
-        let y: u32;
-        let b_x: u32;
-        let b_y: u32;
-
-        if x_pos < 0 {              // if -10 and width 100
-            x = 0;                  // set 0  
-            b_x = (-x_pos) as u32;// (-10) => 10
-            width -= b_x;           // 100 + (-10) => 90 
-        } else {
-            x = x_pos as u32;
-        }
-
-        if y_pos < 0 {
-            y = 0;
-            b_y = (-y_pos) as u32;
-            height -= b_y;
-        } else {
-            y = y_pos as u32;
-        }
-
-        if  x + width >= self.width {
-            width = self.width - x;
-        }
-
-        if  y + height >= self.height {
-            height = self.height - y;
-        }
-
-        debug_assert!(width <= self.width);
-        debug_assert!(height <= self.height);
-        debug_assert!(width <= bitmap.width());
-        debug_assert!(height <= bitmap.height());
-    
-        for j in 0..height {
-            for i in 0..width {
-                assert!(x + i < self.width);
-                assert!(y + j < self.height);
-                let p = self.get(x + i, y + j);
-                assert!(i < bitmap.width());
-                assert!(j < bitmap.height());
-                let po = bitmap.get(i, j);
-
-                let ao = Color::alpha(po);
-                let a = Color::alpha(p);
-                let r = Color::red(p) * (0xFF - ao);
-                let g = Color::green(p) * (0xFF - ao);
-                let b = Color::blue(p) * (0xFF - ao);
-
-                let ro = Color::red(po) * ao;
-                let go = Color::green(po) * ao;
-                let bo = Color::blue(po) * ao;
-
-                let pixel = Color::rgba_clamped(
-                    (r + ro) / 0xFF,
-                    (g + go) / 0xFF,
-                    (b + bo) / 0xFF,
-                    a );
-
-                self.put(x + i, y + j, pixel);
+        let width = (x1 - x0) as u32;
+        let src_x = (x0 - x_pos) as u32;
+        for y in y0..y1 {
+            let src = bitmap.slice(src_x, width, (y - y_pos) as u32);
+            let begin = (y as u32 * self.width + x0 as u32) as usize;
+            let dst = &mut self.bytes[begin..begin + width as usize];
+            for (d, s) in dst.iter_mut().zip(src) {
+                *d = mode.blend(*s, *d, opacity);
             }
         }
     }
@@ -374,18 +492,8 @@ impl Bitmap {
     /// - various format are supported.
     pub fn save<FileName>(&self, filename: FileName) -> Result<()> 
     where FileName: AsRef<Path> {
-        let mut rgba: RgbaImage = ImageBuffer::new(self.width, self.height);
-        for x in 0..self.width {
-            for y in 0..self.height {
-                let p = self.get(x, y);
-                rgba.put_pixel(x, y, Rgba::from([
-                    Color::r(p),
-                    Color::g(p),
-                    Color::b(p),
-                    Color::a(p)]));
-            }
-        }
-        match rgba.save(filename) {
+        let bytes = to_rgba_bytes(&self.bytes);
+        match image::save_buffer(filename, &bytes, self.width, self.height, ColorType::Rgba8) {
             Ok(_) => Ok(()),
             Err(e) => GemGuiError::error(format!("{e}"))
         }
@@ -433,7 +541,7 @@ impl BitmapRef<'_> {
         BitmapRef {
             width,
             height,
-            bytes,
+            bytes: Cow::Borrowed(bytes),
         }
     }
 }
@@ -463,6 +571,126 @@ impl BitmapData for BitmapRef<'_> {
 
 }
 
+impl AsRef<[u8]> for Bitmap {
+    /// Pixel bytes in native byte order, RGBA on little-endian platforms
+    fn as_ref(&self) -> &[u8] {
+        bytemuck::cast_slice(&self.bytes)
+    }
+}
+
+impl AsMut<[u8]> for Bitmap {
+    /// Pixel bytes in native byte order, RGBA on little-endian platforms
+    fn as_mut(&mut self) -> &mut [u8] {
+        bytemuck::cast_slice_mut(&mut self.bytes)
+    }
+}
+
+impl AsRef<[u8]> for BitmapRef<'_> {
+    /// Pixel bytes in native byte order, RGBA on little-endian platforms
+    fn as_ref(&self) -> &[u8] {
+        bytemuck::cast_slice(&self.bytes)
+    }
+}
+
+/// The image buffer is adopted without copying on little-endian platforms when it is
+/// aligned for pixels, as buffers allocated by Rust are in practice. Otherwise pixels are copied.
+impl From<RgbaImage> for Bitmap {
+    fn from(image: RgbaImage) -> Self {
+        let (width, height) = image.dimensions();
+        let mut bytes = image.into_raw();
+        bytes.truncate((width * height * 4) as usize);
+        Bitmap {
+            width,
+            height,
+            bytes: Pixels::from_image(bytes),
+        }
+    }
+}
+
+/// The buffer of a bitmap converted from an [RgbaImage] is given back without copying,
+/// pixels of other bitmaps are copied as a single block on little-endian platforms.
+impl From<Bitmap> for RgbaImage {
+    fn from(bitmap: Bitmap) -> Self {
+        let bytes = match bitmap.bytes {
+            Pixels::Image(bytes) => bytes,
+            Pixels::Owned(pixels) => to_rgba_bytes(&pixels).into_owned(),
+        };
+        RgbaImage::from_raw(bitmap.width, bitmap.height, bytes).unwrap()
+    }
+}
+
+// RGBA images are used in place on little-endian platforms. Buffers allocated by Rust are
+// aligned for pixels in practice, an unaligned buffer panics; `BitmapRef::from` and
+// `Bitmap::from` copy such a buffer instead.
+#[cfg(target_endian = "little")]
+impl<C: Deref<Target = [u8]>> BitmapData for ImageBuffer<Rgba<u8>, C> {
+    fn iter(&self) -> std::slice::Iter<'_, Color::Pixel> {
+        image_pixels(self).iter()
+    }
+
+    fn width(&self) -> u32 {
+        ImageBuffer::width(self)
+    }
+
+    fn height(&self) -> u32 {
+        ImageBuffer::height(self)
+    }
+
+    fn get(&self, x: u32, y: u32) -> Color::Pixel {
+        image_pixels(self)[(y * ImageBuffer::width(self) + x) as usize]
+    }
+
+    fn slice(&self, x: u32, width: u32, y: u32) -> &[Color::Pixel] {
+        let begin = (y * ImageBuffer::width(self) + x) as usize;
+        &image_pixels(self)[begin..begin + width as usize]
+    }
+}
+
+#[cfg(target_endian = "little")]
+fn image_pixels<C: Deref<Target = [u8]>>(image: &ImageBuffer<Rgba<u8>, C>) -> &[Color::Pixel] {
+    let bytes: &[u8] = image;
+    let len = (image.width() * image.height()) as usize;
+    &bytemuck::cast_slice(bytes)[..len]
+}
+
+impl<'a, C: Deref<Target = [u8]>> From<&'a ImageBuffer<Rgba<u8>, C>> for BitmapRef<'a> {
+    fn from(image: &'a ImageBuffer<Rgba<u8>, C>) -> Self {
+        let bytes: &[u8] = image;
+        let bytes = match bytemuck::try_cast_slice(bytes) {
+            Ok(pixels) if cfg!(target_endian = "little") => Cow::Borrowed(pixels),
+            _ => Cow::Owned(from_rgba_bytes(bytes)),
+        };
+        BitmapRef {
+            width: image.width(),
+            height: image.height(),
+            bytes,
+        }
+    }
+}
+
+// pixels as RGBA bytes, borrowed on little-endian platforms
+fn to_rgba_bytes(pixels: &[Color::Pixel]) -> Cow<'_, [u8]> {
+    if cfg!(target_endian = "little") {
+        Cow::Borrowed(bytemuck::cast_slice(pixels))
+    } else {
+        Cow::Owned(pixels.iter().flat_map(|p| p.to_le_bytes()).collect())
+    }
+}
+
+// RGBA bytes as pixels, a plain copy on little-endian platforms
+fn from_rgba_bytes(bytes: &[u8]) -> Bytes {
+    let count = bytes.len() / 4;
+    let mut pixels = vec![Color::TRANSPARENT; count];
+    if cfg!(target_endian = "little") {
+        bytemuck::cast_slice_mut(&mut pixels).copy_from_slice(&bytes[..count * 4]);
+    } else {
+        for (p, c) in pixels.iter_mut().zip(bytes.chunks_exact(4)) {
+            *p = u32::from_le_bytes([c[0], c[1], c[2], c[3]]);
+        }
+    }
+    pixels
+}
+
 #[cfg(test)] 
 mod tests {
 
@@ -471,12 +699,9 @@ mod tests {
     #[test]
     fn test_bitmap() {
         let mut bmp1 = Bitmap::new(10, 10);
-        let mut vec: Vec<Color::Pixel> = Vec::with_capacity(10 * 10);
-        vec.resize(10 * 10, 0);
-        let mut p: Color::Pixel = 0; 
-        for i in vec.iter_mut() {
+        let mut vec: Vec<Color::Pixel> = vec![0; 10 * 10];
+        for (p, i) in (0..).zip(vec.iter_mut()) {
             *i = p;
-            p += 1;
         }
         let bmp2 = Bitmap::from_bytes(10, 10, vec);
         for y in 0..10 {
@@ -543,7 +768,7 @@ mod tests {
         bmp.merge(10, 10, &blue);
         for row in 0..100 {
             for col in 0..100 {
-                if row >= 10 && row < 20 && col >= 10 && col < 20 {
+                if (10..20).contains(&row) && (10..20).contains(&col) {
                     assert_eq!(bmp.get(row, col), blue_color, "{:x} vs {:x}", bmp.get(row, col), blue_color);
                 } else {
                     assert_eq!(bmp.get(row, col), red_color, "{:x} vs {:x} {} {}", bmp.get(row, col), red_color, row, col);
@@ -616,4 +841,99 @@ mod tests {
         }
 
     }
+
+    #[test]
+    fn test_merge_with() {
+        let red = Color::rgb(0xFF, 0, 0);
+        let half_blue = Color::rgba(0, 0, 0xFF, 0x80);
+
+        // last row and column are drawn, source offset is kept when clipped
+        let mut bmp = Bitmap::rect(10, 10, red);
+        let mut src = Bitmap::new(4, 4);
+        src.put(3, 3, Color::BLACK);
+        bmp.merge_with(-1, -1, &src, BlendMode::Copy, 1.);
+        assert_eq!(bmp.get(2, 2), Color::BLACK);
+        assert_eq!(bmp.get(0, 0), Color::TRANSPARENT);
+        bmp.merge_with(6, 6, &src, BlendMode::Copy, 1.);
+        assert_eq!(bmp.get(9, 9), Color::BLACK);
+
+        // destination alpha is updated
+        let mut bmp = Bitmap::new(1, 1);
+        bmp.merge(0, 0, &Bitmap::rect(1, 1, half_blue));
+        assert_eq!(bmp.get(0, 0), half_blue);
+        bmp.merge(0, 0, &Bitmap::rect(1, 1, half_blue));
+        assert_eq!(bmp.get(0, 0), Color::rgba(0, 0, 0xFF, 0xC0));
+
+        let blend = |dst: Color::Pixel, src: Color::Pixel, mode: BlendMode, opacity: f32| {
+            let mut bmp = Bitmap::rect(1, 1, dst);
+            bmp.merge_with(0, 0, &Bitmap::rect(1, 1, src), mode, opacity);
+            bmp.get(0, 0)
+        };
+        assert_eq!(blend(red, half_blue, BlendMode::SourceOver, 1.), Color::rgb(0x7F, 0, 0x80));
+        assert_eq!(blend(red, Color::BLUE, BlendMode::SourceOver, 0.5), Color::rgb(0x80, 0, 0x80));
+        assert_eq!(blend(red, Color::BLUE, BlendMode::DestinationOver, 1.), red);
+        assert_eq!(blend(red, Color::BLUE, BlendMode::Clear, 1.), Color::TRANSPARENT);
+        assert_eq!(blend(red, Color::BLUE, BlendMode::DestinationOut, 1.), Color::TRANSPARENT);
+        assert_eq!(blend(Color::TRANSPARENT, Color::BLUE, BlendMode::SourceIn, 1.), Color::TRANSPARENT);
+        assert_eq!(blend(red, half_blue, BlendMode::SourceAtop, 1.), Color::rgb(0x7F, 0, 0x80));
+        assert_eq!(blend(red, Color::BLUE, BlendMode::Xor, 1.), Color::TRANSPARENT);
+        assert_eq!(blend(red, Color::BLUE, BlendMode::Add, 1.), Color::rgb(0xFF, 0, 0xFF));
+        let gray = Color::rgb(0x80, 0x80, 0x80);
+        assert_eq!(blend(gray, gray, BlendMode::Multiply, 1.), Color::rgb(0x40, 0x40, 0x40));
+        assert_eq!(blend(gray, gray, BlendMode::Screen, 1.), Color::rgb(0xC0, 0xC0, 0xC0));
+        assert_eq!(blend(red, Color::WHITE, BlendMode::Darken, 1.), red);
+        assert_eq!(blend(red, Color::WHITE, BlendMode::Difference, 1.), Color::rgb(0, 0xFF, 0xFF));
+        assert_eq!(blend(Color::BLACK, Color::WHITE, BlendMode::Overlay, 1.), Color::BLACK);
+        assert_eq!(blend(Color::BLACK, Color::WHITE, BlendMode::HardLight, 1.), Color::WHITE);
+        // blend modes over transparent are as source over
+        assert_eq!(blend(Color::TRANSPARENT, half_blue, BlendMode::Multiply, 1.), half_blue);
+    }
+
+    #[test]
+    fn test_image_conversions() {
+        let mut image = RgbaImage::new(3, 2);
+        image.put_pixel(2, 1, Rgba([1, 2, 3, 4]));
+        let mut bitmap: Bitmap = image.clone().into();
+        assert_eq!(bitmap.get(2, 1), Color::rgba(1, 2, 3, 4));
+        // image is used as bitmap data in place
+        if cfg!(target_endian = "little") {
+            assert_eq!(image.get(2, 1), Color::rgba(1, 2, 3, 4));
+            assert_eq!(image.slice(1, 2, 1), &[0, Color::rgba(1, 2, 3, 4)]);
+        }
+        // image buffer is adopted and given back by the bitmap
+        let copy = image.clone();
+        let raw = copy.as_raw().as_ptr();
+        let mut adopted: Bitmap = copy.into();
+        adopted.put(0, 1, Color::BLUE);
+        let back: RgbaImage = adopted.clone().into();
+        if cfg!(target_endian = "little") {
+            assert_eq!(adopted.as_ref().as_ptr(), raw);
+            let back: RgbaImage = adopted.into();
+            assert_eq!(back.as_raw().as_ptr(), raw);
+        }
+        assert_eq!(back.get_pixel(0, 1).0, [0, 0, 0xFF, 0xFF]);
+        assert_eq!(back.get_pixel(2, 1).0, [1, 2, 3, 4]);
+        let view = BitmapRef::from(&image);
+        assert_eq!(view.get(2, 1), Color::rgba(1, 2, 3, 4));
+        assert_eq!(view.slice(1, 2, 1), &[0, Color::rgba(1, 2, 3, 4)]);
+        let raw = [0u8; 4 * 6 + 1];
+        let unaligned = ImageBuffer::<Rgba<u8>, _>::from_raw(3, 2, &raw[1..]).unwrap();
+        assert!(BitmapRef::from(&unaligned).iter().all(|p| *p == Color::TRANSPARENT));
+
+        if cfg!(target_endian = "little") {
+            assert_eq!(&bitmap.as_ref()[20..], &[1, 2, 3, 4]);
+        }
+        bitmap.as_mut()[0..4].copy_from_slice(&Color::RED.to_ne_bytes());
+        assert_eq!(bitmap.get(0, 0), Color::RED);
+
+        let image: RgbaImage = bitmap.clone().into();
+        assert_eq!(image.get_pixel(0, 0).0, [0xFF, 0, 0, 0xFF]);
+        assert_eq!(image.get_pixel(2, 1).0, [1, 2, 3, 4]);
+
+        let file = std::env::temp_dir().join("gemgui_test_image_conversions.png");
+        bitmap.save(&file).unwrap();
+        let loaded = Bitmap::from_image_file(file.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&file).unwrap();
+        assert!(loaded.iter().eq(bitmap.iter()));
+    }
 }
@@ -2,7 +2,7 @@
 use crate::GemGuiError;
 use crate::Result;
 
-use super::{bitmap::{Bytes, BitmapData}};
+use super::{bitmap::{Bitmap, Bytes, BitmapData}};
 use super::color::TRANSPARENT;
 use super::color as Color;
 
@@ -10,6 +10,113 @@ use Color::Pixel as BitmapPixel;
 
 static BITMAP_MAX: i32 = crate::graphics::bitmap::BITMAP_MAX as i32;
 
+/// Resampling filter used when a bitmap is scaled, rotated or transformed
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Filter {
+    /// Nearest pixel, fastest but blocky
+    #[default]
+    Nearest,
+    /// Linear interpolation of 2x2 pixels
+    Bilinear,
+    /// Catmull-Rom cubic of 4x4 pixels
+    Bicubic,
+    /// Lanczos with 3 lobes of 6x6 pixels, sharpest and slowest
+    Lanczos,
+}
+
+impl Filter {
+    // radius of the kernel in source pixels
+    fn support(&self) -> f64 {
+        match self {
+            Self::Nearest => 0.5,
+            Self::Bilinear => 1.,
+            Self::Bicubic => 2.,
+            Self::Lanczos => 3.,
+        }
+    }
+
+    fn weight(&self, x: f64) -> f64 {
+        let x = x.abs();
+        match self {
+            Self::Nearest => if x <= 0.5 {1.} else {0.},
+            Self::Bilinear => (1. - x).max(0.),
+            Self::Bicubic => if x < 1. {
+                1.5 * x * x * x - 2.5 * x * x + 1.
+            } else if x < 2. {
+                -0.5 * x * x * x + 2.5 * x * x - 4. * x + 2.
+            } else {
+                0.
+            },
+            Self::Lanczos => if x < 1e-8 {
+                1.
+            } else if x < 3. {
+                let px = std::f64::consts::PI * x;
+                3. * px.sin() * (px / 3.).sin() / (px * px)
+            } else {
+                0.
+            },
+        }
+    }
+}
+
+// weights are fixed point numbers of this precision
+static WEIGHT_BITS: i32 = 14;
+static WEIGHT_ONE: i32 = 1 << WEIGHT_BITS;
+// subpixel positions of precomputed kernels
+static PHASES: usize = 256;
+
+// filtering is done on premultiplied pixels so that transparent pixels do not bleed
+fn premultiply(pixel: BitmapPixel) -> BitmapPixel {
+    let a = pixel >> 24;
+    match a {
+        255 => pixel,
+        0 => TRANSPARENT,
+        _ => {
+            let m = |c: u32| (c * a + 127) / 255;
+            m(pixel & 0xFF) | m((pixel >> 8) & 0xFF) << 8 | m((pixel >> 16) & 0xFF) << 16 | a << 24
+        }
+    }
+}
+
+fn unpremultiply(pixel: BitmapPixel) -> BitmapPixel {
+    let a = pixel >> 24;
+    match a {
+        255 => pixel,
+        0 => TRANSPARENT,
+        _ => {
+            let m = |c: u32| ((c * 255 + a / 2) / a).min(255);
+            m(pixel & 0xFF) | m((pixel >> 8) & 0xFF) << 8 | m((pixel >> 16) & 0xFF) << 16 | a << 24
+        }
+    }
+}
+
+fn accumulate(acc: &mut [i32; 4], pixel: BitmapPixel, weight: i32) {
+    acc[0] += (pixel & 0xFF) as i32 * weight;
+    acc[1] += ((pixel >> 8) & 0xFF) as i32 * weight;
+    acc[2] += ((pixel >> 16) & 0xFF) as i32 * weight;
+    acc[3] += (pixel >> 24) as i32 * weight;
+}
+
+// premultiplied pixel of accumulated values, kernels with negative lobes may overshoot
+fn accumulated(acc: &[i32; 4]) -> BitmapPixel {
+    let c = |v: i32| ((v + (WEIGHT_ONE >> 1)) >> WEIGHT_BITS).clamp(0, 255) as u32;
+    let a = c(acc[3]);
+    c(acc[0]).min(a) | c(acc[1]).min(a) << 8 | c(acc[2]).min(a) << 16 | a << 24
+}
+
+// float weights to fixed point, rounding error is added to the largest
+fn fixed_weights(weights: &[f64], fixed: &mut [i32]) {
+    let total: f64 = weights.iter().sum();
+    let total = if total == 0.0 {1.0} else {total};
+    for (f, w) in fixed.iter_mut().zip(weights) {
+        *f = (w / total * WEIGHT_ONE as f64).round() as i32;
+    }
+    let error = WEIGHT_ONE - fixed.iter().sum::<i32>();
+    if let Some(max) = fixed.iter_mut().max_by_key(|w| **w) {
+        *max += error;
+    }
+}
+
 /// Basic bitmap transformations
 #[derive(Clone)]
 pub struct BitmapTransform {
@@ -19,6 +126,7 @@ pub struct BitmapTransform {
     saved_height: i32,
     data: Bytes,
     saved: Bytes,
+    filter: Filter,
 }
 
 impl BitmapTransform {
@@ -54,10 +162,7 @@ impl BitmapTransform {
     /// New BitmapTransform
     ///  
     pub fn new(bitmap: &dyn BitmapData) -> BitmapTransform {
-        let mut data = Bytes::with_capacity((bitmap.width() * bitmap.height()) as usize);
-        for p in bitmap.iter() {
-            data.push(*p);
-        }
+        let data = bitmap.iter().as_slice().to_vec();
         BitmapTransform {
             width: bitmap.width() as i32,
             height: bitmap.height() as i32,
@@ -65,8 +170,23 @@ impl BitmapTransform {
             saved_height: bitmap.height() as i32,
             data: data.clone(),
             saved: data,
+            filter: Filter::Nearest,
         }
     }      
+
+    /// Set filter used to resample pixels, default is [Filter::Nearest]
+    /// 
+    /// # Arguments
+    /// 
+    /// `filter` - filter used by the following transformations
+    pub fn set_filter(&mut self, filter: Filter) {
+        self.filter = filter;
+    }
+
+    /// Current resampling filter
+    pub fn filter(&self) -> Filter {
+        self.filter
+    }
      
 
 /// Change image dimensions extending or cropping by value.
@@ -128,45 +248,234 @@ pub fn restore(&mut self) {
     self.height = self.saved_height;
 }
 
-/// Rotates the image 
-/// Image dimensions are not changed.
+/// Rotates the image around its center
+/// Image dimensions are not changed, corners rotated in are transparent.
 /// 
 /// # Argument
 /// 
 /// `angle` - counter clock wise rotation
 pub fn rotate(&mut self, angle: f64) {
+    let (sin_a, cos_a) = angle.sin_cos();
+    let cx = self.width as f64 / 2.0;
+    let cy = self.height as f64 / 2.0;
+    self.data = self.resample(self.width, self.height, TRANSPARENT, |x, y| {
+        let (dx, dy) = (x - cx, y - cy);
+        (cos_a * dx - sin_a * dy + cx, sin_a * dx + cos_a * dy + cy)
+    });
+}
 
-    let sin_a = angle.sin();
-    let cos_a = angle.cos();
+/// Rotates the image around its center
+/// Image dimensions grow to fit the whole rotated image.
+/// 
+/// # Argument
+/// 
+/// `angle` - counter clock wise rotation
+/// 
+/// `background` - pixel of areas not covered by the image
+pub fn rotate_to_fit(&mut self, angle: f64, background: BitmapPixel) -> Result<()> {
+    let (sin_a, cos_a) = angle.sin_cos();
+    let w = self.width as f64;
+    let h = self.height as f64;
+    // epsilon keeps right angles from adding a pixel
+    let new_width = ((w * cos_a).abs() + (h * sin_a).abs() - 1e-9).ceil().max(1.0) as i32;
+    let new_height = ((w * sin_a).abs() + (h * cos_a).abs() - 1e-9).ceil().max(1.0) as i32;
+
+    if new_width > BITMAP_MAX || new_height > BITMAP_MAX {
+        return GemGuiError::error("Bad size");
+    }
 
-    let half_w = self.width / 2;
-    let half_h = self.height / 2;
+    let (ncx, ncy) = (new_width as f64 / 2.0, new_height as f64 / 2.0);
+    self.data = self.resample(new_width, new_height, background, |x, y| {
+        let (dx, dy) = (x - ncx, y - ncy);
+        (cos_a * dx - sin_a * dy + w / 2.0, sin_a * dx + cos_a * dy + h / 2.0)
+    });
+    self.width = new_width;
+    self.height = new_height;
+    Ok(())
+}
 
-    let mut temp: Bytes = Self::alloc(self.data.len() as i32);
+/// Applies an affine transformation
+/// Image dimensions are not changed, use [enlarge](Self::enlarge) to make room.
+/// 
+/// # Argument
+/// 
+/// `matrix` - a, b, c, d, e and f as in canvas setTransform, a pixel at x, y
+/// is moved to a * x + c * y + e, b * x + d * y + f
+/// 
+/// `background` - pixel of areas not covered by the image
+/// 
+/// # Return
+/// 
+/// Error if matrix cannot be inverted
+pub fn transform(&mut self, matrix: &[f64; 6], background: BitmapPixel) -> Result<()> {
+    let [a, b, c, d, e, f] = *matrix;
+    let det = a * d - b * c;
+    if det.abs() < 1e-12 || !det.is_finite() {
+        return GemGuiError::error("Bad matrix");
+    }
+    // pixels are pulled from the source by the inverse
+    let (ia, ib, ic, id) = (d / det, -b / det, -c / det, a / det);
+    let (ie, jf) = ((c * f - d * e) / det, (b * e - a * f) / det);
+    self.data = self.resample(self.width, self.height, background, |x, y| {
+        (ia * x + ic * y + ie, ib * x + id * y + jf)
+    });
+    Ok(())
+}
 
+// New pixels, source position of each target pixel center is given by the mapping
+fn resample<F>(&self, width: i32, height: i32, background: BitmapPixel, mapping: F) -> Bytes
+where F: Fn(f64, f64) -> (f64, f64) {
+    let mut temp = Self::alloc(width * height);
     let mut index = 0;
-    for y  in 0..self.height {
-        let  y_sin =  sin_a * ((y - half_h) as f64);
-        let y_cos = cos_a * ((y - half_h) as f64);
-        for x in 0..self.width {
-            let new_x = (cos_a * ((x - half_w) as f64) - y_sin).round() as i32 + half_w - 1; // why this 1 makes round go right?
-            let new_y = (sin_a * ((x - half_w) as f64) + y_cos).round() as i32 + half_h;
-            let new_index = (new_y * self.width + new_x) as usize;
-
-            if new_x < 0 || new_x >= self.width || new_y < 0 || new_y >= self.height {
-                temp[index] = self.data[index];
-                }
-            else {
-                temp[index] = self.data[new_index];
-                }
-            index += 1;    
+    if self.filter == Filter::Nearest {
+        for y in 0..height {
+            for x in 0..width {
+                let (sx, sy) = mapping(x as f64 + 0.5, y as f64 + 0.5);
+                let (px, py) = (sx.floor() as i32, sy.floor() as i32);
+                temp[index] = if px < 0 || px >= self.width || py < 0 || py >= self.height {
+                    background
+                } else {
+                    self.data[(py * self.width + px) as usize]
+                };
+                index += 1;
+            }
         }
+        return temp;
     }
-    self.data = temp;
+    let source: Bytes = self.data.iter().map(|p| premultiply(*p)).collect();
+    let background = premultiply(background);
+    let kernels = self.kernels();
+    for y in 0..height {
+        for x in 0..width {
+            let (sx, sy) = mapping(x as f64 + 0.5, y as f64 + 0.5);
+            temp[index] = unpremultiply(self.sample(&source, &kernels, sx, sy, background));
+            index += 1;
+        }
+    }
+    temp
+}
+
+// Fixed point kernel weights for each subpixel phase
+fn kernels(&self) -> Vec<[i32; 6]> {
+    let support = self.filter.support();
+    let taps = (2.0 * support) as usize;
+    (0..PHASES).map(|phase| {
+        let fraction = phase as f64 / PHASES as f64;
+        let mut weights = [0.0; 6];
+        for (k, w) in weights.iter_mut().take(taps).enumerate() {
+            *w = self.filter.weight(fraction + support - 1.0 - k as f64);
+        }
+        let mut fixed = [0; 6];
+        fixed_weights(&weights[..taps], &mut fixed[..taps]);
+        fixed
+    }).collect()
+}
+
+// Filtered premultiplied pixel at a position where pixel centers are at half coordinates
+fn sample(&self, source: &[BitmapPixel], kernels: &[[i32; 6]], x: f64, y: f64, background: BitmapPixel) -> BitmapPixel {
+    let support = self.filter.support();
+    let (u, v) = (x - 0.5, y - 0.5);
+    if u <= -support || v <= -support || u >= self.width as f64 - 1.0 + support || v >= self.height as f64 - 1.0 + support {
+        return background;
+    }
+
+    let taps = (2.0 * support) as usize;
+    let (uf, vf) = (u.floor(), v.floor());
+    let x0 = uf as i32 - taps as i32 / 2 + 1;
+    let y0 = vf as i32 - taps as i32 / 2 + 1;
+    let wx = &kernels[(((u - uf) * PHASES as f64) as usize).min(PHASES - 1)][..taps];
+    let wy = &kernels[(((v - vf) * PHASES as f64) as usize).min(PHASES - 1)][..taps];
+    let inside = x0 >= 0 && y0 >= 0 && x0 + taps as i32 <= self.width && y0 + taps as i32 <= self.height;
+
+    // rows are filtered first and then combined, both in fixed point
+    let mut acc = [0; 4];
+    for (j, weight_y) in wy.iter().enumerate() {
+        let py = y0 + j as i32;
+        let mut row = [0; 4];
+        if inside {
+            let begin = (py * self.width + x0) as usize;
+            for (pixel, weight_x) in source[begin..begin + taps].iter().zip(wx) {
+                accumulate(&mut row, *pixel, *weight_x);
+            }
+        } else {
+            for (i, weight_x) in wx.iter().enumerate() {
+                let px = x0 + i as i32;
+                let pixel = if px < 0 || px >= self.width || py < 0 || py >= self.height {
+                    background
+                } else {
+                    source[(py * self.width + px) as usize]
+                };
+                accumulate(&mut row, pixel, *weight_x);
+            }
+        }
+        for c in 0..4 {
+            acc[c] += ((row[c] + (WEIGHT_ONE >> 1)) >> WEIGHT_BITS) * weight_y;
+        }
+    }
+    accumulated(&acc)
+}
+
+// First source pixel and fixed point weights for each target pixel along an axis.
+// Kernel is widened when scaled down so that all source pixels contribute, taps
+// beyond the edges are merged to the edge pixels.
+fn axis_weights(&self, source: i32, target: i32, factor: f64) -> Vec<(usize, Vec<i32>)> {
+    let stretch = (1.0 / factor).max(1.0);
+    let support = self.filter.support() * stretch;
+    (0..target).map(|i| {
+        let center = (i as f64 + 0.5) / factor - 0.5;
+        let first = (center - support).floor() as i32 + 1;
+        let last = (center + support).ceil() as i32 - 1;
+        let mut weights: Vec<f64> = Vec::new();
+        let mut previous = None;
+        for j in first..=last {
+            let weight = self.filter.weight((j as f64 - center) / stretch);
+            let clamped = j.clamp(0, source - 1);
+            match weights.last_mut() {
+                Some(w) if previous == Some(clamped) => *w += weight,
+                _ => weights.push(weight),
+            }
+            previous = Some(clamped);
+        }
+        let mut fixed = vec![0; weights.len()];
+        fixed_weights(&weights, &mut fixed);
+        (first.clamp(0, source - 1) as usize, fixed)
+    }).collect()
+}
+
+// Separable filtered scaling, first horizontally and then vertically
+fn scale_filtered(&self, new_width: i32, new_height: i32, x_factor: f64, y_factor: f64) -> Bytes {
+    let columns = self.axis_weights(self.width, new_width, x_factor);
+    let rows = self.axis_weights(self.height, new_height, y_factor);
+    let source: Bytes = self.data.iter().map(|p| premultiply(*p)).collect();
+    let (width, new_width) = (self.width as usize, new_width as usize);
+
+    let mut horizontal = Self::alloc(new_width as i32 * self.height);
+    for (line, target) in source.chunks_exact(width).zip(horizontal.chunks_exact_mut(new_width)) {
+        for ((first, weights), pixel) in columns.iter().zip(target.iter_mut()) {
+            let mut acc = [0; 4];
+            for (p, w) in line[*first..first + weights.len()].iter().zip(weights) {
+                accumulate(&mut acc, *p, *w);
+            }
+            *pixel = accumulated(&acc);
+        }
+    }
+
+    let mut temp = Self::alloc(new_width as i32 * new_height);
+    for ((first, weights), target) in rows.iter().zip(temp.chunks_exact_mut(new_width)) {
+        for (x, pixel) in target.iter_mut().enumerate() {
+            let mut acc = [0; 4];
+            for (k, w) in weights.iter().enumerate() {
+                accumulate(&mut acc, horizontal[(first + k) * new_width + x], *w);
+            }
+            *pixel = unpremultiply(accumulated(&acc));
+        }
+    }
+    temp
 }
 
 
 /// Scales the image  by x_factor and y_factor
+/// Pixels are resampled with the current [filter](Self::set_filter).
 /// 
 /// # Argument
 /// 
@@ -182,6 +491,13 @@ pub fn scale(&mut self, x_factor: f64, y_factor: f64) -> Result<()>{
         return GemGuiError::error("Bad size");
     }
 
+    if self.filter != Filter::Nearest {
+        self.data = self.scale_filtered(new_width, new_height, x_factor, y_factor);
+        self.width = new_width;
+        self.height = new_height;
+        return Ok(());
+    }
+
     let vec_sz = (new_width * new_height) as usize;
     let mut temp: Bytes = Self::alloc(new_width * new_height);
 
@@ -267,6 +583,13 @@ pub fn resize(&mut self, width: u32, height: u32) -> Result<()> {
 
 }
 
+impl From<BitmapTransform> for Bitmap {
+    /// Transformed bitmap, pixels are moved without a copy
+    fn from(transform: BitmapTransform) -> Self {
+        Bitmap::from_bytes(transform.width(), transform.height(), transform.data)
+    }
+}
+
 impl BitmapData for BitmapTransform {
     fn iter(&self) ->  std::slice::Iter<'_, BitmapPixel> {
     self.data.iter()
@@ -336,7 +659,7 @@ mod tests {
                 }
             }
         }
-        return true;
+        true
     }
 
     #[test]
@@ -427,4 +750,64 @@ mod tests {
 
 
     }
+
+    #[test]
+    fn test_filters() {
+        let mut b = Bitmap::rect(2, 1, BLACK);
+        b.put(1, 0, Color::WHITE);
+        let mut t = BitmapTransform::new(&b);
+        t.set_filter(Filter::Bilinear);
+        t.scale(2.0, 1.0).unwrap();
+        assert_eq!(t.width(), 4);
+        assert_eq!(t.get(0, 0), BLACK);
+        assert_eq!(t.get(1, 0), Color::rgb(64, 64, 64));
+        assert_eq!(t.get(2, 0), Color::rgb(191, 191, 191));
+        assert_eq!(t.get(3, 0), Color::WHITE);
+
+        // uniform areas are preserved by all filters
+        for filter in [Filter::Bilinear, Filter::Bicubic, Filter::Lanczos] {
+            let mut t = BitmapTransform::new(&Bitmap::rect(30, 20, RED));
+            t.set_filter(filter);
+            t.scale(0.3, 1.7).unwrap();
+            assert!(test_pixels(&t, 0, 0, t.width(), t.height(), RED));
+            t.restore();
+            t.rotate(0.5);
+            assert!(test_pixels(&t, 12, 7, 6, 6, RED));
+            assert_eq!(t.get(0, 0), Color::TRANSPARENT);
+        }
+    }
+
+    #[test]
+    fn test_rotate_to_fit() {
+        let r = Bitmap::rect(20, 10, RED);
+        let mut t = BitmapTransform::new(&r);
+        t.rotate_to_fit(PI / 2.0, BLUE).unwrap();
+        assert_eq!(t.width(), 10);
+        assert_eq!(t.height(), 20);
+        assert!(test_pixels(&t, 0, 0, 10, 20, RED));
+        t.restore();
+        t.rotate_to_fit(PI / 4.0, BLUE).unwrap();
+        assert_eq!(t.width(), 22);
+        assert_eq!(t.height(), 22);
+        assert_eq!(t.get(0, 0), BLUE);
+        assert_eq!(t.get(11, 11), RED);
+    }
+
+    #[test]
+    fn test_affine() {
+        let r = Bitmap::rect(10, 10, RED);
+        let mut b = Bitmap::rect(20, 20, BLUE);
+        b.merge(0, 0, &r);
+        let mut t = BitmapTransform::new(&b);
+        t.transform(&[1.0, 0.0, 0.0, 1.0, 10.0, 5.0], BLACK).unwrap();
+        assert!(test_pixels(&t, 0, 0, 20, 5, BLACK));
+        assert!(test_pixels(&t, 10, 5, 10, 10, RED));
+        assert!(test_pixels(&t, 0, 5, 10, 15, BLACK));
+        t.restore();
+        t.transform(&[0.5, 0.0, 0.0, 0.5, 0.0, 0.0], BLACK).unwrap();
+        assert!(test_pixels(&t, 0, 0, 5, 5, RED));
+        assert!(test_pixels(&t, 5, 0, 5, 10, BLUE));
+        assert!(test_pixels(&t, 10, 0, 10, 20, BLACK));
+        assert!(t.transform(&[1.0, 2.0, 2.0, 4.0, 0.0, 0.0], BLACK).is_err());
+    }
 }
\ No newline at end of file
@@ -1,9 +1,13 @@
-use std::{ops::Deref, collections::HashMap};
+use std::{ops::Deref, collections::HashMap, io::Write};
+
+use flate2::write::ZlibEncoder;
+use image::{ColorType, ImageEncoder};
+use image::codecs::png::{CompressionType, FilterType, PngEncoder};
 
 use byteorder::{WriteBytesExt, LittleEndian};
-use crate::{graphics::{color as Color}, ui_data::UiData, Rect, Result};
+use crate::{graphics::{color as Color}, ui_data::UiData, GemGuiError, Rect, Result};
 use futures::Future;
-use crate::{element::Element, ui_ref::UiRef, JSMessageTx, graphics::bitmap::BitmapData};
+use crate::{element::Element, ui_ref::UiRef, JSMessageTx, graphics::bitmap::{Bitmap, BitmapData}};
 
 // this value depends if websocket payload size
 // in theory is big one (i.e. huge 2^63), but in practice it seems to be 
@@ -12,14 +16,73 @@ static TILE_WIDTH: u32 = 640;
 static TILE_HEIGHT: u32 = 640; 
 
 static CANVAS_ID: u32 = 0xAAA;
+static COMPRESSED_CANVAS_ID: u32 = 0xAAB;
+static CONTEXT_ID: u32 = 0xAAC;
 use LittleEndian as Endianness;
 
-use super::context::Context2D;
+// tiles smaller than this are not worth of compressing
+static MIN_COMPRESS_PIXELS: u32 = 1024;
+
+// encoding values in compressed tile header
+static ENCODING_RLE: u32 = 1;
+static ENCODING_DEFLATE: u32 = 2;
+static ENCODING_PNG: u32 = 3;
+
+use super::context::{CompositeOperation, Context2D};
+
+/// Bitmap compression used when painting bitmaps on canvas
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Compression {
+    /// Raw pixels, the fastest on a local UI
+    None,
+    /// Run length encoded pixels, good for flat areas
+    Rle,
+    /// Deflate compressed pixels
+    Deflate,
+    /// PNG encoded, good for natural images
+    Png,
+    /// Smallest of the above is chosen per tile
+    Auto,
+}
+
+/// Text measures, see [Canvas::measure_text]
+/// 
+/// Values are in CSS pixels, see <https://developer.mozilla.org/en-US/docs/Web/API/TextMetrics>
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TextMetrics {
+    /// Advance width of the text
+    pub width: f32,
+    /// Distance from the alignment point to the left side of the bounding rectangle
+    pub actual_bounding_box_left: f32,
+    /// Distance from the alignment point to the right side of the bounding rectangle
+    pub actual_bounding_box_right: f32,
+    /// Distance from the baseline to the top of the bounding rectangle
+    pub actual_bounding_box_ascent: f32,
+    /// Distance from the baseline to the bottom of the bounding rectangle
+    pub actual_bounding_box_descent: f32,
+    /// Distance from the baseline to the top of the highest bounding rectangle of the font
+    pub font_bounding_box_ascent: f32,
+    /// Distance from the baseline to the bottom of the lowest bounding rectangle of the font
+    pub font_bounding_box_descent: f32,
+}
 
 /// Canvas is-an Element
 #[derive(Clone)]
 pub struct Canvas {
     canvas: Element,
+    compression: Compression,
+    layer: Option<String>, // bitmaps and contexts are drawn on this layer
+}
+
+/// Offscreen layer of a canvas, see [Canvas::create_layer]
+/// 
+/// Layer content persists until it is drawn over or the canvas is resized,
+/// and it is made visible with [Canvas::composite].
+#[derive(Clone)]
+pub struct Layer {
+    canvas: Canvas,
+    name: String,
 }
 
 
@@ -27,14 +90,14 @@ fn align(a: u32) -> u32{
     (a + 3u32) & !3u32
 }
 
-fn write_prelude(vec8: &mut Vec<u8>, data_type: u32, owner: &str, sz: u32, header: &Vec<u32>) {
+fn write_prelude(vec8: &mut Vec<u8>, data_type: u32, owner: &str, sz: u32, header: &[u32]) {
         vec8.write_u32::<Endianness>(data_type).unwrap();
         vec8.write_u32::<Endianness>(sz).unwrap();
         vec8.write_u32::<Endianness>(align(owner.len() as u32)).unwrap();
         vec8.write_u32::<Endianness>(align(header.len() as u32)).unwrap();
 }
 
-fn write_epilog(vec8: &mut Vec<u8>, owner: &str, header: &Vec<u32>)  {      
+fn write_epilog(vec8: &mut Vec<u8>, owner: &str, header: &[u32])  {      
         
     for header_item in header {
             vec8.write_u32::<Endianness>(*header_item).unwrap();
@@ -88,6 +151,8 @@ impl Canvas {
     pub fn new(element: &Element) -> Canvas {   
         Canvas{
             canvas: element.clone(),
+            compression: Compression::None,
+            layer: None,
         }
     }
 
@@ -107,6 +172,8 @@ impl Canvas {
     pub fn from(element: Element) -> Canvas {   
         Canvas{
             canvas: element,
+            compression: Compression::None,
+            layer: None,
         }
     }
 
@@ -129,6 +196,32 @@ impl Canvas {
         &mut self.canvas   
     }
 
+    /// Set bitmap compression
+    /// 
+    /// Compression reduces the amount of data sent on remote and low-bandwidth
+    /// sessions, but costs CPU time on both ends. Default is [Compression::None].
+    /// 
+    /// # Arguments
+    /// 
+    /// `compression` - compression applied on bitmap tiles
+    pub fn set_compression(&mut self, compression: Compression) {
+        self.compression = compression;
+    }
+
+    /// Bitmap compression
+    /// 
+    /// # Return
+    /// 
+    /// compression applied on bitmap tiles
+    pub fn compression(&self) -> Compression {
+        self.compression
+    }
+
+    // id of element or layer where bitmaps and contexts are drawn
+    fn target(&self) -> &str {
+        self.layer.as_deref().unwrap_or(self.id())
+    }
+
     /// Cancels on draw callbacks 
     pub fn on_draw_cancel(&self) {
         self.element().unsubscribe("event_notify");
@@ -288,66 +381,15 @@ impl Canvas {
                     let width = TILE_WIDTH.min(bitmap.width() - i);
                     debug_assert!(!is_last);
                     is_last = (bitmap.height() - j < TILE_HEIGHT) && (bitmap.width() - i < TILE_WIDTH);
-                    let header = vec!(
-                        i + x_pos,
-                        j + y_pos,
-                        width,
-                        height,
-                        // figure out if this is the last one if set notification
-                        (as_draw && is_last) as u32
-                    );
-                    let mut vec8: Vec<u8> = vec![];
-                    write_prelude(
-                        &mut vec8,
-                        CANVAS_ID,
-                        self.id(),
-                        width * height,
-                        &header
-                        );    
-                    
-                    for row in j..(j + height) {
-                        let stride = bitmap.slice(i, width, row);
-                        for elem in stride.iter() {
-                        let p = Color::rgba(
-                            Color::r(*elem),
-                            Color::g(*elem),
-                            Color::b(*elem),
-                            Color::a(*elem));
-                        vec8.write_u32::<Endianness>(p).unwrap();
-                        }
-                    }
-                    
-                    write_epilog(
-                        &mut vec8,
-                        self.id(),
-                        &header,
-                        );
-                
-
-                //    assert!(vec8.len() as u32 == ((bitmap.width() - x) * (bitmap.height() - y) + 4) * 4 + 20 + 16, "w:{} h:{} len:{}", i, j, vec8.len()); 
-                    self.element().send_bin(vec8);
+                    // figure out if this is the last one if set notification
+                    let tile = Rect::new(i, j, width, height);
+                    self.send_tile(i + x_pos, j + y_pos, bitmap, &tile, as_draw && is_last);
                 }
             } 
         } else {
             is_last = true;
             if as_draw {
-                let header = vec!(
-                    0,0,0,0, is_last as u32
-                );
-                let mut vec8: Vec<u8> = vec![];
-                write_prelude(
-                    &mut vec8,
-                    CANVAS_ID,
-                    self.id(),
-                    0,
-                    &header
-                    );
-                write_epilog(
-                    &mut vec8,
-                    self.id(),
-                    &header,
-                    );
-                self.element().send_bin(vec8);
+                self.send_draw_notify();
             }                
         }
 
@@ -359,6 +401,124 @@ impl Canvas {
         
     } 
 
+    // Paint areas of bitmap to the same position on canvas, areas are expected to be within bitmap
+    pub (crate) fn paint_areas(&self, bitmap: &dyn BitmapData, areas: &[Rect<u32>], as_draw: bool) {
+        let mut tiles = Vec::new();
+        for area in areas {
+            for j in (area.y..area.y + area.height).step_by(TILE_HEIGHT as usize) {
+                let height = TILE_HEIGHT.min(area.y + area.height - j);
+                for i in (area.x..area.x + area.width).step_by(TILE_WIDTH as usize) {
+                    let width = TILE_WIDTH.min(area.x + area.width - i);
+                    tiles.push(Rect::new(i, j, width, height));
+                }
+            }
+        }
+        if tiles.is_empty() {
+            if as_draw {
+                self.send_draw_notify();
+            }
+            return;
+        }
+        let last = tiles.len() - 1;
+        for (index, tile) in tiles.iter().enumerate() {
+            self.send_tile(tile.x, tile.y, bitmap, tile, as_draw && index == last);
+        }
+    }
+
+    fn send_tile(&self, x_pos: u32, y_pos: u32, bitmap: &dyn BitmapData, tile: &Rect<u32>, notify: bool) {
+        if self.compression != Compression::None && tile.width * tile.height >= MIN_COMPRESS_PIXELS {
+            if let Some((encoding, payload)) = compress_tile(bitmap, tile, self.compression) {
+                self.send_compressed_tile(x_pos, y_pos, tile, notify, encoding, &payload);
+                return;
+            }
+        }
+        let header = vec!(
+            x_pos,
+            y_pos,
+            tile.width,
+            tile.height,
+            notify as u32
+        );
+        let mut vec8: Vec<u8> = vec![];
+        write_prelude(
+            &mut vec8,
+            CANVAS_ID,
+            self.target(),
+            tile.width * tile.height,
+            &header
+            );    
+        
+        for row in tile.y..(tile.y + tile.height) {
+            let stride = bitmap.slice(tile.x, tile.width, row);
+            for elem in stride.iter() {
+            let p = Color::rgba(
+                Color::r(*elem),
+                Color::g(*elem),
+                Color::b(*elem),
+                Color::a(*elem));
+            vec8.write_u32::<Endianness>(p).unwrap();
+            }
+        }
+        
+        write_epilog(
+            &mut vec8,
+            self.target(),
+            &header,
+            );
+        self.element().send_bin(vec8);
+    }
+
+    fn send_compressed_tile(&self, x_pos: u32, y_pos: u32, tile: &Rect<u32>, notify: bool, encoding: u32, payload: &[u8]) {
+        let header = vec!(
+            x_pos,
+            y_pos,
+            tile.width,
+            tile.height,
+            notify as u32,
+            encoding,
+            payload.len() as u32,
+            0 // padding
+        );
+        let words = align(payload.len() as u32) / 4;
+        let mut vec8: Vec<u8> = vec![];
+        write_prelude(
+            &mut vec8,
+            COMPRESSED_CANVAS_ID,
+            self.target(),
+            words,
+            &header
+            );
+        vec8.extend_from_slice(payload);
+        vec8.resize(vec8.len() + (words * 4) as usize - payload.len(), 0);
+        write_epilog(
+            &mut vec8,
+            self.target(),
+            &header,
+            );
+        self.element().send_bin(vec8);
+    }
+
+    // Empty tail that only requests a draw notification
+    fn send_draw_notify(&self) {
+        let header = vec!(
+            0,0,0,0, true as u32
+        );
+        let mut vec8: Vec<u8> = vec![];
+        write_prelude(
+            &mut vec8,
+            CANVAS_ID,
+            self.target(),
+            0,
+            &header
+            );
+        write_epilog(
+            &mut vec8,
+            self.target(),
+            &header,
+            );
+        self.element().send_bin(vec8);
+    }
+
      
 
     // Add an image to canvas for drawing
@@ -470,25 +630,183 @@ impl Canvas {
         self.send(msg);
     }
 
+    /// Measure text
+    /// 
+    /// # Arguments
+    /// 
+    /// `font` - CSS font, e.g. "16px serif"
+    /// 
+    /// `text` - text measured
+    /// 
+    /// # Return
+    /// 
+    /// Text metrics
+    pub async fn measure_text(&self, font: &str, text: &str) -> Result<TextMetrics> {
+        let value = self.ui().query(self.id(), "measure_text", &vec![font.to_string(), text.to_string()]).await?;
+        match serde_json::from_value::<TextMetrics>(value) {
+            Ok(metrics) => Ok(metrics),
+            Err(e) => GemGuiError::error(format!("Bad text metrics {e}")),
+        }
+    }
+
+    /// Canvas size
+    /// 
+    /// That is the size of canvas bitmap in pixels and it may differ from the element's
+    /// size on screen, see [set_size](Self::set_size). 
+    /// 
+    /// # Return
+    /// 
+    /// width and height
+    pub async fn size(&self) -> Result<(u32, u32)> {
+        let value = self.ui().query(self.id(), "canvas_size", &vec![]).await?;
+        match (value["width"].as_u64(), value["height"].as_u64()) {
+            (Some(width), Some(height)) => Ok((width as u32, height as u32)),
+            _ => GemGuiError::error(format!("Bad canvas size {value}")),
+        }
+    }
+
+    /// Set canvas size
+    /// 
+    /// Canvas element is sized as given in CSS pixels and its bitmap is scaled with UI's
+    /// device pixel ratio so that bitmaps of [size](Self::size) drawn with [draw_bitmap](Self::draw_bitmap)
+    /// are sharp on HiDPI screens. [Context2D] draw commands are scaled to use CSS pixels.
+    /// 
+    /// # Arguments
+    /// 
+    /// `width` - width in CSS pixels
+    /// 
+    /// `height` - height in CSS pixels
+    pub fn set_size(&self, width: u32, height: u32) {
+        let msg =  JSMessageTx {
+            element: self.id(),
+            _type: "canvas_size",
+            rect: Some(vec!(0., 0., width as f32, height as f32)),
+            ..Default::default()
+        };
+        self.send(msg);
+    }
+
+    /// Read pixels from canvas
+    /// 
+    /// Pixels are read from canvas bitmap, see [size](Self::size). Areas outside of
+    /// canvas are transparent black.
+    /// 
+    /// # Arguments
+    /// 
+    /// `rect` - area read
+    /// 
+    /// # Return
+    /// 
+    /// Bitmap of the area 
+    pub async fn get_image_data(&self, rect: Rect<u32>) -> Result<Bitmap> {
+        if rect.width == 0 || rect.height == 0 {
+            return GemGuiError::error("Empty rect");
+        }
+        let params = [rect.x, rect.y, rect.width, rect.height].iter().map(|v| v.to_string()).collect();
+        let data = self.ui().binary_query(self.id(), "image_data", &params).await?;
+        image_data_to_bitmap(&data)
+    }
+
+    /// Canvas content as PNG
+    /// 
+    /// # Return
+    /// 
+    /// PNG encoded image of canvas bitmap
+    pub async fn to_png(&self) -> Result<Vec<u8>> {
+        let data = self.ui().binary_query(self.id(), "canvas_png", &vec![]).await?;
+        if data.is_empty() {
+            return GemGuiError::error("Cannot read canvas");
+        }
+        Ok(data)
+    }
+
     /// Draw context
     /// 
+    /// Commands are sent binary encoded.
+    /// 
     /// # Arguments
     /// 
     /// `context` - context drawn
     pub fn draw_context(&self, context: &Context2D) {
-        let commands = context.composed();
-        if commands.is_empty() {
+        if context.is_empty() {
             return;
         }
+        let mut commands = Vec::new();
+        let count = context.encoded(&mut commands);
+        let as_draw = u32::from(self.layer.is_none());
+        let header = vec!(count, as_draw, 0, 0);
+        let mut vec8: Vec<u8> = vec![];
+        write_prelude(
+            &mut vec8,
+            CONTEXT_ID,
+            self.target(),
+            commands.len() as u32 / 4,
+            &header
+            );
+        vec8.extend_from_slice(&commands);
+        write_epilog(
+            &mut vec8,
+            self.target(),
+            &header,
+            );
+        self.element().send_bin(vec8);
+    }
+    
+    /// Create an offscreen layer
+    /// 
+    /// Layer has the same size as the canvas bitmap, see [size](Self::size). Resizing the canvas
+    /// with [set_size](Self::set_size) resizes its layers and clears them. Creating a layer 
+    /// with an existing name replaces the old layer. 
+    /// 
+    /// # Arguments
+    /// 
+    /// `name` - layer name
+    /// 
+    /// # Return
+    /// 
+    /// Layer
+    pub fn create_layer(&self, name: &str) -> Layer {
+        let layer = Layer {
+            canvas: Canvas {
+                canvas: self.canvas.clone(),
+                compression: self.compression,
+                layer: Some(format!("{}/{}", self.id(), name)),
+            },
+            name: name.to_string(),
+        };
         let msg =  JSMessageTx {
             element: self.id(),
-            _type: "canvas_draw",
-            commands: Some(commands),
+            _type: "create_layer",
+            name: Some(layer.id()),
             ..Default::default()
         };
         self.send(msg);
+        layer
     }
-    
+
+    /// Draw layers on canvas
+    /// 
+    /// Canvas is cleared and layers are drawn in the given order, 
+    /// draw_callback is called if enabled. Bitmaps painted on layers before
+    /// this call are composited, also when their tiles are compressed.
+    /// 
+    /// # Arguments
+    /// 
+    /// `layers` - layers and operations how each layer is composed on the layers below it
+    pub fn composite(&self, layers: &[(&Layer, CompositeOperation)]) {
+        let layers = layers.iter().map(|(layer, operation)| serde_json::json!({
+            "layer": layer.id(),
+            "operation": operation.to_string(),
+        })).collect();
+        let msg =  JSMessageTx {
+            element: self.id(),
+            _type: "composite",
+            layers: Some(&layers),
+            ..Default::default()
+        };
+        self.send(msg);
+    }
+
     /// Erase an area from Canvas
     /// 
     /// # Arguments
@@ -506,6 +824,166 @@ impl Canvas {
 }
 
 
+impl Layer {
+
+    /// Layer name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Layer id, can be used as an image id in [Context2D] draw image commands
+    pub fn id(&self) -> &str {
+        self.canvas.target()
+    }
+
+    /// Set bitmap compression, see [Canvas::set_compression]
+    /// 
+    /// # Arguments
+    /// 
+    /// `compression` - compression applied on bitmap tiles
+    pub fn set_compression(&mut self, compression: Compression) {
+        self.canvas.compression = compression;
+    }
+
+    /// Draws a given bitmap to the coordinates
+    /// 
+    /// # Arguments
+    /// 
+    /// `x` - x coordinate, does not have to be in layer
+    /// 
+    /// `y` - y coordinate, does not have to be in layer
+    /// 
+    /// `bitmap` - to be drawn
+    pub fn paint(&self, x: i32, y: i32, bitmap: &dyn BitmapData) {
+        self.canvas.paint_bitmap(x, y, bitmap, false)
+    }
+
+    /// Draw context
+    /// 
+    /// # Arguments
+    /// 
+    /// `context` - context drawn
+    pub fn draw_context(&self, context: &Context2D) {
+        self.canvas.draw_context(context)
+    }
+
+    /// Erase an area from layer
+    /// 
+    /// # Arguments
+    /// 
+    /// `rect` - erase area
+    pub fn erase(&self, rect: Rect<u32>) {
+        self.canvas.erase(rect)
+    }
+
+    /// Remove layer
+    pub fn remove(self) {
+        let msg =  JSMessageTx {
+            element: self.canvas.id(),
+            _type: "remove_layer",
+            name: Some(self.id()),
+            ..Default::default()
+        };
+        self.canvas.send(msg);
+    }
+}
+
+// width and height followed by RGBA bytes
+fn image_data_to_bitmap(data: &[u8]) -> Result<Bitmap> {
+    if data.len() < 8 {
+        return GemGuiError::error("Cannot read image data");
+    }
+    let width = u32::from_le_bytes(data[0..4].try_into().unwrap());
+    let height = u32::from_le_bytes(data[4..8].try_into().unwrap());
+    let pixels = &data[8..];
+    if pixels.len() != (width * height * 4) as usize {
+        return GemGuiError::error(format!("Bad image data {width}x{height} {}", pixels.len()));
+    }
+    let bytes = pixels.chunks_exact(4).map(|p| Color::rgba(p[0], p[1], p[2], p[3])).collect();
+    Ok(Bitmap::from_bytes(width, height, bytes))
+}
+
+// pixels as RGBA bytes
+fn tile_bytes(bitmap: &dyn BitmapData, tile: &Rect<u32>) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity((tile.width * tile.height * 4) as usize);
+    for row in tile.y..(tile.y + tile.height) {
+        for elem in bitmap.slice(tile.x, tile.width, row) {
+            bytes.extend_from_slice(&[Color::r(*elem), Color::g(*elem), Color::b(*elem), Color::a(*elem)]);
+        }
+    }
+    bytes
+}
+
+// pairs of run length and pixel
+fn rle_encode(bitmap: &dyn BitmapData, tile: &Rect<u32>) -> Vec<u8> {
+    let mut runs: Vec<(u32, Color::Pixel)> = Vec::new();
+    for row in tile.y..(tile.y + tile.height) {
+        for elem in bitmap.slice(tile.x, tile.width, row) {
+            match runs.last_mut() {
+                Some((count, pixel)) if pixel == elem => *count += 1,
+                _ => runs.push((1, *elem)),
+            }
+        }
+    }
+    let mut vec8 = Vec::with_capacity(runs.len() * 8);
+    for (count, pixel) in runs {
+        vec8.write_u32::<Endianness>(count).unwrap();
+        vec8.write_u32::<Endianness>(pixel).unwrap();
+    }
+    vec8
+}
+
+fn deflate_encode(bytes: &[u8]) -> Vec<u8> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), flate2::Compression::fast());
+    encoder.write_all(bytes).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn png_encode(bytes: &[u8], tile: &Rect<u32>) -> Option<Vec<u8>> {
+    let mut png = Vec::new();
+    let encoder = PngEncoder::new_with_quality(&mut png, CompressionType::Fast, FilterType::Adaptive);
+    encoder.write_image(bytes, tile.width, tile.height, ColorType::Rgba8).ok()?;
+    Some(png)
+}
+
+// Encoded tile, None if compression does not make it smaller 
+fn compress_tile(bitmap: &dyn BitmapData, tile: &Rect<u32>, compression: Compression) -> Option<(u32, Vec<u8>)> {
+    let raw_size = (tile.width * tile.height * 4) as usize;
+    let encoded = match compression {
+        Compression::None => return None,
+        Compression::Rle => (ENCODING_RLE, rle_encode(bitmap, tile)),
+        Compression::Deflate => (ENCODING_DEFLATE, deflate_encode(&tile_bytes(bitmap, tile))),
+        Compression::Png => (ENCODING_PNG, png_encode(&tile_bytes(bitmap, tile), tile)?),
+        Compression::Auto => {
+            let rle = rle_encode(bitmap, tile);
+            if rle.len() < raw_size / 8 {
+                (ENCODING_RLE, rle) // flat enough, no need to try more
+            } else {
+                let bytes = tile_bytes(bitmap, tile);
+                let mut best = (ENCODING_DEFLATE, deflate_encode(&bytes));
+                if rle.len() < best.1.len() {
+                    best = (ENCODING_RLE, rle);
+                }
+                // poor ratio, image may be natural and PNG filters help
+                if best.1.len() > raw_size / 2 {
+                    if let Some(png) = png_encode(&bytes, tile) {
+                        if png.len() < best.1.len() {
+                            best = (ENCODING_PNG, png);
+                        }
+                    }
+                }
+                best
+            }
+        }
+    };
+    if encoded.1.len() < raw_size {
+        Some(encoded)
+    } else {
+        None
+    }
+}
+
+
 #[cfg(test)]
 mod tests {
 
@@ -520,13 +998,13 @@ mod tests {
     #[test]
     fn test_data() {
         let mut bitmap = Bitmap::new(10, 10);
-        bitmap.put(3, 3, 0x1);
-        bitmap.put(5, 5, 0x100);
-        bitmap.put(9, 9, 0x10000);
+        bitmap.put(3, 3, 0x1u32);
+        bitmap.put(5, 5, 0x100u32);
+        bitmap.put(9, 9, 0x10000u32);
 
         let mut vec8: Vec<u8> = vec![];
         let name = "kissa";
-        let header = vec!(0, 0, bitmap.width() as u32, bitmap.height() as u32, true as u32);
+        let header = vec!(0, 0, bitmap.width(), bitmap.height(), true as u32);
         write_prelude (
             &mut vec8,
             CANVAS_ID,
@@ -604,4 +1082,52 @@ mod tests {
         assert_eq!(name, name_string);
     
     }
+
+    #[test]
+    fn test_image_data_to_bitmap() {
+        let mut data = Vec::new();
+        data.extend_from_slice(&2u32.to_le_bytes());
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(&[0xFF, 0, 0, 0xFF, 1, 2, 3, 4]);
+        let bitmap = image_data_to_bitmap(&data).unwrap();
+        assert_eq!((bitmap.width(), bitmap.height()), (2, 1));
+        assert_eq!(bitmap.get(0, 0), Color::RED);
+        assert_eq!(bitmap.get(1, 0), Color::rgba(1, 2, 3, 4));
+        assert!(image_data_to_bitmap(&data[..10]).is_err());
+        assert!(image_data_to_bitmap(&[]).is_err());
+    }
+
+    #[test]
+    fn test_compress() {
+        let mut bitmap = Bitmap::rect(64, 64, Color::WHITE);
+        bitmap.put(3, 3, Color::BLACK);
+        let tile = Rect::new(0, 0, 64, 64);
+
+        let rle = rle_encode(&bitmap, &tile);
+        let mut c = Cursor::new(rle);
+        let mut pixels = Vec::new();
+        while let Ok(count) = c.read_u32::<Endianness>() {
+            let pixel = c.read_u32::<Endianness>().unwrap();
+            pixels.extend(std::iter::repeat_n(pixel, count as usize));
+        }
+        assert_eq!(pixels.len(), 64 * 64);
+        assert!(pixels.iter().zip(bitmap.iter()).all(|(a, b)| a == b));
+
+        let (encoding, payload) = compress_tile(&bitmap, &tile, Compression::Auto).unwrap();
+        assert_eq!(encoding, ENCODING_RLE);
+        assert!(payload.len() < 64);
+
+        let (encoding, _) = compress_tile(&bitmap, &tile, Compression::Png).unwrap();
+        assert_eq!(encoding, ENCODING_PNG);
+
+        let (encoding, payload) = compress_tile(&bitmap, &tile, Compression::Deflate).unwrap();
+        assert_eq!(encoding, ENCODING_DEFLATE);
+        let mut decoder = flate2::read::ZlibDecoder::new(&payload[..]);
+        let mut bytes = Vec::new();
+        std::io::Read::read_to_end(&mut decoder, &mut bytes).unwrap();
+        assert_eq!(bytes, tile_bytes(&bitmap, &tile));
+
+        let noise = Bitmap::from_bytes(8, 8, (0..64u32).map(|i| i.wrapping_mul(2654435761)).collect());
+        assert!(compress_tile(&noise, &Rect::new(0, 0, 8, 8), Compression::Rle).is_none());
+    }
 }
\ No newline at end of file
@@ -350,6 +350,416 @@ pub fn rgba_from_tuple(tuple: (u8, u8, u8, u8)) -> Pixel {
     rgba(tuple.0, tuple.1, tuple.2, tuple.3)
 }
 
+/// Color value as its own type
+///
+/// Converts from and into [Pixel], parses CSS colors and
+/// converts in between RGB, HSL, HSV and Lab color spaces. Drawing functions such as
+/// [Bitmap::put](crate::graphics::bitmap::Bitmap::put) and
+/// [Context2D::fill_color](crate::graphics::context::Context2D::fill_color) accept it as is.
+///
+/// # Example
+///
+/// ```
+/// # use gemgui::graphics::color::{Color, Pixel, RED, WHITE};
+/// let orange: Color = "hsl(30deg 100% 50%)".parse().unwrap();
+/// assert_eq!(orange, Color::rgb(255, 128, 0));
+/// let pixel: Pixel = orange.darken(0.1).into();
+/// # use gemgui::graphics::bitmap::Bitmap;
+/// let bitmap = Bitmap::rect(2, 2, orange);
+/// assert!(Color::from(RED).contrast_ratio(&Color::from(WHITE)) > 3.9);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Color(Pixel);
+
+impl Color {
+
+    /// Color from components
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Color {
+        Color(r as Pixel | (g as Pixel) << 8 | (b as Pixel) << 16 | (a as Pixel) << 24)
+    }
+
+    /// Opaque color from components
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Color {
+        Self::rgba(r, g, b, 0xFF)
+    }
+
+    /// Red component
+    pub fn r(&self) -> u8 {
+        r(self.0)
+    }
+
+    /// Green component
+    pub fn g(&self) -> u8 {
+        g(self.0)
+    }
+
+    /// Blue component
+    pub fn b(&self) -> u8 {
+        b(self.0)
+    }
+
+    /// Alpha component
+    pub fn a(&self) -> u8 {
+        a(self.0)
+    }
+
+    /// Color as a pixel value
+    pub fn pixel(&self) -> Pixel {
+        self.0
+    }
+
+    /// Same color with another alpha
+    ///
+    /// # Arguments
+    ///
+    /// `alpha` - new alpha
+    pub fn with_alpha(&self, alpha: u8) -> Color {
+        Self::rgba(self.r(), self.g(), self.b(), alpha)
+    }
+
+    /// Parse a CSS color
+    ///
+    /// Named colors, `transparent`, `#rgb`, `#rgba`, `#rrggbb`, `#rrggbbaa`,
+    /// `rgb()`, `rgba()`, `hsl()`, `hsla()` and `hwb()` in both comma and space separated syntax.
+    ///
+    /// # Arguments
+    ///
+    /// `css` - color string
+    ///
+    /// # Return
+    ///
+    /// Color or error if the string is not a color
+    pub fn parse(css: &str) -> crate::Result<Color> {
+        match parse_css(&css.trim().to_lowercase()) {
+            Some(color) => Ok(color),
+            None => crate::GemGuiError::error(format!("Bad color {css}")),
+        }
+    }
+
+    /// Color from HSL
+    ///
+    /// # Arguments
+    ///
+    /// `hue` - hue in degrees
+    ///
+    /// `saturation` - saturation in between 0.0 and 1.0
+    ///
+    /// `lightness` - lightness in between 0.0 and 1.0
+    ///
+    /// `alpha` - alpha in between 0.0 and 1.0
+    pub fn from_hsl(hue: f32, saturation: f32, lightness: f32, alpha: f32) -> Color {
+        let (s, l) = (saturation.clamp(0., 1.), lightness.clamp(0., 1.));
+        let chroma = (1. - (2. * l - 1.).abs()) * s;
+        from_hue(hue, chroma, l - chroma / 2., alpha)
+    }
+
+    /// HSL components
+    ///
+    /// # Return
+    ///
+    /// Hue in degrees, saturation and lightness in between 0.0 and 1.0
+    pub fn to_hsl(&self) -> (f32, f32, f32) {
+        let (hue, max, min) = self.hue();
+        let l = (max + min) / 2.;
+        let s = if max == min {0.} else {(max - min) / (1. - (2. * l - 1.).abs())};
+        (hue, s, l)
+    }
+
+    /// Color from HSV
+    ///
+    /// # Arguments
+    ///
+    /// `hue` - hue in degrees
+    ///
+    /// `saturation` - saturation in between 0.0 and 1.0
+    ///
+    /// `value` - value in between 0.0 and 1.0
+    ///
+    /// `alpha` - alpha in between 0.0 and 1.0
+    pub fn from_hsv(hue: f32, saturation: f32, value: f32, alpha: f32) -> Color {
+        let (s, v) = (saturation.clamp(0., 1.), value.clamp(0., 1.));
+        let chroma = v * s;
+        from_hue(hue, chroma, v - chroma, alpha)
+    }
+
+    /// HSV components
+    ///
+    /// # Return
+    ///
+    /// Hue in degrees, saturation and value in between 0.0 and 1.0
+    pub fn to_hsv(&self) -> (f32, f32, f32) {
+        let (hue, max, min) = self.hue();
+        let s = if max == 0. {0.} else {(max - min) / max};
+        (hue, s, max)
+    }
+
+    /// Color from HWB
+    ///
+    /// # Arguments
+    ///
+    /// `hue` - hue in degrees
+    ///
+    /// `whiteness` - whiteness in between 0.0 and 1.0
+    ///
+    /// `blackness` - blackness in between 0.0 and 1.0
+    ///
+    /// `alpha` - alpha in between 0.0 and 1.0
+    pub fn from_hwb(hue: f32, whiteness: f32, blackness: f32, alpha: f32) -> Color {
+        let (w, b) = (whiteness.clamp(0., 1.), blackness.clamp(0., 1.));
+        if w + b >= 1. {
+            let gray = w / (w + b);
+            return Self::from_hsv(0., 0., gray, alpha);
+        }
+        let v = 1. - b;
+        Self::from_hsv(hue, 1. - w / v, v, alpha)
+    }
+
+    /// Color from CIE L*a*b* with D65 white point
+    ///
+    /// # Arguments
+    ///
+    /// `l` - lightness in between 0.0 and 100.0
+    ///
+    /// `a` - green to red
+    ///
+    /// `b` - blue to yellow
+    ///
+    /// `alpha` - alpha in between 0.0 and 1.0
+    pub fn from_lab(l: f32, a: f32, b: f32, alpha: f32) -> Color {
+        let fy = (l + 16.) / 116.;
+        let fx = fy + a / 500.;
+        let fz = fy - b / 200.;
+        let finv = |t: f32| if t > LAB_DELTA {t * t * t} else {3. * LAB_DELTA * LAB_DELTA * (t - 4. / 29.)};
+        let (x, y, z) = (finv(fx) * D65[0], finv(fy) * D65[1], finv(fz) * D65[2]);
+        let r = 3.240_454_2 * x - 1.537_138_5 * y - 0.498_531_4 * z;
+        let g = -0.969_266 * x + 1.876_010_8 * y + 0.041_556 * z;
+        let b = 0.055_643_4 * x - 0.204_025_9 * y + 1.057_225_2 * z;
+        Self::rgba(unit_to_channel(to_srgb(r)), unit_to_channel(to_srgb(g)), unit_to_channel(to_srgb(b)), unit_to_channel(alpha))
+    }
+
+    /// CIE L*a*b* components with D65 white point
+    ///
+    /// # Return
+    ///
+    /// Lightness in between 0.0 and 100.0, a and b
+    pub fn to_lab(&self) -> (f32, f32, f32) {
+        let (r, g, b) = self.linear();
+        let x = (0.412_456_4 * r + 0.357_576_1 * g + 0.180_437_5 * b) / D65[0];
+        let y = (0.212_672_9 * r + 0.715_152_2 * g + 0.072_175 * b) / D65[1];
+        let z = (0.019_333_9 * r + 0.119_192 * g + 0.950_304_1 * b) / D65[2];
+        let f = |t: f32| if t > LAB_DELTA * LAB_DELTA * LAB_DELTA {t.cbrt()} else {t / (3. * LAB_DELTA * LAB_DELTA) + 4. / 29.};
+        (116. * f(y) - 16., 500. * (f(x) - f(y)), 200. * (f(y) - f(z)))
+    }
+
+    /// Mix with another color
+    ///
+    /// # Arguments
+    ///
+    /// `other` - color mixed in
+    ///
+    /// `amount` - 0.0 is this color and 1.0 is the other
+    pub fn mix(&self, other: &Color, amount: f32) -> Color {
+        let t = amount.clamp(0., 1.);
+        let (a0, a1) = (self.a() as f32 / 255., other.a() as f32 / 255.);
+        let alpha = a0 + (a1 - a0) * t;
+        if alpha <= 0. {
+            return Color(TRANSPARENT);
+        }
+        // premultiplied so that transparent colors do not tint
+        let c = |c0: u8, c1: u8| {
+            let v = (c0 as f32 * a0 + (c1 as f32 * a1 - c0 as f32 * a0) * t) / alpha;
+            v.round().clamp(0., 255.) as u8
+        };
+        Self::rgba(c(self.r(), other.r()), c(self.g(), other.g()), c(self.b(), other.b()), unit_to_channel(alpha))
+    }
+
+    /// This color drawn over a background
+    ///
+    /// # Arguments
+    ///
+    /// `background` - color behind
+    pub fn over(&self, background: &Color) -> Color {
+        let (sa, da) = (self.a() as f32 / 255., background.a() as f32 / 255.);
+        let alpha = sa + da * (1. - sa);
+        if alpha <= 0. {
+            return Color(TRANSPARENT);
+        }
+        let c = |s: u8, d: u8| ((s as f32 * sa + d as f32 * da * (1. - sa)) / alpha).round().clamp(0., 255.) as u8;
+        Self::rgba(c(self.r(), background.r()), c(self.g(), background.g()), c(self.b(), background.b()), unit_to_channel(alpha))
+    }
+
+    /// Lighter color
+    ///
+    /// # Arguments
+    ///
+    /// `amount` - added to HSL lightness, in between 0.0 and 1.0
+    pub fn lighten(&self, amount: f32) -> Color {
+        let (h, s, l) = self.to_hsl();
+        Self::from_hsl(h, s, l + amount, self.a() as f32 / 255.)
+    }
+
+    /// Darker color
+    ///
+    /// # Arguments
+    ///
+    /// `amount` - subtracted from HSL lightness, in between 0.0 and 1.0
+    pub fn darken(&self, amount: f32) -> Color {
+        self.lighten(-amount)
+    }
+
+    /// Relative luminance as defined in WCAG, alpha is ignored
+    pub fn luminance(&self) -> f32 {
+        let (r, g, b) = self.linear();
+        0.2126 * r + 0.7152 * g + 0.0722 * b
+    }
+
+    /// WCAG contrast ratio in between 1.0 and 21.0
+    ///
+    /// # Arguments
+    ///
+    /// `other` - compared color
+    pub fn contrast_ratio(&self, other: &Color) -> f32 {
+        let (l0, l1) = (self.luminance(), other.luminance());
+        (l0.max(l1) + 0.05) / (l0.min(l1) + 0.05)
+    }
+
+    // linear RGB components
+    fn linear(&self) -> (f32, f32, f32) {
+        let f = |c: u8| {
+            let c = c as f32 / 255.;
+            if c <= 0.04045 {c / 12.92} else {((c + 0.055) / 1.055).powf(2.4)}
+        };
+        (f(self.r()), f(self.g()), f(self.b()))
+    }
+
+    // hue in degrees, max and min of components
+    fn hue(&self) -> (f32, f32, f32) {
+        let (r, g, b) = (self.r() as f32 / 255., self.g() as f32 / 255., self.b() as f32 / 255.);
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        let d = max - min;
+        let hue = if d == 0. {
+            0.
+        } else if max == r {
+            60. * ((g - b) / d).rem_euclid(6.)
+        } else if max == g {
+            60. * ((b - r) / d + 2.)
+        } else {
+            60. * ((r - g) / d + 4.)
+        };
+        (hue, max, min)
+    }
+}
+
+static D65: [f32; 3] = [0.950_47, 1.0, 1.088_83];
+static LAB_DELTA: f32 = 6. / 29.;
+
+fn unit_to_channel(v: f32) -> u8 {
+    (v * 255.).round().clamp(0., 255.) as u8
+}
+
+fn to_srgb(c: f32) -> f32 {
+    if c <= 0.003_130_8 {12.92 * c} else {1.055 * c.powf(1. / 2.4) - 0.055}
+}
+
+// color of a hue with chroma and an amount added to each component
+fn from_hue(hue: f32, chroma: f32, m: f32, alpha: f32) -> Color {
+    let h = hue.rem_euclid(360.) / 60.;
+    let x = chroma * (1. - (h % 2. - 1.).abs());
+    let (r, g, b) = match h as u32 {
+        0 => (chroma, x, 0.),
+        1 => (x, chroma, 0.),
+        2 => (0., chroma, x),
+        3 => (0., x, chroma),
+        4 => (x, 0., chroma),
+        _ => (chroma, 0., x),
+    };
+    Color::rgba(unit_to_channel(r + m), unit_to_channel(g + m), unit_to_channel(b + m), unit_to_channel(alpha))
+}
+
+fn parse_css(css: &str) -> Option<Color> {
+    if css == "transparent" {
+        return Some(Color(TRANSPARENT));
+    }
+    if let Some(hex) = css.strip_prefix('#') {
+        let digits: Vec<u8> = hex.chars().map(|c| c.to_digit(16).map(|d| d as u8)).collect::<Option<_>>()?;
+        let v: Vec<u8> = match digits.len() {
+            3 | 4 => digits.iter().map(|d| d * 17).collect(),
+            6 | 8 => digits.chunks(2).map(|d| d[0] * 16 + d[1]).collect(),
+            _ => return None,
+        };
+        return Some(Color::rgba(v[0], v[1], v[2], v.get(3).copied().unwrap_or(0xFF)));
+    }
+    if let Some((name, args)) = css.split_once('(') {
+        let args: Vec<&str> = args.strip_suffix(')')?
+            .split(|c: char| c == ',' || c == '/' || c.is_whitespace())
+            .filter(|s| !s.is_empty())
+            .collect();
+        if args.len() != 3 && args.len() != 4 {
+            return None;
+        }
+        let alpha = match args.get(3) {
+            Some(a) => css_number(a, 1.)?,
+            None => 1.,
+        };
+        return match name.trim() {
+            "rgb" | "rgba" => Some(Color::rgba(
+                (css_number(args[0], 255.)? * 255.).round().clamp(0., 255.) as u8,
+                (css_number(args[1], 255.)? * 255.).round().clamp(0., 255.) as u8,
+                (css_number(args[2], 255.)? * 255.).round().clamp(0., 255.) as u8,
+                unit_to_channel(alpha))),
+            "hsl" | "hsla" => Some(Color::from_hsl(css_angle(args[0])?, css_number(args[1], 100.)?, css_number(args[2], 100.)?, alpha)),
+            "hwb" => Some(Color::from_hwb(css_angle(args[0])?, css_number(args[1], 100.)?, css_number(args[2], 100.)?, alpha)),
+            _ => None,
+        };
+    }
+    name_to_rgb(css).map(Color)
+}
+
+// number or percentage as a fraction of the range
+fn css_number(value: &str, range: f32) -> Option<f32> {
+    match value.strip_suffix('%') {
+        Some(p) => p.parse::<f32>().ok().map(|v| v / 100.),
+        None => value.parse::<f32>().ok().map(|v| v / range),
+    }
+}
+
+// angle in degrees
+fn css_angle(value: &str) -> Option<f32> {
+    for (unit, degrees) in [("deg", 1.), ("grad", 0.9), ("rad", 180. / std::f32::consts::PI), ("turn", 360.)] {
+        if let Some(v) = value.strip_suffix(unit) {
+            return v.parse::<f32>().ok().map(|v| v * degrees);
+        }
+    }
+    value.parse::<f32>().ok()
+}
+
+impl From<Pixel> for Color {
+    fn from(pixel: Pixel) -> Color {
+        Color(pixel)
+    }
+}
+
+impl From<Color> for Pixel {
+    fn from(color: Color) -> Pixel {
+        color.0
+    }
+}
+
+impl std::str::FromStr for Color {
+    type Err = crate::GemGuiError;
+
+    fn from_str(s: &str) -> crate::Result<Color> {
+        Color::parse(s)
+    }
+}
+
+impl std::fmt::Display for Color {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", to_string(self.0))
+    }
+}
+
 #[test]
 fn test_rgb() {
     let col1 = rgb(0x33, 0x44, 0x55);
@@ -452,6 +862,75 @@ mod tests {
 
     }
 
+    #[test]
+    fn test_css_color() {
+        let parse = |s: &str| Color::parse(s).unwrap();
+        assert_eq!(parse("#f00"), Color::from(RED));
+        assert_eq!(parse("#0000ff80"), Color::rgba(0, 0, 0xFF, 0x80));
+        assert_eq!(parse("#00F8"), Color::rgba(0, 0, 0xFF, 0x88));
+        assert_eq!(parse("rgb(255, 0, 0)"), Color::from(RED));
+        assert_eq!(parse("rgba(0, 255, 0, 0.5)"), Color::rgba(0, 0xFF, 0, 128));
+        assert_eq!(parse("rgb(0 0 255 / 50%)"), Color::rgba(0, 0, 0xFF, 128));
+        assert_eq!(parse("rgb(100%, 50%, 0%)"), Color::rgb(255, 128, 0));
+        assert_eq!(parse("hsl(120, 100%, 25%)"), Color::rgb(0, 128, 0));
+        assert_eq!(parse("hsla(0.5turn 100% 50% / 0.25)"), Color::rgba(0, 0xFF, 0xFF, 64));
+        assert_eq!(parse("hwb(0 0% 0%)"), Color::from(RED));
+        assert_eq!(parse("hwb(90 50% 50%)"), Color::rgb(128, 128, 128));
+        assert_eq!(parse("RebeccaPurple"), Color::rgb(0x66, 0x33, 0x99));
+        assert_eq!(parse(" transparent "), Color::from(TRANSPARENT));
+        assert!(Color::parse("#12345").is_err());
+        assert!(Color::parse("rgb(1, 2)").is_err());
+        assert!(Color::parse("nocolor").is_err());
+        assert_eq!("#112233".parse::<Color>().unwrap().to_string(), "#112233");
+        let pixel: Pixel = Color::rgba(1, 2, 3, 4).into();
+        assert_eq!(pixel, rgba(1, 2, 3, 4));
+    }
+
+    #[test]
+    fn test_color_spaces() {
+        let c = Color::rgb(51, 102, 153);
+        let (h, s, l) = c.to_hsl();
+        assert_eq!((h.round(), (s * 100.).round(), (l * 100.).round()), (210., 50., 40.));
+        assert_eq!(Color::from_hsl(h, s, l, 1.), c);
+        let (h, s, v) = c.to_hsv();
+        assert_eq!((h.round(), (s * 100.).round(), (v * 100.).round()), (210., 67., 60.));
+        assert_eq!(Color::from_hsv(h, s, v, 1.), c);
+        let (l, a, b) = Color::from(WHITE).to_lab();
+        assert!((l - 100.).abs() < 0.01 && a.abs() < 0.01 && b.abs() < 0.01);
+        let (l, a, b) = c.to_lab();
+        assert_eq!(Color::from_lab(l, a, b, 1.), c);
+        let (l, a, b) = Color::from(RED).to_lab();
+        assert_eq!((l.round(), a.round(), b.round()), (53., 80., 67.));
+    }
+
+    #[test]
+    fn test_color_helpers() {
+        let (black, white) = (Color::from(BLACK), Color::from(WHITE));
+        assert!((black.contrast_ratio(&white) - 21.).abs() < 0.01);
+        assert_eq!(white.contrast_ratio(&white), 1.);
+        assert_eq!(black.mix(&white, 0.5), Color::rgb(128, 128, 128));
+        assert_eq!(Color::from(TRANSPARENT).mix(&Color::from(RED), 0.5), Color::rgba(0xFF, 0, 0, 128));
+        assert_eq!(Color::from(RED).with_alpha(128).over(&white), Color::rgb(255, 127, 127));
+        assert_eq!(Color::from(RED).lighten(0.25), Color::rgb(255, 128, 128));
+        assert_eq!(Color::from(RED).darken(0.25), Color::rgb(128, 0, 0));
+    }
+
+    #[test]
+    fn test_color_arguments() {
+        use crate::graphics::bitmap::{Bitmap, BitmapData};
+        use crate::graphics::context::Context2D;
+        use crate::graphics::raster::Renderer;
+        let orange = Color::rgb(255, 128, 0);
+        let mut bitmap = Bitmap::rect(4, 4, Color::from(BLUE));
+        bitmap.put(1, 1, orange);
+        assert_eq!(bitmap.get(1, 1), orange.pixel());
+        assert_eq!(bitmap.get(0, 0), BLUE);
+        let mut ctx = Context2D::new();
+        ctx.fill_color(orange).fill_rect(&crate::Rect::new(0., 0., 4., 4.));
+        Renderer::new().draw_context(&mut bitmap, &ctx).unwrap();
+        assert_eq!(bitmap.get(3, 3), orange.pixel());
+    }
+
     #[test]
     fn test_rgb_to_name() {
 
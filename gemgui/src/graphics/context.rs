@@ -1,16 +1,127 @@
 use std::fmt;
 
+use byteorder::{WriteBytesExt, LittleEndian};
+
 use crate::{graphics::{color as Color}, Rect};
 
 /// Context holding draw commands
 #[derive(Clone)]
 pub struct Context2D {
-    commands: Vec<String>,
+    commands: Vec<Arg>,
 }
 
 /// Floating point type
 pub type Float = f32;
 
+// command and arguments, numbers are kept as numbers until sent 
+#[derive(Clone, Debug, PartialEq)]
+pub (crate) enum Arg {
+    Command(&'static str),
+    Number(Float),
+    Text(String),
+}
+
+impl From<Float> for Arg {
+    fn from(value: Float) -> Self {
+        Arg::Number(value)
+    }
+}
+
+impl From<usize> for Arg {
+    fn from(value: usize) -> Self {
+        Arg::Number(value as Float)
+    }
+}
+
+impl From<&str> for Arg {
+    fn from(value: &str) -> Self {
+        Arg::Text(value.to_string())
+    }
+}
+
+impl From<String> for Arg {
+    fn from(value: String) -> Self {
+        Arg::Text(value)
+    }
+}
+
+impl From<bool> for Arg {
+    fn from(value: bool) -> Self {
+        Arg::Text(value.to_string())
+    }
+}
+
+impl fmt::Display for Arg {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Command(c) => write!(f, "{c}"),
+            Self::Number(n) => write!(f, "{n}"),
+            Self::Text(t) => write!(f, "{t}"),
+        }
+    }
+}
+
+// Binary opcodes, the index in canvasOpcodes in gemgui.js must match
+static OPCODES: phf::Map<&'static str, u32> = phf::phf_map! {
+    "strokeRect" => 0,
+    "clearRect" => 1,
+    "fillRect" => 2,
+    "fillText" => 3,
+    "strokeText" => 4,
+    "arc" => 5,
+    "ellipse" => 6,
+    "beginPath" => 7,
+    "closePath" => 8,
+    "lineTo" => 9,
+    "moveTo" => 10,
+    "bezierCurveTo" => 11,
+    "quadraticCurveTo" => 12,
+    "arcTo" => 13,
+    "rect" => 14,
+    "stroke" => 15,
+    "fill" => 16,
+    "fillStyle" => 17,
+    "strokeStyle" => 18,
+    "lineWidth" => 19,
+    "font" => 20,
+    "textAlign" => 21,
+    "save" => 22,
+    "restore" => 23,
+    "rotate" => 24,
+    "translate" => 25,
+    "scale" => 26,
+    "drawImage" => 27,
+    "drawImageRect" => 28,
+    "drawImageClip" => 29,
+    "textBaseline" => 30,
+    "fillGradient" => 31,
+    "strokeGradient" => 32,
+    "fillPattern" => 33,
+    "strokePattern" => 34,
+    "setLineDash" => 35,
+    "lineDashOffset" => 36,
+    "lineCap" => 37,
+    "lineJoin" => 38,
+    "miterLimit" => 39,
+    "globalAlpha" => 40,
+    "globalCompositeOperation" => 41,
+    "shadowColor" => 42,
+    "shadowBlur" => 43,
+    "shadowOffset" => 44,
+    "clip" => 45,
+    "setTransform" => 46,
+    "transform" => 47,
+    "resetTransform" => 48,
+    "roundRect" => 49,
+    "filter" => 50,
+    "imageSmoothingEnabled" => 51,
+    "imageSmoothingQuality" => 52,
+};
+
+// command header bits
+const TEXT_FLAG: u32 = 0x100;
+const ARGC_SHIFT: u32 = 16;
+
 /// TextAlignment
 pub enum TextAlign {
      /// Left align data, left justify text.
@@ -66,11 +177,253 @@ impl fmt::Display for TextBaseLine {
     }
 }
 
+/// Line end style
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LineCap {
+    /// Default. Line ends at the end point.
+    Butt,
+    /// Rounded end.
+    Round,
+    /// Square end that extends over the end point.
+    Square,
+}
+
+impl fmt::Display for LineCap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Butt => write!(f, "butt"),
+            Self::Round => write!(f, "round"),
+            Self::Square => write!(f, "square"),
+        }
+    }
+}
+
+/// Line corner style
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LineJoin {
+    /// Rounded corner.
+    Round,
+    /// Beveled corner.
+    Bevel,
+    /// Default. Sharp corner, see [Context2D::miter_limit].
+    Miter,
+}
+
+impl fmt::Display for LineJoin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Round => write!(f, "round"),
+            Self::Bevel => write!(f, "bevel"),
+            Self::Miter => write!(f, "miter"),
+        }
+    }
+}
+
+/// How new drawing is composited with existing content
+pub enum CompositeOperation {
+    /// Default. Draw new over existing.
+    SourceOver,
+    /// Draw new only where it overlaps existing.
+    SourceIn,
+    /// Draw new only where it does not overlap existing.
+    SourceOut,
+    /// Draw new only where it overlaps existing, keep existing.
+    SourceAtop,
+    /// Draw new behind existing.
+    DestinationOver,
+    /// Keep existing only where it overlaps new.
+    DestinationIn,
+    /// Keep existing only where it does not overlap new.
+    DestinationOut,
+    /// Keep existing only where it overlaps new, draw new behind.
+    DestinationAtop,
+    /// Add colors where shapes overlap.
+    Lighter,
+    /// Show only new.
+    Copy,
+    /// Transparent where shapes overlap.
+    Xor,
+    /// Multiply colors.
+    Multiply,
+    /// Inverted multiply of inverted colors.
+    Screen,
+    /// Multiply or screen depending on existing.
+    Overlay,
+    /// Darker of the colors.
+    Darken,
+    /// Lighter of the colors.
+    Lighten,
+    /// Divide existing by inverted new.
+    ColorDodge,
+    /// Divide inverted existing by new, invert result.
+    ColorBurn,
+    /// Multiply or screen depending on new.
+    HardLight,
+    /// Softer version of hard light.
+    SoftLight,
+    /// Subtract darker from lighter.
+    Difference,
+    /// Like difference with lower contrast.
+    Exclusion,
+    /// Hue of new, luma and chroma of existing.
+    Hue,
+    /// Chroma of new, luma and hue of existing.
+    Saturation,
+    /// Hue and chroma of new, luma of existing.
+    Color,
+    /// Luma of new, hue and chroma of existing.
+    Luminosity,
+}
+
+impl fmt::Display for CompositeOperation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let value = match self {
+            Self::SourceOver => "source-over",
+            Self::SourceIn => "source-in",
+            Self::SourceOut => "source-out",
+            Self::SourceAtop => "source-atop",
+            Self::DestinationOver => "destination-over",
+            Self::DestinationIn => "destination-in",
+            Self::DestinationOut => "destination-out",
+            Self::DestinationAtop => "destination-atop",
+            Self::Lighter => "lighter",
+            Self::Copy => "copy",
+            Self::Xor => "xor",
+            Self::Multiply => "multiply",
+            Self::Screen => "screen",
+            Self::Overlay => "overlay",
+            Self::Darken => "darken",
+            Self::Lighten => "lighten",
+            Self::ColorDodge => "color-dodge",
+            Self::ColorBurn => "color-burn",
+            Self::HardLight => "hard-light",
+            Self::SoftLight => "soft-light",
+            Self::Difference => "difference",
+            Self::Exclusion => "exclusion",
+            Self::Hue => "hue",
+            Self::Saturation => "saturation",
+            Self::Color => "color",
+            Self::Luminosity => "luminosity",
+        };
+        write!(f, "{value}")
+    }
+}
+
+/// Pattern repetition
+pub enum Repetition {
+    /// Default. Repeat both directions.
+    Repeat,
+    /// Repeat horizontally.
+    RepeatX,
+    /// Repeat vertically.
+    RepeatY,
+    /// Draw once.
+    NoRepeat,
+}
+
+impl fmt::Display for Repetition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Repeat => write!(f, "repeat"),
+            Self::RepeatX => write!(f, "repeat-x"),
+            Self::RepeatY => write!(f, "repeat-y"),
+            Self::NoRepeat => write!(f, "no-repeat"),
+        }
+    }
+}
+
+/// Image smoothing quality
+pub enum SmoothingQuality {
+    /// Default.
+    Low,
+    /// Medium.
+    Medium,
+    /// High.
+    High,
+}
+
+impl fmt::Display for SmoothingQuality {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Low => write!(f, "low"),
+            Self::Medium => write!(f, "medium"),
+            Self::High => write!(f, "high"),
+        }
+    }
+}
+
+/// Gradient used as a fill or stroke style
+/// 
+/// # Example
+/// 
+/// ```
+/// # use gemgui::graphics::context::{Context2D, Gradient};
+/// # use gemgui::graphics::color as Color;
+/// let gradient = Gradient::linear(0., 0., 100., 0.)
+///     .color_stop(0., Color::RED)
+///     .color_stop(1., Color::BLUE);
+/// let mut ctx = Context2D::new();
+/// ctx.fill_gradient(&gradient).fill_rect(&gemgui::Rect::new(0., 0., 100., 100.));
+/// ```
+#[derive(Clone)]
+pub struct Gradient {
+    values: Vec<Arg>,
+    stops: Vec<(Float, Color::Pixel)>,
+}
+
+impl Gradient {
+    /// Linear gradient along a line
+    pub fn linear(x0: Float, y0: Float, x1: Float, y1: Float) -> Gradient {
+        Gradient {
+            values: vec!(Arg::from("linear"), x0.into(), y0.into(), x1.into(), y1.into()),
+            stops: Vec::new(),
+        }
+    }
+
+    /// Radial gradient in between two circles
+    pub fn radial(x0: Float, y0: Float, r0: Float, x1: Float, y1: Float, r1: Float) -> Gradient {
+        Gradient {
+            values: vec!(Arg::from("radial"), x0.into(), y0.into(), r0.into(), x1.into(), y1.into(), r1.into()),
+            stops: Vec::new(),
+        }
+    }
+
+    /// Conic gradient around a point
+    pub fn conic(start_angle: Float, x: Float, y: Float) -> Gradient {
+        Gradient {
+            values: vec!(Arg::from("conic"), start_angle.into(), x.into(), y.into()),
+            stops: Vec::new(),
+        }
+    }
+
+    /// Add color stop
+    /// 
+    /// # Arguments
+    /// 
+    /// `offset` - position in between 0.0 and 1.0
+    /// 
+    /// `color` - color at the offset, a Pixel or a Color
+    pub fn color_stop(mut self, offset: Float, color: impl Into<Color::Pixel>) -> Gradient {
+        self.stops.push((offset, color.into()));
+        self
+    }
+
+    fn compose(&self, commands: &mut Vec<Arg>) {
+        commands.extend(self.values.iter().cloned());
+        commands.push(self.stops.len().into());
+        for (offset, color) in &self.stops {
+            commands.push((*offset).into());
+            commands.push(Color::to_string(*color).into());
+        }
+    }
+}
+
 macro_rules! push {
-    ( $self:ident, $($x:expr ),* ) => {
+    ( $self:ident, $command:literal $(, $x:expr )* ) => {
         {
+            $self.commands.push(Arg::Command($command));
             $(
-                $self.commands.push($x.to_string());
+                $self.commands.push(Arg::from($x));
             )*
             return $self
         }
@@ -190,8 +543,8 @@ impl Context2D {
     }
 
     /// <https://www.w3schools.com/graphics/canvas_reference.asp>
-    pub fn fill_color(&'_ mut self, color: Color::Pixel) -> &'_ mut  Context2D {
-         push!(self, "fillStyle", Color::to_string(color));
+    pub fn fill_color(&'_ mut self, color: impl Into<Color::Pixel>) -> &'_ mut  Context2D {
+         push!(self, "fillStyle", Color::to_string(color.into()));
     }
 
     /// <https://www.w3schools.com/graphics/canvas_reference.asp>
@@ -200,8 +553,8 @@ impl Context2D {
     }
 
     /// <https://www.w3schools.com/graphics/canvas_reference.asp>
-    pub fn stroke_color(&'_ mut self, color: Color::Pixel) -> &'_ mut  Context2D {
-        push!(self, "strokeStyle", Color::to_string(color))
+    pub fn stroke_color(&'_ mut self, color: impl Into<Color::Pixel>) -> &'_ mut  Context2D {
+        push!(self, "strokeStyle", Color::to_string(color.into()))
     }
 
     /// <https://www.w3schools.com/graphics/canvas_reference.asp>
@@ -265,9 +618,191 @@ impl Context2D {
         push!(self, "textBaseline", base_line.to_string())
     }
 
-    /// <https://www.w3schools.com/graphics/canvas_reference.asp>
-    pub (crate) fn composed(&self) -> &Vec<String> {
-        &self.commands
+    /// <https://developer.mozilla.org/en-US/docs/Web/API/CanvasRenderingContext2D/fillStyle>
+    pub fn fill_gradient(&'_ mut self, gradient: &Gradient) -> &'_ mut  Context2D {
+        self.commands.push(Arg::Command("fillGradient"));
+        gradient.compose(&mut self.commands);
+        self
+    }
+
+    /// <https://developer.mozilla.org/en-US/docs/Web/API/CanvasRenderingContext2D/strokeStyle>
+    pub fn stroke_gradient(&'_ mut self, gradient: &Gradient) -> &'_ mut  Context2D {
+        self.commands.push(Arg::Command("strokeGradient"));
+        gradient.compose(&mut self.commands);
+        self
+    }
+
+    /// <https://developer.mozilla.org/en-US/docs/Web/API/CanvasRenderingContext2D/createPattern>
+    pub fn fill_pattern(&'_ mut self, id: &str, repetition: Repetition) -> &'_ mut  Context2D {
+        push!(self, "fillPattern", id, repetition.to_string())
+    }
+
+    /// <https://developer.mozilla.org/en-US/docs/Web/API/CanvasRenderingContext2D/createPattern>
+    pub fn stroke_pattern(&'_ mut self, id: &str, repetition: Repetition) -> &'_ mut  Context2D {
+        push!(self, "strokePattern", id, repetition.to_string())
+    }
+
+    /// <https://developer.mozilla.org/en-US/docs/Web/API/CanvasRenderingContext2D/setLineDash>
+    pub fn set_line_dash(&'_ mut self, segments: &[Float]) -> &'_ mut  Context2D {
+        self.commands.push(Arg::Command("setLineDash"));
+        self.commands.push(segments.len().into());
+        self.commands.extend(segments.iter().map(|s| Arg::from(*s)));
+        self
+    }
+
+    /// <https://developer.mozilla.org/en-US/docs/Web/API/CanvasRenderingContext2D/lineDashOffset>
+    pub fn line_dash_offset(&'_ mut self, offset: Float) -> &'_ mut  Context2D {
+        push!(self, "lineDashOffset", offset)
+    }
+
+    /// <https://developer.mozilla.org/en-US/docs/Web/API/CanvasRenderingContext2D/lineCap>
+    pub fn line_cap(&'_ mut self, cap: LineCap) -> &'_ mut  Context2D {
+        push!(self, "lineCap", cap.to_string())
+    }
+
+    /// <https://developer.mozilla.org/en-US/docs/Web/API/CanvasRenderingContext2D/lineJoin>
+    pub fn line_join(&'_ mut self, join: LineJoin) -> &'_ mut  Context2D {
+        push!(self, "lineJoin", join.to_string())
+    }
+
+    /// <https://developer.mozilla.org/en-US/docs/Web/API/CanvasRenderingContext2D/miterLimit>
+    pub fn miter_limit(&'_ mut self, limit: Float) -> &'_ mut  Context2D {
+        push!(self, "miterLimit", limit)
+    }
+
+    /// <https://developer.mozilla.org/en-US/docs/Web/API/CanvasRenderingContext2D/globalAlpha>
+    pub fn global_alpha(&'_ mut self, alpha: Float) -> &'_ mut  Context2D {
+        push!(self, "globalAlpha", alpha)
+    }
+
+    /// <https://developer.mozilla.org/en-US/docs/Web/API/CanvasRenderingContext2D/globalCompositeOperation>
+    pub fn global_composite_operation(&'_ mut self, operation: CompositeOperation) -> &'_ mut  Context2D {
+        push!(self, "globalCompositeOperation", operation.to_string())
+    }
+
+    /// <https://developer.mozilla.org/en-US/docs/Web/API/CanvasRenderingContext2D/shadowColor>
+    pub fn shadow_color(&'_ mut self, color: impl Into<Color::Pixel>) -> &'_ mut  Context2D {
+        push!(self, "shadowColor", Color::to_string(color.into()))
+    }
+
+    /// <https://developer.mozilla.org/en-US/docs/Web/API/CanvasRenderingContext2D/shadowBlur>
+    pub fn shadow_blur(&'_ mut self, blur: Float) -> &'_ mut  Context2D {
+        push!(self, "shadowBlur", blur)
+    }
+
+    /// <https://developer.mozilla.org/en-US/docs/Web/API/CanvasRenderingContext2D/shadowOffsetX>
+    pub fn shadow_offset(&'_ mut self, x: Float, y: Float) -> &'_ mut  Context2D {
+        push!(self, "shadowOffset", x, y)
+    }
+
+    /// <https://developer.mozilla.org/en-US/docs/Web/API/CanvasRenderingContext2D/clip>
+    pub fn clip(&'_ mut self) -> &'_ mut  Context2D {
+        push!(self, "clip")
+    }
+
+    /// <https://developer.mozilla.org/en-US/docs/Web/API/CanvasRenderingContext2D/setTransform>
+    pub fn set_transform(&'_ mut self, a: Float, b: Float, c: Float, d: Float, e: Float, f: Float) -> &'_ mut  Context2D {
+        push!(self, "setTransform", a, b, c, d, e, f)
+    }
+
+    /// <https://developer.mozilla.org/en-US/docs/Web/API/CanvasRenderingContext2D/transform>
+    pub fn transform(&'_ mut self, a: Float, b: Float, c: Float, d: Float, e: Float, f: Float) -> &'_ mut  Context2D {
+        push!(self, "transform", a, b, c, d, e, f)
+    }
+
+    /// <https://developer.mozilla.org/en-US/docs/Web/API/CanvasRenderingContext2D/resetTransform>
+    pub fn reset_transform(&'_ mut self) -> &'_ mut  Context2D {
+        push!(self, "resetTransform")
+    }
+
+    /// <https://developer.mozilla.org/en-US/docs/Web/API/CanvasRenderingContext2D/roundRect>
+    /// 
+    /// `radii` - one to four corner radii as in CSS border-radius
+    pub fn round_rect(&'_ mut self, rect: &Rect<Float>, radii: &[Float]) -> &'_ mut  Context2D {
+        self.commands.extend([Arg::Command("roundRect"), rect.x.into(), rect.y.into(),
+            rect.width.into(), rect.height.into(), radii.len().into()]);
+        self.commands.extend(radii.iter().map(|r| Arg::from(*r)));
+        self
+    }
+
+    /// <https://developer.mozilla.org/en-US/docs/Web/API/CanvasRenderingContext2D/filter>
+    pub fn filter(&'_ mut self, filter: &str) -> &'_ mut  Context2D {
+        push!(self, "filter", filter)
+    }
+
+    /// <https://developer.mozilla.org/en-US/docs/Web/API/CanvasRenderingContext2D/imageSmoothingEnabled>
+    pub fn image_smoothing(&'_ mut self, enabled: bool) -> &'_ mut  Context2D {
+        push!(self, "imageSmoothingEnabled", enabled)
+    }
+
+    /// <https://developer.mozilla.org/en-US/docs/Web/API/CanvasRenderingContext2D/imageSmoothingQuality>
+    pub fn image_smoothing_quality(&'_ mut self, quality: SmoothingQuality) -> &'_ mut  Context2D {
+        push!(self, "imageSmoothingQuality", quality.to_string())
+    }
+
+    /// Whether context has no commands
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    // commands as strings
+    #[cfg(test)]
+    pub (crate) fn composed(&self) -> Vec<String> {
+        self.commands.iter().map(|c| c.to_string()).collect()
+    }
+
+    // commands as binary opcodes, each command is a header word having opcode, a text flag and an argument count,
+    // if text flag is set, bitmasks of text arguments follows. Then numbers are f32 and texts are byte length 
+    // and UTF-8 padded to words.
+    pub (crate) fn encoded(&self, vec8: &mut Vec<u8>) -> u32 {
+        let mut count = 0;
+        for (command, args) in self.commands() {
+            let opcode = *OPCODES.get(command).unwrap_or_else(|| panic!("No opcode for {command}"));
+            let has_text = args.iter().any(|a| matches!(a, Arg::Text(_)));
+            let header = opcode | if has_text {TEXT_FLAG} else {0} | ((args.len() as u32) << ARGC_SHIFT);
+            vec8.write_u32::<LittleEndian>(header).unwrap();
+            if has_text {
+                for mask_args in args.chunks(32) {
+                    let mask = mask_args.iter().enumerate()
+                        .filter(|(_, a)| matches!(a, Arg::Text(_)))
+                        .fold(0u32, |mask, (i, _)| mask | (1 << i));
+                    vec8.write_u32::<LittleEndian>(mask).unwrap();
+                }
+            }
+            for arg in args {
+                match arg {
+                    Arg::Number(n) => vec8.write_f32::<LittleEndian>(*n).unwrap(),
+                    Arg::Text(t) => {
+                        vec8.write_u32::<LittleEndian>(t.len() as u32).unwrap();
+                        vec8.extend_from_slice(t.as_bytes());
+                        vec8.resize(vec8.len() + (4 - t.len() % 4) % 4, 0);
+                    },
+                    Arg::Command(_) => unreachable!(),
+                }
+            }
+            count += 1;
+        }
+        count
+    }
+
+    // commands with their arguments
+    pub (crate) fn commands(&self) -> impl Iterator<Item = (&'static str, &[Arg])> {
+        let mut pos = 0;
+        std::iter::from_fn(move || {
+            if pos >= self.commands.len() {
+                return None;
+            }
+            let command = match &self.commands[pos] {
+                Arg::Command(c) => *c,
+                other => panic!("Command expected, got {other}"),
+            };
+            let end = self.commands[pos + 1..].iter()
+                .position(|a| matches!(a, Arg::Command(_)))
+                .map_or(self.commands.len(), |p| pos + 1 + p);
+            let args = &self.commands[pos + 1..end];
+            pos = end;
+            Some((command, args))
+        })
     }
 
    
@@ -276,6 +811,10 @@ impl Context2D {
 #[cfg(test)]
 mod tests {
 
+    use std::io::Cursor;
+
+    use byteorder::ReadBytesExt;
+
     use super::*;    
 
     #[test]
@@ -326,6 +865,57 @@ mod tests {
         let count = content.iter().filter(|s| s.contains("drawImage")).count();
         assert_eq!(count, 3);
 
+        let gradient = Gradient::radial(0., 0., 1., 2., 3., 4.)
+            .color_stop(0., Color::RED)
+            .color_stop(1., Color::BLUE);
+        let mut ctx = Context2D::new();
+        ctx.fill_gradient(&gradient)
+        .stroke_pattern(image_id, Repetition::RepeatX)
+        .set_line_dash(&[1., 2.])
+        .line_cap(LineCap::Round)
+        .line_join(LineJoin::Bevel)
+        .global_composite_operation(CompositeOperation::DestinationOver)
+        .round_rect(&rect, &[5.])
+        .image_smoothing(false);
+        let content = ctx.composed();
+        assert_eq!(content[0..9], ["fillGradient", "radial", "0", "0", "1", "2", "3", "4", "2"]);
+        assert_eq!(content[9], "0");
+        assert!(content.contains(&"repeat-x".to_string()));
+        assert!(content.contains(&"destination-over".to_string()));
+        assert!(content.contains(&"false".to_string()));
+        let pos = content.iter().position(|s| s == "setLineDash").unwrap();
+        assert_eq!(content[pos + 1..pos + 4], ["2", "1", "2"]);
+    }
+
+    #[test]
+    fn test_encoded() {
+        let mut ctx = Context2D::new();
+        ctx.line_to(1., 2.)
+        .fill_text("abcde", 3., 4.)
+        .stroke();
+        let mut vec8 = Vec::new();
+        assert_eq!(ctx.encoded(&mut vec8), 3);
+        assert_eq!(vec8.len() % 4, 0);
+        let mut c = Cursor::new(vec8);
+        let read32 = |c: &mut Cursor<Vec<u8>>| c.read_u32::<LittleEndian>().unwrap();
+        let readf = |c: &mut Cursor<Vec<u8>>| c.read_f32::<LittleEndian>().unwrap();
+        
+        assert_eq!(read32(&mut c), OPCODES["lineTo"] | (2 << ARGC_SHIFT));
+        assert_eq!(readf(&mut c), 1.);
+        assert_eq!(readf(&mut c), 2.);
+
+        assert_eq!(read32(&mut c), OPCODES["fillText"] | TEXT_FLAG | (3 << ARGC_SHIFT));
+        assert_eq!(read32(&mut c), 0b1); // mask
+        assert_eq!(read32(&mut c), 5);
+        let mut text = [0u8; 8];
+        std::io::Read::read_exact(&mut c, &mut text).unwrap();
+        assert_eq!(&text[..5], b"abcde");
+        assert_eq!(readf(&mut c), 3.);
+        assert_eq!(readf(&mut c), 4.);
+
+        assert_eq!(read32(&mut c), OPCODES["stroke"]);
+        assert!(c.read_u32::<LittleEndian>().is_err());
+
     }
 
 
@@ -0,0 +1,473 @@
+use rayon::prelude::*;
+
+use crate::GemGuiError;
+use crate::Result;
+use crate::graphics::bitmap::{Bitmap, BitmapData, Bytes};
+use crate::graphics::color as Color;
+
+use Color::Pixel;
+
+// Rows of a new bitmap are produced in parallel from a copy of the source pixels
+fn map_rows<F>(bitmap: &dyn BitmapData, f: F) -> Bitmap
+where F: Fn(usize, &[Pixel], &mut [Pixel]) + Sync {
+    let (width, height) = (bitmap.width(), bitmap.height());
+    if width == 0 || height == 0 {
+        return Bitmap::new(width, height);
+    }
+    let source: Bytes = bitmap.iter().copied().collect();
+    let mut target = vec![Color::TRANSPARENT; source.len()];
+    target.par_chunks_mut(width as usize)
+        .enumerate()
+        .for_each(|(y, row)| f(y, &source, row));
+    Bitmap::from_bytes(width, height, target)
+}
+
+fn map_pixels<F>(bitmap: &dyn BitmapData, f: F) -> Bitmap
+where F: Fn(Pixel) -> Pixel + Sync {
+    map_rows(bitmap, |y, source, row| {
+        let width = row.len();
+        for (target, pixel) in row.iter_mut().zip(&source[y * width..(y + 1) * width]) {
+            *target = f(*pixel);
+        }
+    })
+}
+
+// Table is applied to color channels, alpha is kept
+fn map_channels(bitmap: &dyn BitmapData, table: &[u8; 256]) -> Bitmap {
+    map_pixels(bitmap, |p| Color::rgba(
+        table[Color::r(p) as usize],
+        table[Color::g(p) as usize],
+        table[Color::b(p) as usize],
+        Color::a(p)))
+}
+
+fn channel_table<F>(f: F) -> [u8; 256]
+where F: Fn(f32) -> f32 {
+    let mut table = [0; 256];
+    for (i, value) in table.iter_mut().enumerate() {
+        *value = (f(i as f32 / 255.) * 255.).round().clamp(0., 255.) as u8;
+    }
+    table
+}
+
+/// Perceived brightness of a pixel
+///
+/// # Arguments
+///
+/// `pixel` - color
+///
+/// # Return
+///
+/// Luma as ITU-R BT.601 weighted sum of color channels
+pub fn luma(pixel: Pixel) -> u8 {
+    ((Color::r(pixel) as u32 * 299 + Color::g(pixel) as u32 * 587 + Color::b(pixel) as u32 * 114 + 500) / 1000) as u8
+}
+
+/// Adjust brightness and contrast
+///
+/// # Arguments
+///
+/// `bitmap` - source bitmap
+///
+/// `brightness` - added to color channels, -1.0 is black, 0.0 keeps and 1.0 is white
+///
+/// `contrast` - multiplies a distance from the middle gray, 0.0 is gray and 1.0 keeps
+///
+/// # Return
+///
+/// New bitmap
+pub fn brightness_contrast(bitmap: &dyn BitmapData, brightness: f32, contrast: f32) -> Bitmap {
+    let table = channel_table(|v| (v - 0.5) * contrast + 0.5 + brightness);
+    map_channels(bitmap, &table)
+}
+
+/// Gamma correction
+///
+/// # Arguments
+///
+/// `bitmap` - source bitmap
+///
+/// `gamma` - greater than 1.0 lightens and less than 1.0 darkens
+///
+/// # Return
+///
+/// New bitmap or error if gamma is not positive
+pub fn gamma(bitmap: &dyn BitmapData, gamma: f32) -> Result<Bitmap> {
+    if gamma <= 0. || !gamma.is_finite() {
+        return GemGuiError::error(format!("Bad gamma {gamma}"));
+    }
+    let table = channel_table(|v| v.powf(1. / gamma));
+    Ok(map_channels(bitmap, &table))
+}
+
+/// Convert to shades of gray
+///
+/// # Arguments
+///
+/// `bitmap` - source bitmap
+///
+/// # Return
+///
+/// New bitmap, alpha is kept
+pub fn grayscale(bitmap: &dyn BitmapData) -> Bitmap {
+    map_pixels(bitmap, |p| {
+        let l = luma(p);
+        Color::rgba(l, l, l, Color::a(p))
+    })
+}
+
+/// Invert colors
+///
+/// # Arguments
+///
+/// `bitmap` - source bitmap
+///
+/// # Return
+///
+/// New bitmap, alpha is kept
+pub fn invert(bitmap: &dyn BitmapData) -> Bitmap {
+    map_pixels(bitmap, |p| p ^ 0x00FFFFFF)
+}
+
+/// Convert to black and white
+///
+/// # Arguments
+///
+/// `bitmap` - source bitmap
+///
+/// `level` - pixels of this [luma] or brighter turn white, others black
+///
+/// # Return
+///
+/// New bitmap, alpha is kept
+pub fn threshold(bitmap: &dyn BitmapData, level: u8) -> Bitmap {
+    map_pixels(bitmap, |p| {
+        let v = if luma(p) >= level {0xFF} else {0};
+        Color::rgba(v, v, v, Color::a(p))
+    })
+}
+
+/// Pixel counts of each channel value
+pub struct Histogram {
+    /// Red channel
+    pub red: [u32; 256],
+    /// Green channel
+    pub green: [u32; 256],
+    /// Blue channel
+    pub blue: [u32; 256],
+    /// Alpha channel
+    pub alpha: [u32; 256],
+    /// Perceived brightness, see [luma]
+    pub luma: [u32; 256],
+}
+
+/// Histogram of a bitmap
+///
+/// # Arguments
+///
+/// `bitmap` - source bitmap
+///
+/// # Return
+///
+/// Histogram
+pub fn histogram(bitmap: &dyn BitmapData) -> Histogram {
+    let mut histogram = Histogram {
+        red: [0; 256],
+        green: [0; 256],
+        blue: [0; 256],
+        alpha: [0; 256],
+        luma: [0; 256],
+    };
+    for p in bitmap.iter() {
+        histogram.red[Color::r(*p) as usize] += 1;
+        histogram.green[Color::g(*p) as usize] += 1;
+        histogram.blue[Color::b(*p) as usize] += 1;
+        histogram.alpha[Color::a(*p) as usize] += 1;
+        histogram.luma[luma(*p) as usize] += 1;
+    }
+    histogram
+}
+
+/// Square convolution matrix
+///
+/// # Example
+///
+/// ```
+/// # use gemgui::graphics::filter::{self, Kernel};
+/// # use gemgui::graphics::bitmap::{Bitmap, BitmapData};
+/// # use gemgui::graphics::color as Color;
+/// let bitmap = Bitmap::rect(10, 10, Color::RED);
+/// let emboss = Kernel::new(3, &[-2., -1., 0., -1., 1., 1., 0., 1., 2.]).unwrap();
+/// let embossed = filter::convolve(&bitmap, &emboss);
+/// let blurred = filter::convolve(&embossed, &Kernel::gaussian_blur(2, 1.0));
+/// assert_eq!(blurred.width(), 10);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Kernel {
+    size: usize,
+    weights: Vec<f32>,
+}
+
+impl Kernel {
+
+    /// New kernel
+    ///
+    /// # Arguments
+    ///
+    /// `size` - width and height, an odd number
+    ///
+    /// `weights` - size * size weights row by row
+    ///
+    /// # Return
+    ///
+    /// Kernel or error if size does not match
+    pub fn new(size: usize, weights: &[f32]) -> Result<Kernel> {
+        if size.is_multiple_of(2) || weights.len() != size * size {
+            return GemGuiError::error(format!("Bad kernel size {size}, {} weights", weights.len()));
+        }
+        Ok(Kernel{size, weights: weights.to_vec()})
+    }
+
+    /// Blur by averaging a square area
+    ///
+    /// # Arguments
+    ///
+    /// `radius` - pixels around the center
+    pub fn box_blur(radius: usize) -> Kernel {
+        let size = radius * 2 + 1;
+        Kernel{size, weights: vec![1. / (size * size) as f32; size * size]}
+    }
+
+    /// Gaussian blur
+    ///
+    /// # Arguments
+    ///
+    /// `radius` - pixels around the center
+    ///
+    /// `sigma` - standard deviation, zero, negative and NaN values are taken as a tiny
+    /// positive value that leaves the image as is
+    pub fn gaussian_blur(radius: usize, sigma: f32) -> Kernel {
+        let sigma = sigma.max(f32::EPSILON); // NaN is replaced too
+        let size = radius * 2 + 1;
+        let r = radius as f32;
+        let mut weights = Vec::with_capacity(size * size);
+        for y in 0..size {
+            for x in 0..size {
+                let (dx, dy) = (x as f32 - r, y as f32 - r);
+                weights.push((-(dx * dx + dy * dy) / (2. * sigma * sigma)).exp());
+            }
+        }
+        let total: f32 = weights.iter().sum();
+        weights.iter_mut().for_each(|w| *w /= total);
+        Kernel{size, weights}
+    }
+
+    /// Sharpen
+    pub fn sharpen() -> Kernel {
+        Kernel{size: 3, weights: vec!(0., -1., 0., -1., 5., -1., 0., -1., 0.)}
+    }
+
+    /// Laplacian edge detection, flat areas turn black
+    pub fn edge_detect() -> Kernel {
+        Kernel{size: 3, weights: vec!(-1., -1., -1., -1., 8., -1., -1., -1., -1.)}
+    }
+
+    /// Kernel width and height
+    pub fn size(&self) -> usize {
+        self.size
+    }
+}
+
+/// Apply convolution kernel
+///
+/// Color channels are convolved, pixels beyond edges repeat the edge pixels.
+///
+/// # Arguments
+///
+/// `bitmap` - source bitmap
+///
+/// `kernel` - convolution kernel
+///
+/// # Return
+///
+/// New bitmap, alpha is kept
+pub fn convolve(bitmap: &dyn BitmapData, kernel: &Kernel) -> Bitmap {
+    let (width, height) = (bitmap.width() as i32, bitmap.height() as i32);
+    let radius = (kernel.size / 2) as i32;
+    map_rows(bitmap, |y, source, row| {
+        let alphas = &source[y * width as usize..(y + 1) * width as usize];
+        for ((x, target), alpha) in row.iter_mut().enumerate().zip(alphas) {
+            let mut acc = [0f32; 3];
+            for (ky, weights) in kernel.weights.chunks_exact(kernel.size).enumerate() {
+                let sy = (y as i32 + ky as i32 - radius).clamp(0, height - 1);
+                let line = &source[(sy * width) as usize..((sy + 1) * width) as usize];
+                for (kx, weight) in weights.iter().enumerate() {
+                    let sx = (x as i32 + kx as i32 - radius).clamp(0, width - 1);
+                    let p = line[sx as usize];
+                    acc[0] += Color::r(p) as f32 * weight;
+                    acc[1] += Color::g(p) as f32 * weight;
+                    acc[2] += Color::b(p) as f32 * weight;
+                }
+            }
+            let c = |v: f32| v.round().clamp(0., 255.) as u8;
+            *target = Color::rgba(c(acc[0]), c(acc[1]), c(acc[2]), Color::a(*alpha));
+        }
+    })
+}
+
+/// Fill an area of similar color
+///
+/// Area is the pixels connected horizontally and vertically to the start point
+/// that differ from its color at most by tolerance in each channel.
+///
+/// # Arguments
+///
+/// `bitmap` - source bitmap
+///
+/// `x` - start x coordinate
+///
+/// `y` - start y coordinate
+///
+/// `pixel` - fill color
+///
+/// `tolerance` - allowed channel difference, 0 fills only the exact color
+///
+/// # Return
+///
+/// New bitmap or error if start point is outside of bitmap
+pub fn flood_fill(bitmap: &dyn BitmapData, x: u32, y: u32, pixel: Pixel, tolerance: u8) -> Result<Bitmap> {
+    let (width, height) = (bitmap.width() as usize, bitmap.height() as usize);
+    if x as usize >= width || y as usize >= height {
+        return GemGuiError::error(format!("Bad position {x}x{y}"));
+    }
+    let mut bytes: Bytes = bitmap.iter().copied().collect();
+    let seed = bytes[y as usize * width + x as usize];
+    let similar = |p: Pixel| (0..4).all(|i| {
+        let shift = i * 8;
+        ((p >> shift) & 0xFF).abs_diff((seed >> shift) & 0xFF) <= tolerance as u32
+    });
+    let mut filled = vec![false; width * height];
+    let mut stack = vec!((x as usize, y as usize));
+
+    // scan line fill, each popped point is extended to a horizontal span
+    while let Some((px, py)) = stack.pop() {
+        let line = py * width;
+        if filled[line + px] {
+            continue;
+        }
+        let mut left = px;
+        while left > 0 && !filled[line + left - 1] && similar(bytes[line + left - 1]) {
+            left -= 1;
+        }
+        let mut right = px;
+        while right + 1 < width && !filled[line + right + 1] && similar(bytes[line + right + 1]) {
+            right += 1;
+        }
+        for sx in left..=right {
+            filled[line + sx] = true;
+            bytes[line + sx] = pixel;
+        }
+        for ny in [py.wrapping_sub(1), py + 1] {
+            if ny >= height {
+                continue;
+            }
+            let next = ny * width;
+            let mut in_span = false;
+            for sx in left..=right {
+                let fillable = !filled[next + sx] && similar(bytes[next + sx]);
+                if fillable && !in_span {
+                    stack.push((sx, ny));
+                }
+                in_span = fillable;
+            }
+        }
+    }
+    Ok(Bitmap::from_bytes(width as u32, height as u32, bytes))
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use crate::graphics::color::{BLACK, BLUE, RED, WHITE};
+
+    use super::*;
+
+    #[test]
+    fn test_pixel_operations() {
+        let mut b = Bitmap::rect(4, 3, Color::rgba(100, 150, 200, 128));
+        b.put(0, 0, WHITE);
+
+        let inverted = invert(&b);
+        assert_eq!(inverted.get(0, 0), BLACK);
+        assert_eq!(inverted.get(1, 0), Color::rgba(155, 105, 55, 128));
+
+        let gray = grayscale(&b);
+        assert_eq!(gray.get(1, 1), Color::rgba(141, 141, 141, 128));
+
+        assert_eq!(brightness_contrast(&b, 0., 1.).iter().as_slice(), b.iter().as_slice());
+        assert_eq!(brightness_contrast(&b, 1., 1.).get(1, 1), Color::rgba(255, 255, 255, 128));
+        assert_eq!(brightness_contrast(&b, 0., 0.).get(0, 0), Color::rgb(128, 128, 128));
+
+        assert_eq!(gamma(&b, 1.).unwrap().get(2, 2), b.get(2, 2));
+        assert!(Color::r(gamma(&b, 2.).unwrap().get(2, 2)) > 100);
+        assert!(gamma(&b, 0.).is_err());
+
+        let bw = threshold(&b, 141);
+        assert_eq!(bw.get(0, 0), WHITE);
+        assert_eq!(bw.get(1, 0), Color::rgba(255, 255, 255, 128));
+        assert_eq!(threshold(&b, 142).get(1, 0), Color::rgba(0, 0, 0, 128));
+
+        let h = histogram(&b);
+        assert_eq!(h.red[255], 1);
+        assert_eq!(h.red[100], 11);
+        assert_eq!(h.alpha[128], 11);
+        assert_eq!(h.luma.iter().sum::<u32>(), 12);
+    }
+
+    #[test]
+    fn test_convolve() {
+        let mut b = Bitmap::rect(5, 5, BLACK);
+        b.put(2, 2, WHITE);
+        let blurred = convolve(&b, &Kernel::box_blur(1));
+        assert_eq!(blurred.get(1, 1), Color::rgb(28, 28, 28));
+        assert_eq!(blurred.get(0, 0), BLACK);
+
+        let flat = Bitmap::rect(6, 4, RED);
+        assert_eq!(convolve(&flat, &Kernel::gaussian_blur(2, 1.5)).iter().as_slice(), flat.iter().as_slice());
+        assert_eq!(convolve(&flat, &Kernel::sharpen()).iter().as_slice(), flat.iter().as_slice());
+        assert!(convolve(&flat, &Kernel::edge_detect()).iter().all(|p| *p == BLACK));
+        assert_eq!(convolve(&b, &Kernel::edge_detect()).get(2, 2), WHITE);
+        assert!(Kernel::new(2, &[1., 1., 1., 1.]).is_err());
+
+        for sigma in [0., -1., f32::NAN] {
+            let kernel = Kernel::gaussian_blur(2, sigma);
+            assert!(kernel.weights.iter().all(|w| w.is_finite()));
+            assert_eq!(convolve(&b, &kernel).iter().as_slice(), b.iter().as_slice());
+        }
+    }
+
+    #[test]
+    fn test_flood_fill() {
+        // a blue ring, inside and outside are separate areas
+        let mut b = Bitmap::rect(7, 7, WHITE);
+        for i in 1..6 {
+            b.put(i, 1, BLUE);
+            b.put(i, 5, BLUE);
+            b.put(1, i, BLUE);
+            b.put(5, i, BLUE);
+        }
+        let filled = flood_fill(&b, 3, 3, RED, 0).unwrap();
+        assert_eq!(filled.iter().filter(|p| **p == RED).count(), 9);
+        assert_eq!(filled.get(0, 0), WHITE);
+
+        let filled = flood_fill(&b, 0, 0, RED, 0).unwrap();
+        assert_eq!(filled.iter().filter(|p| **p == RED).count(), 24);
+        assert_eq!(filled.get(3, 3), WHITE);
+
+        b.put(6, 6, Color::rgb(250, 250, 250));
+        assert_eq!(flood_fill(&b, 0, 0, RED, 0).unwrap().get(6, 6), Color::rgb(250, 250, 250));
+        assert_eq!(flood_fill(&b, 0, 0, RED, 5).unwrap().get(6, 6), RED);
+        assert!(flood_fill(&b, 7, 0, RED, 0).is_err());
+    }
+}
@@ -0,0 +1,1725 @@
+use std::f32::consts::PI;
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+use crate::GemGuiError;
+use crate::Result;
+use crate::graphics::bitmap::{Bitmap, BitmapData};
+use crate::graphics::color as Color;
+use crate::Rect;
+use crate::graphics::context::{Arg, Context2D, Float, LineCap, LineJoin};
+use crate::graphics::font;
+
+// curves are flattened to lines with this accuracy in pixels
+static TOLERANCE: Float = 0.1;
+static MAX_SEGMENTS: usize = 1024;
+
+// canvas defaults
+static DEFAULT_FONT_SIZE: Float = 10.;
+static DEFAULT_MITER_LIMIT: Float = 10.;
+
+type Point = (Float, Float);
+// straight alpha color, components are in between 0.0 and 1.0
+type Rgba = [Float; 4];
+type Stop = (Float, Rgba);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Matrix {
+    a: Float,
+    b: Float,
+    c: Float,
+    d: Float,
+    e: Float,
+    f: Float,
+}
+
+impl Matrix {
+    fn new(a: Float, b: Float, c: Float, d: Float, e: Float, f: Float) -> Matrix {
+        Matrix{a, b, c, d, e, f}
+    }
+
+    fn identity() -> Matrix {
+        Matrix::new(1., 0., 0., 1., 0., 0.)
+    }
+
+    // other is applied first, as in canvas transform
+    fn multiply(&self, m: &Matrix) -> Matrix {
+        Matrix {
+            a: self.a * m.a + self.c * m.b,
+            b: self.b * m.a + self.d * m.b,
+            c: self.a * m.c + self.c * m.d,
+            d: self.b * m.c + self.d * m.d,
+            e: self.a * m.e + self.c * m.f + self.e,
+            f: self.b * m.e + self.d * m.f + self.f,
+        }
+    }
+
+    fn apply(&self, x: Float, y: Float) -> Point {
+        (self.a * x + self.c * y + self.e, self.b * x + self.d * y + self.f)
+    }
+
+    fn invert(&self) -> Option<Matrix> {
+        let det = self.a * self.d - self.b * self.c;
+        if det.abs() < 1e-12 {
+            return None;
+        }
+        Some(Matrix {
+            a: self.d / det,
+            b: -self.b / det,
+            c: -self.c / det,
+            d: self.a / det,
+            e: (self.c * self.f - self.d * self.e) / det,
+            f: (self.b * self.e - self.a * self.f) / det,
+        })
+    }
+
+    // average scale, used to size strokes and curve segments
+    fn scale(&self) -> Float {
+        (self.a * self.d - self.b * self.c).abs().sqrt()
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Paint {
+    Color(Rgba),
+    Linear(Point, Point, Vec<Stop>),
+    Radial(Point, Float, Point, Float, Vec<Stop>),
+    Conic(Float, Point, Vec<Stop>),
+}
+
+impl Paint {
+    // color at a point in user coordinates
+    fn color_at(&self, p: Point) -> Rgba {
+        match self {
+            Self::Color(color) => *color,
+            Self::Linear(p0, p1, stops) => {
+                let (dx, dy) = (p1.0 - p0.0, p1.1 - p0.1);
+                let len = dx * dx + dy * dy;
+                if len == 0. {
+                    return [0.; 4];
+                }
+                stop_color(stops, ((p.0 - p0.0) * dx + (p.1 - p0.1) * dy) / len)
+            },
+            Self::Radial(c0, r0, c1, r1, stops) => {
+                // largest t where the point is on the circle interpolated in between the circles
+                let (cdx, cdy) = (c1.0 - c0.0, c1.1 - c0.1);
+                let (pdx, pdy) = (p.0 - c0.0, p.1 - c0.1);
+                let dr = r1 - r0;
+                let a = cdx * cdx + cdy * cdy - dr * dr;
+                let b = pdx * cdx + pdy * cdy + r0 * dr;
+                let c = pdx * pdx + pdy * pdy - r0 * r0;
+                let t = if a.abs() < 1e-6 {
+                    if b == 0. {
+                        return [0.; 4];
+                    }
+                    c / (2. * b)
+                } else {
+                    let disc = b * b - a * c;
+                    if disc < 0. {
+                        return [0.; 4];
+                    }
+                    let t = (b + disc.sqrt()) / a;
+                    if r0 + t * dr >= 0. {t} else {(b - disc.sqrt()) / a}
+                };
+                if r0 + t * dr < 0. {
+                    return [0.; 4];
+                }
+                stop_color(stops, t)
+            },
+            Self::Conic(start, c, stops) => {
+                let angle = (p.1 - c.1).atan2(p.0 - c.0) - start;
+                stop_color(stops, angle.rem_euclid(2. * PI) / (2. * PI))
+            },
+        }
+    }
+}
+
+fn stop_color(stops: &[Stop], t: Float) -> Rgba {
+    let (first, last) = match (stops.first(), stops.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return [0.; 4],
+    };
+    if t <= first.0 {
+        return first.1;
+    }
+    if t >= last.0 {
+        return last.1;
+    }
+    for pair in stops.windows(2) {
+        let ((o0, c0), (o1, c1)) = (pair[0], pair[1]);
+        if t >= o0 && t < o1 {
+            let f = (t - o0) / (o1 - o0);
+            return [0, 1, 2, 3].map(|i| c0[i] + (c1[i] - c0[i]) * f);
+        }
+    }
+    last.1
+}
+
+// Porter-Duff operations, blend modes are not supported
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Operation {
+    SourceOver,
+    SourceIn,
+    SourceOut,
+    SourceAtop,
+    DestinationOver,
+    DestinationIn,
+    DestinationOut,
+    DestinationAtop,
+    Lighter,
+    Copy,
+    Xor,
+}
+
+impl Operation {
+    fn from_name(name: &str) -> Option<Operation> {
+        Some(match name {
+            "source-over" => Self::SourceOver,
+            "source-in" => Self::SourceIn,
+            "source-out" => Self::SourceOut,
+            "source-atop" => Self::SourceAtop,
+            "destination-over" => Self::DestinationOver,
+            "destination-in" => Self::DestinationIn,
+            "destination-out" => Self::DestinationOut,
+            "destination-atop" => Self::DestinationAtop,
+            "lighter" => Self::Lighter,
+            "copy" => Self::Copy,
+            "xor" => Self::Xor,
+            _ => return None,
+        })
+    }
+
+    // source and destination factors for source and destination alphas
+    fn factors(&self, sa: Float, da: Float) -> (Float, Float) {
+        match self {
+            Self::SourceOver => (1., 1. - sa),
+            Self::SourceIn => (da, 0.),
+            Self::SourceOut => (1. - da, 0.),
+            Self::SourceAtop => (da, 1. - sa),
+            Self::DestinationOver => (1. - da, 1.),
+            Self::DestinationIn => (0., sa),
+            Self::DestinationOut => (0., 1. - sa),
+            Self::DestinationAtop => (1. - da, sa),
+            Self::Lighter => (1., 1.),
+            Self::Copy => (1., 0.),
+            Self::Xor => (1. - da, 1. - sa),
+        }
+    }
+
+    // whether areas outside of the drawn shape are affected
+    fn is_unbounded(&self) -> bool {
+        matches!(self, Self::SourceIn | Self::SourceOut | Self::DestinationIn | Self::DestinationAtop | Self::Copy)
+    }
+}
+
+// per pixel coverage
+#[derive(Clone, Debug)]
+struct Mask {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    values: Vec<Float>,
+}
+
+impl Mask {
+    fn get(&self, x: u32, y: u32) -> Float {
+        if x < self.x || y < self.y || x >= self.x + self.width || y >= self.y + self.height {
+            return 0.;
+        }
+        self.values[((y - self.y) * self.width + (x - self.x)) as usize]
+    }
+}
+
+#[derive(Clone, Debug)]
+struct State {
+    transform: Matrix,
+    fill: Paint,
+    stroke: Paint,
+    line: Stroke,
+    alpha: Float,
+    operation: Operation,
+    font_size: Float,
+    text_align: String,
+    text_baseline: String,
+    clip: Option<Mask>,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        State {
+            transform: Matrix::identity(),
+            fill: Paint::Color([0., 0., 0., 1.]),
+            stroke: Paint::Color([0., 0., 0., 1.]),
+            line: Stroke::default(),
+            alpha: 1.,
+            operation: Operation::SourceOver,
+            font_size: DEFAULT_FONT_SIZE,
+            text_align: "start".to_string(),
+            text_baseline: "alphabetic".to_string(),
+            clip: None,
+        }
+    }
+}
+
+// points are in bitmap coordinates
+#[derive(Clone, Debug, Default)]
+struct SubPath {
+    points: Vec<Point>,
+    closed: bool,
+}
+
+#[derive(Clone, Debug, Default)]
+struct Path {
+    subpaths: Vec<SubPath>,
+}
+
+impl Path {
+    fn move_to(&mut self, p: Point) {
+        self.subpaths.push(SubPath{points: vec!(p), closed: false});
+    }
+
+    fn line_to(&mut self, p: Point) {
+        match self.subpaths.last_mut() {
+            Some(sub) if !sub.closed => sub.points.push(p),
+            _ => self.move_to(p),
+        }
+    }
+
+    // closed subpath is followed by a new one starting from the same point
+    fn close(&mut self) {
+        if let Some(sub) = self.subpaths.last_mut() {
+            if !sub.closed && !sub.points.is_empty() {
+                sub.closed = true;
+                let first = sub.points[0];
+                self.move_to(first);
+            }
+        }
+    }
+
+    fn current(&self) -> Option<Point> {
+        self.subpaths.last().and_then(|sub| sub.points.last().copied())
+    }
+
+    fn polygons(&self) -> Vec<Vec<Point>> {
+        self.subpaths.iter().filter(|sub| sub.points.len() > 1).map(|sub| sub.points.clone()).collect()
+    }
+}
+
+/// Which parts of self-intersecting or nested polygons are inside
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FillRule {
+    /// Default. Inside if the polygons wind around the point in total.
+    #[default]
+    NonZero,
+    /// Inside if a ray from the point crosses edges an odd number of times.
+    EvenOdd,
+}
+
+/// Line style used for drawing on bitmaps
+///
+/// # Example
+///
+/// ```
+/// # use gemgui::graphics::raster::Stroke;
+/// # use gemgui::graphics::context::LineCap;
+/// let stroke = Stroke::new(2.).cap(LineCap::Round).dash(&[4., 2.], 0.);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stroke {
+    width: Float,
+    cap: LineCap,
+    join: LineJoin,
+    miter_limit: Float,
+    dash: Vec<Float>,
+    dash_offset: Float,
+}
+
+impl Default for Stroke {
+    fn default() -> Self {
+        Stroke::new(1.)
+    }
+}
+
+impl Stroke {
+
+    /// New solid stroke with butt ends and miter corners
+    ///
+    /// # Arguments
+    ///
+    /// `width` - line width in pixels
+    pub fn new(width: Float) -> Stroke {
+        Stroke {
+            width: width.max(0.),
+            cap: LineCap::Butt,
+            join: LineJoin::Miter,
+            miter_limit: DEFAULT_MITER_LIMIT,
+            dash: Vec::new(),
+            dash_offset: 0.,
+        }
+    }
+
+    /// Set line end style
+    ///
+    /// # Arguments
+    ///
+    /// `cap` - line cap
+    pub fn cap(mut self, cap: LineCap) -> Stroke {
+        self.cap = cap;
+        self
+    }
+
+    /// Set line corner style
+    ///
+    /// # Arguments
+    ///
+    /// `join` - line join
+    pub fn join(mut self, join: LineJoin) -> Stroke {
+        self.join = join;
+        self
+    }
+
+    /// Set how long miter corners can be, see [Context2D::miter_limit]
+    ///
+    /// # Arguments
+    ///
+    /// `limit` - ratio of miter length to line width
+    pub fn miter_limit(mut self, limit: Float) -> Stroke {
+        if limit > 0. {
+            self.miter_limit = limit;
+        }
+        self
+    }
+
+    /// Set dash pattern
+    ///
+    /// As in canvas, a pattern of odd length is repeated twice and a pattern
+    /// having negative values is ignored.
+    ///
+    /// # Arguments
+    ///
+    /// `segments` - alternating lengths of dashes and gaps, empty for a solid line
+    ///
+    /// `offset` - distance into the pattern the line starts
+    pub fn dash(mut self, segments: &[Float], offset: Float) -> Stroke {
+        self.set_dash(segments, offset);
+        self
+    }
+
+    fn set_dash(&mut self, segments: &[Float], offset: Float) {
+        if segments.iter().all(|d| *d >= 0.) {
+            self.dash = segments.to_vec();
+            if self.dash.len() % 2 == 1 {
+                self.dash.extend_from_slice(segments);
+            }
+        }
+        self.dash_offset = offset;
+    }
+
+    // polygons covering the stroked subpaths, scale is applied to the width and dashes
+    fn outline(&self, subpaths: &[SubPath], scale: Float) -> Vec<Vec<Point>> {
+        let half_width = self.width * scale / 2.;
+        let dash: Vec<Float> = self.dash.iter().map(|d| d * scale).collect();
+        let mut polygons = Vec::new();
+        for sub in subpaths {
+            let mut points = sub.points.clone();
+            points.dedup();
+            if sub.closed && points.len() > 1 && points.first() == points.last() {
+                points.pop();
+            }
+            if dash.iter().sum::<Float>() > 0. {
+                if sub.closed && !points.is_empty() {
+                    points.push(points[0]);
+                }
+                for piece in dash_polyline(&points, &dash, self.dash_offset * scale) {
+                    self.polyline(&piece, false, half_width, &mut polygons);
+                }
+            } else {
+                self.polyline(&points, sub.closed, half_width, &mut polygons);
+            }
+        }
+        polygons
+    }
+
+    fn polyline(&self, points: &[Point], closed: bool, half_width: Float, polygons: &mut Vec<Vec<Point>>) {
+        let count = points.len();
+        if count < 2 || half_width <= 0. {
+            return;
+        }
+        let segments = if closed {count} else {count - 1};
+        let direction = |i: usize| normalize((points[(i + 1) % count].0 - points[i].0, points[(i + 1) % count].1 - points[i].1)).0;
+        for i in 0..segments {
+            let (p, q) = (points[i], points[(i + 1) % count]);
+            let d = direction(i);
+            let n = (-d.1 * half_width, d.0 * half_width);
+            polygons.push(oriented(vec!(
+                (p.0 + n.0, p.1 + n.1), (q.0 + n.0, q.1 + n.1),
+                (q.0 - n.0, q.1 - n.1), (p.0 - n.0, p.1 - n.1))));
+        }
+        let joins = if closed {0..count} else {1..count - 1};
+        for i in joins {
+            let d_in = direction((i + count - 1) % count);
+            self.line_join(points[i], d_in, direction(i), half_width, polygons);
+        }
+        if !closed {
+            let d = direction(0);
+            self.line_cap(points[0], (-d.0, -d.1), half_width, polygons);
+            let d = direction(count - 2);
+            self.line_cap(points[count - 1], d, half_width, polygons);
+        }
+    }
+
+    fn line_join(&self, v: Point, d1: Point, d2: Point, half_width: Float, polygons: &mut Vec<Vec<Point>>) {
+        let cross = d1.0 * d2.1 - d1.1 * d2.0;
+        let dot = d1.0 * d2.0 + d1.1 * d2.1;
+        if cross.abs() < 1e-6 && dot > 0. {
+            return;
+        }
+        if self.join == LineJoin::Round {
+            polygons.push(circle_polygon(v, half_width));
+            return;
+        }
+        let n1 = (-d1.1 * half_width, d1.0 * half_width);
+        let n2 = (-d2.1 * half_width, d2.0 * half_width);
+        // outer side is away from the next segment
+        let side = if n1.0 * d2.0 + n1.1 * d2.1 > 0. {-1.} else {1.};
+        let a = (v.0 + side * n1.0, v.1 + side * n1.1);
+        let b = (v.0 + side * n2.0, v.1 + side * n2.1);
+        let half_turn_cos = ((1. + dot) / 2.).max(0.).sqrt();
+        if self.join == LineJoin::Miter && half_turn_cos > 0. && 1. / half_turn_cos <= self.miter_limit {
+            let (bisector, _) = normalize((n1.0 + n2.0, n1.1 + n2.1));
+            let length = side * half_width / half_turn_cos;
+            let m = (v.0 + bisector.0 * length, v.1 + bisector.1 * length);
+            polygons.push(oriented(vec!(v, a, m, b)));
+        } else {
+            polygons.push(oriented(vec!(v, a, b)));
+        }
+    }
+
+    fn line_cap(&self, p: Point, d: Point, half_width: Float, polygons: &mut Vec<Vec<Point>>) {
+        match self.cap {
+            LineCap::Butt => (),
+            LineCap::Round => polygons.push(circle_polygon(p, half_width)),
+            LineCap::Square => {
+                let n = (-d.1 * half_width, d.0 * half_width);
+                let e = (d.0 * half_width, d.1 * half_width);
+                polygons.push(oriented(vec!(
+                    (p.0 + n.0, p.1 + n.1), (p.0 + n.0 + e.0, p.1 + n.1 + e.1),
+                    (p.0 - n.0 + e.0, p.1 - n.1 + e.1), (p.0 - n.0, p.1 - n.1))));
+            }
+        }
+    }
+}
+
+/// Software renderer that draws [Context2D] commands on a bitmap
+///
+/// Renderer keeps the drawing state (styles, transforms, path and clip) in between draws
+/// as a canvas does. Text is drawn with an embedded 8x8 pixel font scaled to the font size.
+/// Shadows, filters and image smoothing settings are ignored. Images, patterns and blend
+/// composite operations are not available and are reported as errors.
+///
+/// # Example
+///
+/// ```
+/// # use gemgui::graphics::raster::Renderer;
+/// # use gemgui::graphics::context::Context2D;
+/// # use gemgui::graphics::bitmap::{Bitmap, BitmapData};
+/// # use gemgui::graphics::color as Color;
+/// # use gemgui::Rect;
+/// let mut bitmap = Bitmap::new(100, 100);
+/// let mut context = Context2D::new();
+/// context.fill_color(Color::RED).fill_rect(&Rect::new(10., 10., 20., 20.));
+/// Renderer::new().draw_context(&mut bitmap, &context).unwrap();
+/// assert_eq!(bitmap.get(15, 15), Color::RED);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Renderer {
+    state: State,
+    stack: Vec<State>,
+    path: Path,
+}
+
+impl Renderer {
+
+    /// New renderer with the default canvas state
+    pub fn new() -> Renderer {
+        Renderer::default()
+    }
+
+    /// Draw context on a bitmap
+    ///
+    /// # Arguments
+    ///
+    /// `bitmap` - target bitmap
+    ///
+    /// `context` - commands drawn
+    ///
+    /// # Return
+    ///
+    /// Error if context has commands that cannot be rendered, then nothing is drawn
+    pub fn draw_context(&mut self, bitmap: &mut Bitmap, context: &Context2D) -> Result<()> {
+        for (command, args) in context.commands() {
+            Self::check_supported(command, args)?;
+        }
+        for (command, args) in context.commands() {
+            // as canvas, commands having non-finite numbers are ignored
+            if args.iter().all(|a| !matches!(a, Arg::Number(n) if !n.is_finite())) {
+                self.execute(bitmap, command, args);
+            }
+        }
+        Ok(())
+    }
+
+    fn check_supported(command: &str, args: &[Arg]) -> Result<()> {
+        match command {
+            "drawImage" | "drawImageRect" | "drawImageClip" | "fillPattern" | "strokePattern" =>
+                GemGuiError::error(format!("{command} is not supported")),
+            "globalCompositeOperation" if Operation::from_name(text(args, 0)).is_none() =>
+                GemGuiError::error(format!("{} is not supported", text(args, 0))),
+            _ => Ok(()),
+        }
+    }
+
+    fn execute(&mut self, bitmap: &mut Bitmap, command: &str, args: &[Arg]) {
+        let n = |i| number(args, i);
+        match command {
+            "fillRect" => {
+                let polygon = self.rect_polygon(n(0), n(1), n(2), n(3));
+                let paint = self.state.fill.clone();
+                self.fill_polygons(bitmap, &[polygon], Some(&paint));
+            },
+            "strokeRect" => {
+                let mut path = Path::default();
+                path.subpaths.push(SubPath{points: self.rect_polygon(n(0), n(1), n(2), n(3)), closed: true});
+                self.stroke_path(bitmap, &path);
+            },
+            "clearRect" => {
+                let polygon = self.rect_polygon(n(0), n(1), n(2), n(3));
+                self.fill_polygons(bitmap, &[polygon], None);
+            },
+            "fillText" => {
+                let polygons = self.text_polygons(text(args, 0), n(1), n(2));
+                let paint = self.state.fill.clone();
+                self.fill_polygons(bitmap, &polygons, Some(&paint));
+            },
+            "strokeText" => {
+                // glyphs are only a few pixels wide, outlines would not be readable
+                let polygons = self.text_polygons(text(args, 0), n(1), n(2));
+                let paint = self.state.stroke.clone();
+                self.fill_polygons(bitmap, &polygons, Some(&paint));
+            },
+            "beginPath" => self.path = Path::default(),
+            "closePath" => self.path.close(),
+            "moveTo" => {
+                let p = self.state.transform.apply(n(0), n(1));
+                self.path.move_to(p);
+            },
+            "lineTo" => {
+                let p = self.state.transform.apply(n(0), n(1));
+                self.path.line_to(p);
+            },
+            "bezierCurveTo" => self.bezier_curve_to((n(0), n(1)), (n(2), n(3)), (n(4), n(5))),
+            "quadraticCurveTo" => self.quadratic_curve_to((n(0), n(1)), (n(2), n(3))),
+            "arc" => self.ellipse((n(0), n(1)), n(2), n(2), 0., n(3), n(4)),
+            "ellipse" => self.ellipse((n(0), n(1)), n(2), n(3), n(4), n(5), n(6)),
+            "arcTo" => self.arc_to((n(0), n(1)), (n(2), n(3)), n(4)),
+            "rect" => {
+                let polygon = self.rect_polygon(n(0), n(1), n(2), n(3));
+                self.path.subpaths.push(SubPath{points: polygon, closed: false});
+                self.path.close();
+            },
+            "roundRect" => {
+                let radii: Vec<Float> = (0..n(4) as usize).map(|i| n(5 + i)).collect();
+                self.round_rect(n(0), n(1), n(2), n(3), &radii);
+            },
+            "fill" => {
+                let polygons = self.path.polygons();
+                let paint = self.state.fill.clone();
+                self.fill_polygons(bitmap, &polygons, Some(&paint));
+            },
+            "stroke" => {
+                let path = self.path.clone();
+                self.stroke_path(bitmap, &path);
+            },
+            "clip" => self.clip(bitmap),
+            "fillStyle" => if let Some(color) = parse_color(text(args, 0)) {
+                self.state.fill = Paint::Color(color);
+            },
+            "strokeStyle" => if let Some(color) = parse_color(text(args, 0)) {
+                self.state.stroke = Paint::Color(color);
+            },
+            "fillGradient" => if let Some(paint) = parse_gradient(args) {
+                self.state.fill = paint;
+            },
+            "strokeGradient" => if let Some(paint) = parse_gradient(args) {
+                self.state.stroke = paint;
+            },
+            "lineWidth" if n(0) > 0. => self.state.line.width = n(0),
+            "lineCap" => match text(args, 0) {
+                "butt" => self.state.line.cap = LineCap::Butt,
+                "round" => self.state.line.cap = LineCap::Round,
+                "square" => self.state.line.cap = LineCap::Square,
+                _ => (),
+            },
+            "lineJoin" => match text(args, 0) {
+                "round" => self.state.line.join = LineJoin::Round,
+                "bevel" => self.state.line.join = LineJoin::Bevel,
+                "miter" => self.state.line.join = LineJoin::Miter,
+                _ => (),
+            },
+            "miterLimit" if n(0) > 0. => self.state.line.miter_limit = n(0),
+            "setLineDash" => {
+                let dash: Vec<Float> = (0..n(0) as usize).map(|i| n(1 + i)).collect();
+                let offset = self.state.line.dash_offset;
+                self.state.line.set_dash(&dash, offset);
+            },
+            "lineDashOffset" => self.state.line.dash_offset = n(0),
+            "globalAlpha" if (0. ..=1.).contains(&n(0)) => self.state.alpha = n(0),
+            "globalCompositeOperation" => if let Some(operation) = Operation::from_name(text(args, 0)) {
+                self.state.operation = operation;
+            },
+            "font" => if let Some(size) = parse_font_size(text(args, 0)) {
+                self.state.font_size = size;
+            },
+            "textAlign" => self.state.text_align = text(args, 0).to_string(),
+            "textBaseline" => self.state.text_baseline = text(args, 0).to_string(),
+            "save" => self.stack.push(self.state.clone()),
+            "restore" => if let Some(state) = self.stack.pop() {
+                self.state = state;
+            },
+            "translate" => self.transform(Matrix::new(1., 0., 0., 1., n(0), n(1))),
+            "scale" => self.transform(Matrix::new(n(0), 0., 0., n(1), 0., 0.)),
+            "rotate" => {
+                let (sin, cos) = n(0).sin_cos();
+                self.transform(Matrix::new(cos, sin, -sin, cos, 0., 0.));
+            },
+            "transform" => self.transform(Matrix::new(n(0), n(1), n(2), n(3), n(4), n(5))),
+            "setTransform" => self.state.transform = Matrix::new(n(0), n(1), n(2), n(3), n(4), n(5)),
+            "resetTransform" => self.state.transform = Matrix::identity(),
+            _ => (), // shadows, filters and smoothing have no effect
+        }
+    }
+
+    fn transform(&mut self, m: Matrix) {
+        self.state.transform = self.state.transform.multiply(&m);
+    }
+
+    fn rect_polygon(&self, x: Float, y: Float, width: Float, height: Float) -> Vec<Point> {
+        let t = &self.state.transform;
+        vec!(t.apply(x, y), t.apply(x + width, y), t.apply(x + width, y + height), t.apply(x, y + height))
+    }
+
+    fn bezier_curve_to(&mut self, c1: Point, c2: Point, p: Point) {
+        if self.path.current().is_none() {
+            let start = self.state.transform.apply(c1.0, c1.1);
+            self.path.move_to(start);
+        }
+        let t = self.state.transform;
+        let p0 = self.path.current().unwrap();
+        let (p1, p2, p3) = (t.apply(c1.0, c1.1), t.apply(c2.0, c2.1), t.apply(p.0, p.1));
+        let length = distance(p0, p1) + distance(p1, p2) + distance(p2, p3);
+        let count = ((length / 4.).ceil() as usize).clamp(1, MAX_SEGMENTS);
+        for i in 1..=count {
+            let s = i as Float / count as Float;
+            let r = 1. - s;
+            let a = r * r * r;
+            let b = 3. * r * r * s;
+            let c = 3. * r * s * s;
+            let d = s * s * s;
+            self.path.line_to((
+                a * p0.0 + b * p1.0 + c * p2.0 + d * p3.0,
+                a * p0.1 + b * p1.1 + c * p2.1 + d * p3.1));
+        }
+    }
+
+    fn quadratic_curve_to(&mut self, c: Point, p: Point) {
+        if self.path.current().is_none() {
+            let start = self.state.transform.apply(c.0, c.1);
+            self.path.move_to(start);
+        }
+        let t = self.state.transform;
+        let p0 = self.path.current().unwrap();
+        let (p1, p2) = (t.apply(c.0, c.1), t.apply(p.0, p.1));
+        let count = (((distance(p0, p1) + distance(p1, p2)) / 4.).ceil() as usize).clamp(1, MAX_SEGMENTS);
+        for i in 1..=count {
+            let s = i as Float / count as Float;
+            let r = 1. - s;
+            self.path.line_to((
+                r * r * p0.0 + 2. * r * s * p1.0 + s * s * p2.0,
+                r * r * p0.1 + 2. * r * s * p1.1 + s * s * p2.1));
+        }
+    }
+
+    // angles grow clockwise, end angle is reached going clockwise
+    fn ellipse(&mut self, center: Point, rx: Float, ry: Float, rotation: Float, start: Float, end: Float) {
+        if rx < 0. || ry < 0. {
+            return;
+        }
+        let sweep = if end - start >= 2. * PI {2. * PI} else {(end - start).rem_euclid(2. * PI)};
+        self.arc_points(center, rx, ry, rotation, start, sweep);
+    }
+
+    fn arc_points(&mut self, center: Point, rx: Float, ry: Float, rotation: Float, start: Float, sweep: Float) {
+        let radius = rx.max(ry) * self.state.transform.scale();
+        let step = if radius > TOLERANCE {2. * (1. - TOLERANCE / radius).acos()} else {PI / 2.};
+        let count = ((sweep.abs() / step).ceil() as usize).clamp(1, MAX_SEGMENTS);
+        let (rot_sin, rot_cos) = rotation.sin_cos();
+        for i in 0..=count {
+            let (sin, cos) = (start + sweep * i as Float / count as Float).sin_cos();
+            let (x, y) = (rx * cos, ry * sin);
+            let p = self.state.transform.apply(
+                center.0 + x * rot_cos - y * rot_sin,
+                center.1 + x * rot_sin + y * rot_cos);
+            self.path.line_to(p);
+        }
+    }
+
+    fn arc_to(&mut self, p1: Point, p2: Point, radius: Float) {
+        let inverse = self.state.transform.invert();
+        let p0 = match (self.path.current(), inverse) {
+            (Some(current), Some(inverse)) => inverse.apply(current.0, current.1),
+            _ => {
+                let p = self.state.transform.apply(p1.0, p1.1);
+                self.path.move_to(p);
+                return;
+            }
+        };
+        let (v1, l1) = normalize((p0.0 - p1.0, p0.1 - p1.1));
+        let (v2, l2) = normalize((p2.0 - p1.0, p2.1 - p1.1));
+        let cross = v1.0 * v2.1 - v1.1 * v2.0;
+        if radius <= 0. || l1 == 0. || l2 == 0. || cross.abs() < 1e-6 {
+            let p = self.state.transform.apply(p1.0, p1.1);
+            self.path.line_to(p);
+            return;
+        }
+        let angle = (v1.0 * v2.0 + v1.1 * v2.1).clamp(-1., 1.).acos();
+        let tangent = radius / (angle / 2.).tan();
+        let (bisector, _) = normalize((v1.0 + v2.0, v1.1 + v2.1));
+        let center_distance = radius / (angle / 2.).sin();
+        let center = (p1.0 + bisector.0 * center_distance, p1.1 + bisector.1 * center_distance);
+        let t1 = (p1.0 + v1.0 * tangent, p1.1 + v1.1 * tangent);
+        let t2 = (p1.0 + v2.0 * tangent, p1.1 + v2.1 * tangent);
+        let a1 = (t1.1 - center.1).atan2(t1.0 - center.0);
+        let a2 = (t2.1 - center.1).atan2(t2.0 - center.0);
+        let mut sweep = a2 - a1;
+        if sweep > PI {
+            sweep -= 2. * PI;
+        } else if sweep < -PI {
+            sweep += 2. * PI;
+        }
+        self.arc_points(center, radius, radius, 0., a1, sweep);
+    }
+
+    fn round_rect(&mut self, x: Float, y: Float, width: Float, height: Float, radii: &[Float]) {
+        let (x, width) = if width < 0. {(x + width, -width)} else {(x, width)};
+        let (y, height) = if height < 0. {(y + height, -height)} else {(y, height)};
+        let r = |i: usize| radii.get(i).copied().unwrap_or(0.).max(0.);
+        // as CSS border-radius: top-left, top-right, bottom-right, bottom-left
+        let [tl, tr, br, bl] = match radii.len() {
+            1 => [r(0), r(0), r(0), r(0)],
+            2 => [r(0), r(1), r(0), r(1)],
+            3 => [r(0), r(1), r(2), r(1)],
+            4 => [r(0), r(1), r(2), r(3)],
+            _ => [0.; 4],
+        };
+        let fit = |side: Float, a: Float, b: Float| if a + b > side {side / (a + b)} else {1.};
+        let f = fit(width, tl, tr).min(fit(width, bl, br)).min(fit(height, tl, bl)).min(fit(height, tr, br));
+        let [tl, tr, br, bl] = [tl * f, tr * f, br * f, bl * f];
+        let start = self.state.transform.apply(x + tl, y);
+        self.path.move_to(start);
+        self.arc_points((x + width - tr, y + tr), tr, tr, 0., -PI / 2., PI / 2.);
+        self.arc_points((x + width - br, y + height - br), br, br, 0., 0., PI / 2.);
+        self.arc_points((x + bl, y + height - bl), bl, bl, 0., PI / 2., PI / 2.);
+        self.arc_points((x + tl, y + tl), tl, tl, 0., PI, PI / 2.);
+        self.path.close();
+    }
+
+    fn text_polygons(&self, text: &str, x: Float, y: Float) -> Vec<Vec<Point>> {
+        let scale = self.state.font_size / font::GLYPH_SIZE as Float;
+        let advance = font::GLYPH_SIZE as Float * scale;
+        let width = text.chars().count() as Float * advance;
+        let left = match self.state.text_align.as_str() {
+            "center" => x - width / 2.,
+            "right" | "end" => x - width,
+            _ => x,
+        };
+        let top = match self.state.text_baseline.as_str() {
+            "top" | "hanging" => y,
+            "middle" => y - self.state.font_size / 2.,
+            "bottom" => y - self.state.font_size,
+            _ => y - font::BASELINE as Float * scale,
+        };
+        glyph_polygons(text, left, top, self.state.font_size, &self.state.transform)
+    }
+
+    fn stroke_path(&mut self, bitmap: &mut Bitmap, path: &Path) {
+        let polygons = self.state.line.outline(&path.subpaths, self.state.transform.scale());
+        let paint = self.state.stroke.clone();
+        self.fill_polygons(bitmap, &polygons, Some(&paint));
+    }
+
+    fn clip(&mut self, bitmap: &Bitmap) {
+        let coverage = rasterize(&self.path.polygons(), bitmap.width(), bitmap.height(), FillRule::NonZero);
+        let (width, height) = (bitmap.width(), bitmap.height());
+        let mut values = vec![0.; (width * height) as usize];
+        for y in 0..height {
+            for x in 0..width {
+                let old = self.clip_value(bitmap, x, y);
+                let new = coverage.as_ref().map_or(0., |c| c.get(x, y));
+                values[(y * width + x) as usize] = old * new;
+            }
+        }
+        self.state.clip = Some(Mask{x: 0, y: 0, width, height, values});
+    }
+
+    fn clip_value(&self, bitmap: &Bitmap, x: u32, y: u32) -> Float {
+        match &self.state.clip {
+            Some(clip) if clip.width == bitmap.width() && clip.height == bitmap.height() => clip.get(x, y),
+            _ => 1.,
+        }
+    }
+
+    // paint None clears
+    fn fill_polygons(&mut self, bitmap: &mut Bitmap, polygons: &[Vec<Point>], paint: Option<&Paint>) {
+        let inverse = match self.state.transform.invert() {
+            Some(inverse) => inverse,
+            None => return,
+        };
+        let operation = self.state.operation;
+        let unbounded = paint.is_some() && operation.is_unbounded();
+        let coverage = rasterize(polygons, bitmap.width(), bitmap.height(), FillRule::NonZero);
+        let (x0, y0, x1, y1) = match (&coverage, unbounded) {
+            (_, true) => (0, 0, bitmap.width(), bitmap.height()),
+            (Some(c), false) => (c.x, c.y, c.x + c.width, c.y + c.height),
+            (None, false) => return,
+        };
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let cover = coverage.as_ref().map_or(0., |c| c.get(x, y));
+                let clip = self.clip_value(bitmap, x, y);
+                if clip == 0. || (cover == 0. && !unbounded) {
+                    continue;
+                }
+                let dst = to_rgba(bitmap.get(x, y));
+                let result = match paint {
+                    None => mix(dst, [0.; 4], cover),
+                    Some(paint) => {
+                        let p = inverse.apply(x as Float + 0.5, y as Float + 0.5);
+                        let mut src = paint.color_at(p);
+                        src[3] *= self.state.alpha * cover;
+                        composite(operation, src, dst)
+                    }
+                };
+                let result = if clip < 1. {mix(dst, result, clip)} else {result};
+                bitmap.put(x, y, from_rgba(result));
+            }
+        }
+    }
+}
+
+/// Width and height of a text drawn with [Bitmap::draw_text]
+///
+/// # Arguments
+///
+/// `text` - text
+///
+/// `size` - font size in pixels
+///
+/// # Return
+///
+/// width and height in pixels
+pub fn text_size(text: &str, size: Float) -> (Float, Float) {
+    (text.chars().count() as Float * size, size)
+}
+
+// Anti-aliased drawing on bitmaps. Shapes are composited over the existing pixels,
+// coordinates are in pixels and the pixel centers are at half pixel positions.
+// Points that are not finite are skipped. Kept here with the renderer, see Bitmap docs.
+impl Bitmap {
+
+    /// Draw a line
+    ///
+    /// # Arguments
+    ///
+    /// `from` - start point
+    ///
+    /// `to` - end point
+    ///
+    /// `color` - line color, a Pixel or a Color
+    ///
+    /// `stroke` - line style
+    pub fn draw_line<C: Into<Color::Pixel>>(&mut self, from: (Float, Float), to: (Float, Float), color: C, stroke: &Stroke) {
+        self.draw_polyline(&[from, to], color, stroke);
+    }
+
+    /// Draw connected lines
+    ///
+    /// # Arguments
+    ///
+    /// `points` - line vertices
+    ///
+    /// `color` - line color, a Pixel or a Color
+    ///
+    /// `stroke` - line style
+    pub fn draw_polyline<C: Into<Color::Pixel>>(&mut self, points: &[(Float, Float)], color: C, stroke: &Stroke) {
+        self.draw_outline(points.to_vec(), false, color.into(), stroke);
+    }
+
+    /// Draw outline of a polygon
+    ///
+    /// # Arguments
+    ///
+    /// `points` - polygon vertices, the last vertex is connected to the first
+    ///
+    /// `color` - line color, a Pixel or a Color
+    ///
+    /// `stroke` - line style
+    pub fn draw_polygon<C: Into<Color::Pixel>>(&mut self, points: &[(Float, Float)], color: C, stroke: &Stroke) {
+        self.draw_outline(points.to_vec(), true, color.into(), stroke);
+    }
+
+    /// Fill a polygon
+    ///
+    /// # Arguments
+    ///
+    /// `points` - polygon vertices
+    ///
+    /// `color` - fill color, a Pixel or a Color
+    ///
+    /// `rule` - which parts of a self-intersecting polygon are filled
+    pub fn fill_polygon<C: Into<Color::Pixel>>(&mut self, points: &[(Float, Float)], color: C, rule: FillRule) {
+        self.fill_shapes(&[points.to_vec()], color.into(), rule);
+    }
+
+    /// Fill polygons as a single shape
+    ///
+    /// E.g. a polygon inside of another makes a hole with [FillRule::EvenOdd] or
+    /// with [FillRule::NonZero] if vertices are in the opposite order.
+    ///
+    /// # Arguments
+    ///
+    /// `polygons` - polygon vertices
+    ///
+    /// `color` - fill color, a Pixel or a Color
+    ///
+    /// `rule` - which parts of overlapping polygons are filled
+    pub fn fill_polygons<C: Into<Color::Pixel>>(&mut self, polygons: &[Vec<(Float, Float)>], color: C, rule: FillRule) {
+        self.fill_shapes(polygons, color.into(), rule);
+    }
+
+    /// Draw outline of a rectangle
+    ///
+    /// # Arguments
+    ///
+    /// `rect` - rectangle
+    ///
+    /// `color` - line color, a Pixel or a Color
+    ///
+    /// `stroke` - line style
+    pub fn draw_rect<C: Into<Color::Pixel>>(&mut self, rect: &Rect<Float>, color: C, stroke: &Stroke) {
+        self.draw_outline(rect_points(rect), true, color.into(), stroke);
+    }
+
+    /// Fill a rectangle
+    ///
+    /// # Arguments
+    ///
+    /// `rect` - rectangle
+    ///
+    /// `color` - fill color, a Pixel or a Color
+    pub fn fill_rect<C: Into<Color::Pixel>>(&mut self, rect: &Rect<Float>, color: C) {
+        self.fill_shapes(&[rect_points(rect)], color.into(), FillRule::NonZero);
+    }
+
+    /// Draw outline of a circle
+    ///
+    /// # Arguments
+    ///
+    /// `center` - circle center
+    ///
+    /// `radius` - circle radius
+    ///
+    /// `color` - line color, a Pixel or a Color
+    ///
+    /// `stroke` - line style
+    pub fn draw_circle<C: Into<Color::Pixel>>(&mut self, center: (Float, Float), radius: Float, color: C, stroke: &Stroke) {
+        self.draw_ellipse(center, radius, radius, color, stroke);
+    }
+
+    /// Fill a circle
+    ///
+    /// # Arguments
+    ///
+    /// `center` - circle center
+    ///
+    /// `radius` - circle radius
+    ///
+    /// `color` - fill color, a Pixel or a Color
+    pub fn fill_circle<C: Into<Color::Pixel>>(&mut self, center: (Float, Float), radius: Float, color: C) {
+        self.fill_ellipse(center, radius, radius, color);
+    }
+
+    /// Draw outline of an ellipse
+    ///
+    /// # Arguments
+    ///
+    /// `center` - ellipse center
+    ///
+    /// `rx` - horizontal radius
+    ///
+    /// `ry` - vertical radius
+    ///
+    /// `color` - line color, a Pixel or a Color
+    ///
+    /// `stroke` - line style
+    pub fn draw_ellipse<C: Into<Color::Pixel>>(&mut self, center: (Float, Float), rx: Float, ry: Float, color: C, stroke: &Stroke) {
+        if rx > 0. && ry > 0. {
+            self.draw_outline(ellipse_polygon(center, rx, ry), true, color.into(), stroke);
+        }
+    }
+
+    /// Fill an ellipse
+    ///
+    /// # Arguments
+    ///
+    /// `center` - ellipse center
+    ///
+    /// `rx` - horizontal radius
+    ///
+    /// `ry` - vertical radius
+    ///
+    /// `color` - fill color, a Pixel or a Color
+    pub fn fill_ellipse<C: Into<Color::Pixel>>(&mut self, center: (Float, Float), rx: Float, ry: Float, color: C) {
+        if rx > 0. && ry > 0. {
+            self.fill_shapes(&[ellipse_polygon(center, rx, ry)], color.into(), FillRule::NonZero);
+        }
+    }
+
+    /// Draw text with the embedded 8x8 pixel font
+    ///
+    /// Font has printable ASCII characters, others are drawn as boxes.
+    /// See [text_size] to get the drawn area.
+    ///
+    /// # Arguments
+    ///
+    /// `text` - drawn text
+    ///
+    /// `position` - top-left corner of the text
+    ///
+    /// `size` - font size in pixels, multiples of 8 are sharpest
+    ///
+    /// `color` - text color, a Pixel or a Color
+    pub fn draw_text<C: Into<Color::Pixel>>(&mut self, text: &str, position: (Float, Float), size: Float, color: C) {
+        let polygons = glyph_polygons(text, position.0, position.1, size, &Matrix::identity());
+        self.fill_shapes(&polygons, color.into(), FillRule::NonZero);
+    }
+
+    fn draw_outline(&mut self, mut points: Vec<Point>, closed: bool, color: Color::Pixel, stroke: &Stroke) {
+        points.retain(is_finite);
+        let polygons = stroke.outline(&[SubPath{points, closed}], 1.);
+        self.fill_shapes(&polygons, color, FillRule::NonZero);
+    }
+
+    fn fill_shapes(&mut self, polygons: &[Vec<Point>], color: Color::Pixel, rule: FillRule) {
+        let polygons: Vec<Vec<Point>> = polygons.iter()
+            .map(|polygon| polygon.iter().copied().filter(is_finite).collect())
+            .collect();
+        let coverage = match rasterize(&polygons, self.width(), self.height(), rule) {
+            Some(coverage) => coverage,
+            None => return,
+        };
+        let src = to_rgba(color);
+        for y in coverage.y..coverage.y + coverage.height {
+            for x in coverage.x..coverage.x + coverage.width {
+                let cover = coverage.get(x, y);
+                if cover <= 0. {
+                    continue;
+                }
+                if cover >= 1. && Color::a(color) == 0xFF {
+                    self.put(x, y, color);
+                    continue;
+                }
+                let dst = to_rgba(self.get(x, y));
+                let result = composite(Operation::SourceOver, [src[0], src[1], src[2], src[3] * cover], dst);
+                self.put(x, y, from_rgba(result));
+            }
+        }
+    }
+}
+
+fn number(args: &[Arg], index: usize) -> Float {
+    match args.get(index) {
+        Some(Arg::Number(n)) => *n,
+        Some(Arg::Text(t)) => t.parse().unwrap_or(0.),
+        _ => 0.,
+    }
+}
+
+fn text(args: &[Arg], index: usize) -> &str {
+    match args.get(index) {
+        Some(Arg::Text(t)) => t,
+        _ => "",
+    }
+}
+
+fn distance(a: Point, b: Point) -> Float {
+    ((b.0 - a.0).powi(2) + (b.1 - a.1).powi(2)).sqrt()
+}
+
+// unit vector and length
+fn normalize(v: Point) -> (Point, Float) {
+    let length = (v.0 * v.0 + v.1 * v.1).sqrt();
+    if length == 0. {
+        ((0., 0.), 0.)
+    } else {
+        ((v.0 / length, v.1 / length), length)
+    }
+}
+
+// polygons of strokes are all in the same direction so that overlapping parts do not cancel out
+fn oriented(mut polygon: Vec<Point>) -> Vec<Point> {
+    let count = polygon.len();
+    let area: Float = (0..count).map(|i| {
+        let (a, b) = (polygon[i], polygon[(i + 1) % count]);
+        a.0 * b.1 - b.0 * a.1
+    }).sum();
+    if area < 0. {
+        polygon.reverse();
+    }
+    polygon
+}
+
+fn is_finite(point: &Point) -> bool {
+    point.0.is_finite() && point.1.is_finite()
+}
+
+fn rect_points(rect: &Rect<Float>) -> Vec<Point> {
+    let (x, y, width, height) = (rect.x(), rect.y(), rect.width(), rect.height());
+    vec!((x, y), (x + width, y), (x + width, y + height), (x, y + height))
+}
+
+fn circle_polygon(center: Point, radius: Float) -> Vec<Point> {
+    ellipse_polygon(center, radius, radius)
+}
+
+fn ellipse_polygon(center: Point, rx: Float, ry: Float) -> Vec<Point> {
+    let radius = rx.max(ry);
+    let step = if radius > TOLERANCE {2. * (1. - TOLERANCE / radius).acos()} else {PI / 2.};
+    let count = ((2. * PI / step).ceil() as usize).clamp(8, MAX_SEGMENTS);
+    (0..count).map(|i| {
+        let (sin, cos) = (2. * PI * i as Float / count as Float).sin_cos();
+        (center.0 + rx * cos, center.1 + ry * sin)
+    }).collect()
+}
+
+// rectangles of glyph pixels, left and top are in user coordinates
+fn glyph_polygons(text: &str, left: Float, top: Float, size: Float, transform: &Matrix) -> Vec<Vec<Point>> {
+    let scale = size / font::GLYPH_SIZE as Float;
+    let advance = font::GLYPH_SIZE as Float * scale;
+    let mut polygons = Vec::new();
+    for (i, c) in text.chars().enumerate() {
+        let glyph_x = left + i as Float * advance;
+        for (gx, gy, gw) in font::glyph_runs(c) {
+            let (x, y) = (glyph_x + gx as Float * scale, top + gy as Float * scale);
+            let (right, bottom) = (x + gw as Float * scale, y + scale);
+            polygons.push(vec!(transform.apply(x, y), transform.apply(right, y),
+                transform.apply(right, bottom), transform.apply(x, bottom)));
+        }
+    }
+    polygons
+}
+
+// split polyline to dashes, pattern has an even length
+fn dash_polyline(points: &[Point], pattern: &[Float], offset: Float) -> Vec<Vec<Point>> {
+    let total: Float = pattern.iter().sum();
+    let mut index = 0;
+    let mut remaining = pattern[0];
+    let mut skip = offset.rem_euclid(total);
+    while skip > 0. {
+        if skip >= remaining {
+            skip -= remaining;
+            index = (index + 1) % pattern.len();
+            remaining = pattern[index];
+        } else {
+            remaining -= skip;
+            skip = 0.;
+        }
+    }
+    let mut dashes = Vec::new();
+    let mut current: Vec<Point> = points.iter().take(1).copied().collect();
+    for pair in points.windows(2) {
+        let (p, q) = (pair[0], pair[1]);
+        let length = distance(p, q);
+        let mut pos = 0.;
+        while length - pos > remaining {
+            pos += remaining;
+            let f = pos / length;
+            let point = (p.0 + (q.0 - p.0) * f, p.1 + (q.1 - p.1) * f);
+            if index % 2 == 0 {
+                current.push(point);
+                dashes.push(std::mem::take(&mut current));
+            } else {
+                current = vec!(point);
+            }
+            index = (index + 1) % pattern.len();
+            remaining = pattern[index];
+        }
+        remaining -= length - pos;
+        if index % 2 == 0 {
+            current.push(q);
+        }
+    }
+    if index % 2 == 0 && current.len() > 1 {
+        dashes.push(current);
+    }
+    dashes
+}
+
+// Anti-aliased coverage of polygons. Each edge adds its signed area to the pixels it
+// crosses and a prefix sum along each row gives the winding number, partial on edges.
+fn rasterize(polygons: &[Vec<Point>], width: u32, height: u32, rule: FillRule) -> Option<Mask> {
+    if !polygons.iter().flatten().all(is_finite) {
+        // points that cannot be placed are dropped
+        let finite: Vec<Vec<Point>> = polygons.iter()
+            .map(|polygon| polygon.iter().copied().filter(is_finite).collect())
+            .collect();
+        return rasterize(&finite, width, height, rule);
+    }
+    let points = polygons.iter().flatten();
+    let (min_x, min_y, max_x, max_y) = points.fold(
+        (Float::MAX, Float::MAX, Float::MIN, Float::MIN),
+        |(x0, y0, x1, y1), p| (x0.min(p.0), y0.min(p.1), x1.max(p.0), y1.max(p.1)));
+    let x0 = min_x.floor().max(0.) as u32;
+    let y0 = min_y.floor().max(0.) as u32;
+    let x1 = (max_x.ceil().max(0.) as u32).min(width);
+    let y1 = (max_y.ceil().max(0.) as u32).min(height);
+    if x0 >= x1 || y0 >= y1 {
+        return None;
+    }
+    let (mask_width, mask_height) = (x1 - x0, y1 - y0);
+    let stride = mask_width as usize + 2;
+    let mut accumulator = vec![0.; stride * mask_height as usize];
+    let limit = mask_width as Float;
+    for polygon in polygons {
+        for i in 0..polygon.len() {
+            let p = (polygon[i].0 - x0 as Float, polygon[i].1 - y0 as Float);
+            let q = (polygon[(i + 1) % polygon.len()].0 - x0 as Float, polygon[(i + 1) % polygon.len()].1 - y0 as Float);
+            // parts left or right of the mask are accumulated on its border
+            let mut cuts = vec!(0., 1.);
+            for border in [0., limit] {
+                if (p.0 - border) * (q.0 - border) < 0. {
+                    cuts.push((border - p.0) / (q.0 - p.0));
+                }
+            }
+            cuts.sort_by(|a, b| a.total_cmp(b));
+            let at = |t: Float| ((p.0 + (q.0 - p.0) * t).clamp(0., limit), p.1 + (q.1 - p.1) * t);
+            for cut in cuts.windows(2) {
+                accumulate_line(&mut accumulator, stride, mask_height as usize, at(cut[0]), at(cut[1]));
+            }
+        }
+    }
+    let mut values = Vec::with_capacity((mask_width * mask_height) as usize);
+    for row in accumulator.chunks(stride) {
+        let mut sum = 0.;
+        for value in &row[..mask_width as usize] {
+            sum += value;
+            let cover = match rule {
+                FillRule::NonZero => sum.abs().min(1.),
+                // odd windings are inside, fractions fold in between
+                FillRule::EvenOdd => {
+                    let w = sum.abs() % 2.;
+                    if w > 1. {2. - w} else {w}
+                }
+            };
+            values.push(cover);
+        }
+    }
+    Some(Mask{x: x0, y: y0, width: mask_width, height: mask_height, values})
+}
+
+fn accumulate_line(accumulator: &mut [Float], stride: usize, height: usize, p0: Point, p1: Point) {
+    if p0.1 == p1.1 {
+        return;
+    }
+    let (dir, p0, p1) = if p0.1 < p1.1 {(1., p0, p1)} else {(-1., p1, p0)};
+    let dxdy = (p1.0 - p0.0) / (p1.1 - p0.1);
+    let mut x = p0.0;
+    if p0.1 < 0. {
+        x -= p0.1 * dxdy;
+    }
+    let first_row = p0.1.max(0.) as usize;
+    let end_row = height.min(p1.1.ceil().max(0.) as usize);
+    for y in first_row..end_row {
+        let row = y * stride;
+        let dy = ((y + 1) as Float).min(p1.1) - (y as Float).max(p0.1);
+        let x_next = x + dxdy * dy;
+        let d = dy * dir;
+        let (xa, xb) = if x < x_next {(x, x_next)} else {(x_next, x)};
+        let xa_floor = xa.floor();
+        let xa_i = xa_floor as usize;
+        let xb_ceil = xb.ceil();
+        let xb_i = xb_ceil as usize;
+        if xb_i <= xa_i + 1 {
+            let xm = 0.5 * (x + x_next) - xa_floor;
+            accumulator[row + xa_i] += d - d * xm;
+            accumulator[row + xa_i + 1] += d * xm;
+        } else {
+            let s = 1. / (xb - xa);
+            let xa_f = xa - xa_floor;
+            let a0 = 0.5 * s * (1. - xa_f) * (1. - xa_f);
+            let xb_f = xb - xb_ceil + 1.;
+            let am = 0.5 * s * xb_f * xb_f;
+            accumulator[row + xa_i] += d * a0;
+            if xb_i == xa_i + 2 {
+                accumulator[row + xa_i + 1] += d * (1. - a0 - am);
+            } else {
+                let a1 = s * (1.5 - xa_f);
+                accumulator[row + xa_i + 1] += d * (a1 - a0);
+                for xi in xa_i + 2..xb_i - 1 {
+                    accumulator[row + xi] += d * s;
+                }
+                let a2 = a1 + (xb_i - xa_i - 3) as Float * s;
+                accumulator[row + xb_i - 1] += d * (1. - a2 - am);
+            }
+            accumulator[row + xb_i] += d * am;
+        }
+        x = x_next;
+    }
+}
+
+fn to_rgba(pixel: Color::Pixel) -> Rgba {
+    [Color::r(pixel), Color::g(pixel), Color::b(pixel), Color::a(pixel)].map(|c| c as Float / 255.)
+}
+
+fn from_rgba(color: Rgba) -> Color::Pixel {
+    let [r, g, b, a] = color.map(|c| (c.clamp(0., 1.) * 255.).round() as u8);
+    Color::rgba(r, g, b, a)
+}
+
+fn mix(a: Rgba, b: Rgba, f: Float) -> Rgba {
+    let pa = premultiply(a);
+    let pb = premultiply(b);
+    unpremultiply([0, 1, 2, 3].map(|i| pa[i] + (pb[i] - pa[i]) * f))
+}
+
+fn premultiply(c: Rgba) -> Rgba {
+    [c[0] * c[3], c[1] * c[3], c[2] * c[3], c[3]]
+}
+
+fn unpremultiply(c: Rgba) -> Rgba {
+    if c[3] <= 0. {
+        [0.; 4]
+    } else {
+        [c[0] / c[3], c[1] / c[3], c[2] / c[3], c[3].min(1.)]
+    }
+}
+
+fn composite(operation: Operation, src: Rgba, dst: Rgba) -> Rgba {
+    let (fa, fb) = operation.factors(src[3], dst[3]);
+    let s = premultiply(src);
+    let d = premultiply(dst);
+    unpremultiply([0, 1, 2, 3].map(|i| (s[i] * fa + d[i] * fb).min(1.)))
+}
+
+fn parse_font_size(font: &str) -> Option<Float> {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let re = RE.get_or_init(|| Regex::new(r"(\d+(?:\.\d+)?)(px|pt)").unwrap());
+    let cap = re.captures(font)?;
+    let size = cap.get(1)?.as_str().parse::<Float>().ok()?;
+    Some(if &cap[2] == "pt" {size * 4. / 3.} else {size})
+}
+
+fn parse_color(style: &str) -> Option<Rgba> {
+    Color::Color::parse(style).ok().map(|c| to_rgba(c.pixel()))
+}
+
+// kind, coordinates, stop count and stops
+fn parse_gradient(args: &[Arg]) -> Option<Paint> {
+    let n = |i| number(args, i);
+    match text(args, 0) {
+        "linear" => Some(Paint::Linear((n(1), n(2)), (n(3), n(4)), parse_stops(args, 5)?)),
+        "radial" if n(3) >= 0. && n(6) >= 0. =>
+            Some(Paint::Radial((n(1), n(2)), n(3), (n(4), n(5)), n(6), parse_stops(args, 7)?)),
+        "conic" => Some(Paint::Conic(n(1), (n(2), n(3)), parse_stops(args, 4)?)),
+        _ => None,
+    }
+}
+
+fn parse_stops(args: &[Arg], index: usize) -> Option<Vec<Stop>> {
+    let count = number(args, index) as usize;
+    let mut stops = Vec::with_capacity(count);
+    for i in 0..count {
+        let offset = number(args, index + 1 + i * 2);
+        if !(0. ..=1.).contains(&offset) {
+            return None;
+        }
+        stops.push((offset, parse_color(text(args, index + 2 + i * 2))?));
+    }
+    // stable sort keeps stops of the same offset in order
+    stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+    Some(stops)
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use crate::Rect;
+
+    use super::*;
+
+    fn render(width: u32, height: u32, context: &Context2D) -> Bitmap {
+        let mut bitmap = Bitmap::new(width, height);
+        Renderer::new().draw_context(&mut bitmap, context).unwrap();
+        bitmap
+    }
+
+    #[test]
+    fn test_fill_rect() {
+        let mut ctx = Context2D::new();
+        ctx.fill_color(Color::RED).fill_rect(&Rect::new(2., 2., 4., 3.));
+        let bitmap = render(10, 10, &ctx);
+        assert_eq!(bitmap.get(2, 2), Color::RED);
+        assert_eq!(bitmap.get(5, 4), Color::RED);
+        assert_eq!(bitmap.get(6, 4), Color::TRANSPARENT);
+        assert_eq!(bitmap.get(5, 5), Color::TRANSPARENT);
+        assert_eq!(bitmap.iter().filter(|p| **p == Color::RED).count(), 12);
+
+        // half pixel edges are anti-aliased
+        let mut ctx = Context2D::new();
+        ctx.fill_color(Color::BLACK).fill_rect(&Rect::new(0.5, 0., 2., 1.));
+        let bitmap = render(4, 1, &ctx);
+        assert_eq!(Color::a(bitmap.get(0, 0)), 128);
+        assert_eq!(Color::a(bitmap.get(1, 0)), 255);
+        assert_eq!(Color::a(bitmap.get(2, 0)), 128);
+        assert_eq!(Color::a(bitmap.get(3, 0)), 0);
+    }
+
+    #[test]
+    fn test_transform_and_state() {
+        let mut ctx = Context2D::new();
+        ctx.save()
+            .translate(5., 5.)
+            .scale(2., 2.)
+            .fill_style("rgb(0, 0, 255)")
+            .fill_rect(&Rect::new(0., 0., 1., 1.))
+            .restore()
+            .fill_rect(&Rect::new(0., 0., 1., 1.));
+        let bitmap = render(10, 10, &ctx);
+        assert_eq!(bitmap.get(6, 6), Color::BLUE);
+        assert_eq!(bitmap.get(7, 7), Color::TRANSPARENT);
+        assert_eq!(bitmap.get(0, 0), Color::BLACK);
+    }
+
+    #[test]
+    fn test_path() {
+        let mut ctx = Context2D::new();
+        ctx.fill_color(Color::GREEN)
+            .begin_path()
+            .arc(50., 50., 20., 0., 2. * PI)
+            .fill();
+        let bitmap = render(100, 100, &ctx);
+        assert_eq!(bitmap.get(50, 50), Color::GREEN);
+        assert_eq!(bitmap.get(50, 32), Color::GREEN);
+        assert_eq!(bitmap.get(50, 25), Color::TRANSPARENT);
+        assert_eq!(bitmap.get(34, 34), Color::TRANSPARENT); // outside on diagonal
+        let covered = bitmap.iter().map(|p| Color::a(*p) as Float / 255.).sum::<Float>();
+        assert!((covered - PI * 400.).abs() < 10., "{covered}");
+
+        let mut ctx = Context2D::new();
+        ctx.stroke_color(Color::RED)
+            .line_width(2.)
+            .begin_path()
+            .move_to(10., 10.)
+            .line_to(90., 10.)
+            .stroke();
+        let bitmap = render(100, 20, &ctx);
+        assert_eq!(bitmap.get(50, 9), Color::RED);
+        assert_eq!(bitmap.get(50, 10), Color::RED);
+        assert_eq!(bitmap.get(50, 11), Color::TRANSPARENT);
+        assert_eq!(bitmap.get(9, 9), Color::TRANSPARENT); // butt cap
+
+        let mut ctx = Context2D::new();
+        ctx.set_line_dash(&[10., 10.])
+            .line_width(2.)
+            .begin_path()
+            .move_to(0., 10.)
+            .line_to(100., 10.)
+            .stroke();
+        let bitmap = render(100, 20, &ctx);
+        assert_eq!(bitmap.get(5, 10), Color::BLACK);
+        assert_eq!(bitmap.get(15, 10), Color::TRANSPARENT);
+        assert_eq!(bitmap.get(25, 10), Color::BLACK);
+    }
+
+    #[test]
+    fn test_clip_and_clear() {
+        let mut ctx = Context2D::new();
+        ctx.fill_color(Color::WHITE)
+            .fill_rect(&Rect::new(0., 0., 10., 10.))
+            .clear_rect(&Rect::new(0., 0., 2., 2.))
+            .begin_path()
+            .rect(&Rect::new(5., 5., 5., 5.))
+            .clip()
+            .fill_color(Color::RED)
+            .fill_rect(&Rect::new(0., 0., 10., 10.));
+        let bitmap = render(10, 10, &ctx);
+        assert_eq!(bitmap.get(1, 1), Color::TRANSPARENT);
+        assert_eq!(bitmap.get(3, 3), Color::WHITE);
+        assert_eq!(bitmap.get(7, 7), Color::RED);
+    }
+
+    #[test]
+    fn test_composite_and_alpha() {
+        let mut ctx = Context2D::new();
+        ctx.fill_color(Color::WHITE)
+            .fill_rect(&Rect::new(0., 0., 4., 4.))
+            .global_alpha(0.5)
+            .fill_color(Color::BLACK)
+            .fill_rect(&Rect::new(0., 0., 2., 4.))
+            .global_alpha(1.)
+            .global_composite_operation(crate::graphics::context::CompositeOperation::DestinationOut)
+            .fill_rect(&Rect::new(3., 0., 1., 4.));
+        let bitmap = render(4, 4, &ctx);
+        assert_eq!(bitmap.get(0, 0), Color::rgb(128, 128, 128));
+        assert_eq!(bitmap.get(2, 0), Color::WHITE);
+        assert_eq!(bitmap.get(3, 0), Color::TRANSPARENT);
+    }
+
+    #[test]
+    fn test_gradient_and_text() {
+        let gradient = crate::graphics::context::Gradient::linear(0., 0., 10., 0.)
+            .color_stop(0., Color::BLACK)
+            .color_stop(1., Color::WHITE);
+        let mut ctx = Context2D::new();
+        ctx.fill_gradient(&gradient).fill_rect(&Rect::new(0., 0., 10., 1.));
+        let bitmap = render(10, 1, &ctx);
+        assert_eq!(Color::r(bitmap.get(0, 0)), 13);
+        assert!(Color::r(bitmap.get(5, 0)) > 128);
+        assert_eq!(Color::r(bitmap.get(9, 0)), 242);
+
+        let mut ctx = Context2D::new();
+        ctx.font("16px sans-serif")
+            .text_baseline(crate::graphics::context::TextBaseLine::Top)
+            .fill_text("I", 0., 0.);
+        let bitmap = render(16, 16, &ctx);
+        // 'I' has a vertical stem at the glyph columns 2 and 3
+        assert_eq!(bitmap.get(4, 8), Color::BLACK);
+        assert_eq!(bitmap.get(0, 8), Color::TRANSPARENT);
+    }
+
+    #[test]
+    fn test_bitmap_drawing() {
+        let mut bitmap = Bitmap::new(100, 100);
+        bitmap.draw_line((10., 10.5), (90., 10.5), Color::RED, &Stroke::new(1.));
+        assert_eq!(bitmap.get(50, 10), Color::RED);
+        assert_eq!(bitmap.get(50, 11), Color::TRANSPARENT);
+        // diagonal line is anti-aliased
+        bitmap.draw_line((10., 20.), (90., 60.), Color::BLUE, &Stroke::new(3.).cap(LineCap::Round));
+        assert!(bitmap.iter().any(|p| Color::a(*p) > 0 && Color::a(*p) < 0xFF));
+        assert_eq!(bitmap.get(9, 20), Color::BLUE); // round cap
+
+        let mut bitmap = Bitmap::rect(100, 100, Color::WHITE);
+        bitmap.fill_circle((50., 50.), 20., Color::BLACK);
+        bitmap.draw_circle((50., 50.), 30., Color::GREEN, &Stroke::new(4.));
+        assert_eq!(bitmap.get(50, 50), Color::BLACK);
+        assert_eq!(bitmap.get(50, 79), Color::GREEN);
+        assert_eq!(bitmap.get(50, 75), Color::WHITE);
+        // half transparent is composited over
+        bitmap.fill_rect(&Rect::new(0., 0., 10., 10.), Color::rgba(0, 0, 0, 0x80));
+        assert_eq!(bitmap.get(5, 5), Color::rgb(0x7F, 0x7F, 0x7F));
+        bitmap.fill_ellipse((50., 10.), 30., 5., Color::RED);
+        assert_eq!(bitmap.get(75, 10), Color::RED);
+        assert_eq!(bitmap.get(50, 16), Color::WHITE);
+
+        // pentagram center is inside with non-zero and outside with even-odd
+        let star: Vec<(Float, Float)> = (0..5).map(|i| {
+            let (sin, cos) = (i as Float * 4. * PI / 5. - PI / 2.).sin_cos();
+            (50. + 40. * cos, 50. + 40. * sin)
+        }).collect();
+        let mut bitmap = Bitmap::new(100, 100);
+        bitmap.fill_polygon(&star, Color::BLACK, FillRule::NonZero);
+        assert_eq!(bitmap.get(50, 50), Color::BLACK);
+        let mut bitmap = Bitmap::new(100, 100);
+        bitmap.fill_polygon(&star, Color::BLACK, FillRule::EvenOdd);
+        assert_eq!(bitmap.get(50, 50), Color::TRANSPARENT);
+        assert_eq!(bitmap.get(50, 15), Color::BLACK);
+
+        let outer = vec!((10., 10.), (90., 10.), (90., 90.), (10., 90.));
+        let inner = vec!((30., 30.), (70., 30.), (70., 70.), (30., 70.));
+        let mut bitmap = Bitmap::new(100, 100);
+        bitmap.fill_polygons(&[outer, inner], Color::BLACK, FillRule::EvenOdd);
+        assert_eq!(bitmap.get(20, 20), Color::BLACK);
+        assert_eq!(bitmap.get(50, 50), Color::TRANSPARENT);
+
+        let mut bitmap = Bitmap::new(100, 100);
+        bitmap.draw_rect(&Rect::new(10., 10., 80., 80.), Color::BLACK, &Stroke::new(2.));
+        bitmap.draw_polygon(&[(20., 20.), (80., 20.), (50., 80.)], Color::RED, &Stroke::new(2.).join(LineJoin::Bevel));
+        bitmap.draw_polyline(&[(0., 95.), (100., 95.)], Color::BLUE, &Stroke::new(2.).dash(&[10.], 0.));
+        assert_eq!(bitmap.get(10, 50), Color::BLACK);
+        assert_eq!(bitmap.get(50, 50), Color::TRANSPARENT);
+        assert_eq!(bitmap.get(50, 20), Color::RED);
+        assert_eq!(bitmap.get(5, 95), Color::BLUE);
+        assert_eq!(bitmap.get(15, 95), Color::TRANSPARENT);
+
+        let mut bitmap = Bitmap::new(32, 16);
+        bitmap.draw_text("II", (0., 0.), 16., Color::BLACK);
+        assert_eq!(text_size("II", 16.), (32., 16.));
+        assert_eq!(bitmap.get(4, 8), Color::BLACK);
+        assert_eq!(bitmap.get(20, 8), Color::BLACK);
+        assert_eq!(bitmap.get(0, 8), Color::TRANSPARENT);
+    }
+
+    #[test]
+    fn test_bitmap_non_finite() {
+        let mut bitmap = Bitmap::new(10, 10);
+        bitmap.fill_polygon(&[(Float::INFINITY, 5.), (-1., 5.), (0., 10.)], Color::BLACK, FillRule::NonZero);
+        bitmap.fill_polygons(&[vec!((Float::NAN, 0.), (1., 1.), (2., 0.))], Color::BLACK, FillRule::EvenOdd);
+        bitmap.draw_polyline(&[(0., 0.), (Float::NEG_INFINITY, 5.), (10., 10.)], Color::BLACK, &Stroke::new(1.).dash(&[2.], 0.));
+        bitmap.fill_circle((Float::NAN, 5.), 3., Color::BLACK);
+        bitmap.draw_ellipse((5., 5.), Float::INFINITY, 3., Color::BLACK, &Stroke::new(1.));
+        bitmap.fill_rect(&Rect::new(0., 0., Float::INFINITY, 2.), Color::BLACK);
+        // finite points of the polyline are still drawn
+        assert!(bitmap.iter().any(|p| Color::a(*p) > 0));
+        let mut bitmap = Bitmap::new(10, 10);
+        bitmap.fill_polygon(&[(0., 0.), (10., 0.), (Float::NAN, 5.), (10., 10.), (0., 10.)], Color::BLACK, FillRule::NonZero);
+        assert_eq!(bitmap.get(5, 5), Color::BLACK);
+    }
+
+    #[test]
+    fn test_non_finite_path() {
+        let mut ctx = Context2D::new();
+        ctx.fill_color(Color::BLACK)
+            .begin_path()
+            .move_to(0., 0.)
+            .line_to(Float::NAN, 5.)
+            .line_to(10., Float::INFINITY)
+            .line_to(10., 10.)
+            .line_to(0., 10.)
+            .fill();
+        // non-finite points are dropped, the rest is a triangle
+        let bitmap = render(10, 10, &ctx);
+        assert_eq!(bitmap.get(2, 7), Color::BLACK);
+        assert_eq!(bitmap.get(7, 2), Color::TRANSPARENT);
+    }
+
+    #[test]
+    fn test_unsupported() {
+        let mut bitmap = Bitmap::new(10, 10);
+        let mut ctx = Context2D::new();
+        ctx.fill_rect(&Rect::new(0., 0., 10., 10.)).draw_image("foo", 0., 0.);
+        assert!(Renderer::new().draw_context(&mut bitmap, &ctx).is_err());
+        assert_eq!(bitmap.get(0, 0), Color::TRANSPARENT);
+    }
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(parse_color("#f00"), Some([1., 0., 0., 1.]));
+        assert_eq!(parse_color("#0000FF80").map(from_rgba), Some(Color::rgba(0, 0, 255, 128)));
+        assert_eq!(parse_color("rgba(255, 0, 0, 0.5)").map(from_rgba), Some(Color::rgba(255, 0, 0, 128)));
+        assert_eq!(parse_color("red").map(from_rgba), Some(Color::RED));
+        assert_eq!(parse_color("nocolor"), None);
+        assert_eq!(parse_font_size("bold 12pt serif"), Some(16.));
+    }
+}
@@ -0,0 +1,183 @@
+use crate::Rect;
+use crate::graphics::bitmap::{Bitmap, BitmapData};
+use crate::graphics::canvas::Canvas;
+
+// granularity of change detection
+static DIRTY_TILE_WIDTH: u32 = 64;
+static DIRTY_TILE_HEIGHT: u32 = 64;
+
+/// Canvas surface that sends only changed areas
+///
+/// Surface keeps the last frame sent and on each draw only the tiles that
+/// differ from it (or are explicitly invalidated) are sent to UI. Frames
+/// are drawn to the top-left corner of the canvas.
+///
+/// # Example
+///
+/// ```no_run
+/// # use gemgui::graphics::canvas::Canvas;
+/// # use gemgui::graphics::surface::CanvasSurface;
+/// # use gemgui::graphics::bitmap::Bitmap;
+/// # use gemgui::graphics::color as Color;
+/// fn plot(canvas: &Canvas) {
+///     let mut surface = CanvasSurface::new(canvas);
+///     let mut bitmap = Bitmap::rect(1920, 1080, Color::WHITE);
+///     surface.draw_bitmap(&bitmap); // first frame is sent as whole
+///     bitmap.put(10, 10, Color::BLACK);
+///     surface.draw_bitmap(&bitmap); // only a single tile is sent
+/// }
+/// ```
+pub struct CanvasSurface {
+    canvas: Canvas,
+    previous: Option<Bitmap>,
+    invalidated: Vec<Rect<u32>>,
+}
+
+impl CanvasSurface {
+
+    /// New surface on a canvas
+    ///
+    /// # Arguments
+    ///
+    /// `canvas` - canvas where surface is drawn
+    ///
+    /// # Return
+    ///
+    /// new CanvasSurface
+    pub fn new(canvas: &Canvas) -> CanvasSurface {
+        CanvasSurface {
+            canvas: canvas.clone(),
+            previous: None,
+            invalidated: Vec::new(),
+        }
+    }
+
+    /// Canvas of this surface
+    pub fn canvas(&self) -> &Canvas {
+        &self.canvas
+    }
+
+    /// Mark an area to be sent on the next draw even it has not changed
+    ///
+    /// E.g. if canvas has been drawn by other means.
+    ///
+    /// # Arguments
+    ///
+    /// `rect` - area in canvas coordinates
+    pub fn invalidate(&mut self, rect: Rect<u32>) {
+        self.invalidated.push(rect);
+    }
+
+    /// Send the whole bitmap on the next draw
+    pub fn invalidate_all(&mut self) {
+        self.previous = None;
+        self.invalidated.clear();
+    }
+
+    /// Draws changes of a given bitmap
+    ///
+    /// draw_callback is called if enabled, see [Canvas::on_draw]
+    ///
+    /// # Arguments
+    ///
+    /// `bitmap` - to be drawn
+    pub fn draw_bitmap(&mut self, bitmap: &dyn BitmapData) {
+        self.paint_bitmap(bitmap, true)
+    }
+
+    /// Draws changes of a given bitmap
+    ///
+    /// draw_callback is NOT called even enabled
+    ///
+    /// # Arguments
+    ///
+    /// `bitmap` - to be drawn
+    pub fn paint(&mut self, bitmap: &dyn BitmapData) {
+        self.paint_bitmap(bitmap, false)
+    }
+
+    fn paint_bitmap(&mut self, bitmap: &dyn BitmapData, as_draw: bool) {
+        let areas = dirty_rects(self.previous.as_ref(), bitmap, &self.invalidated);
+        self.canvas.paint_areas(bitmap, &areas, as_draw);
+        self.invalidated.clear();
+        if !areas.is_empty() {
+            self.previous = Some(Bitmap::from(bitmap));
+        }
+    }
+}
+
+fn intersects(a: &Rect<u32>, b: &Rect<u32>) -> bool {
+    a.x < b.x + b.width && b.x < a.x + a.width &&
+    a.y < b.y + b.height && b.y < a.y + a.height
+}
+
+fn is_changed(previous: &Bitmap, current: &dyn BitmapData, tile: &Rect<u32>) -> bool {
+    (tile.y..tile.y + tile.height).any(|row|
+        previous.slice(tile.x, tile.width, row) != current.slice(tile.x, tile.width, row))
+}
+
+// Changed tiles, horizontally adjacent tiles are joined
+fn dirty_rects(previous: Option<&Bitmap>, current: &dyn BitmapData, invalidated: &[Rect<u32>]) -> Vec<Rect<u32>> {
+    let width = current.width();
+    let height = current.height();
+    if width == 0 || height == 0 {
+        return Vec::new();
+    }
+    let previous = match previous {
+        Some(p) if p.width() == width && p.height() == height => p,
+        _ => return vec!(Rect::new(0, 0, width, height)),
+    };
+    let mut rects: Vec<Rect<u32>> = Vec::new();
+    for y in (0..height).step_by(DIRTY_TILE_HEIGHT as usize) {
+        let tile_height = DIRTY_TILE_HEIGHT.min(height - y);
+        let mut run: Option<Rect<u32>> = None;
+        for x in (0..width).step_by(DIRTY_TILE_WIDTH as usize) {
+            let tile = Rect::new(x, y, DIRTY_TILE_WIDTH.min(width - x), tile_height);
+            let dirty = invalidated.iter().any(|r| intersects(r, &tile)) || is_changed(previous, current, &tile);
+            run = match (run, dirty) {
+                (Some(mut r), true) => {r.width += tile.width; Some(r)},
+                (None, true) => Some(tile),
+                (Some(r), false) => {rects.push(r); None},
+                (None, false) => None,
+            };
+        }
+        if let Some(r) = run {
+            rects.push(r);
+        }
+    }
+    rects
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use crate::graphics::color as Color;
+
+    use super::*;
+
+    #[test]
+    fn test_dirty_rects() {
+        let previous = Bitmap::rect(200, 100, Color::WHITE);
+        let mut current = previous.clone();
+        assert_eq!(dirty_rects(Some(&previous), &current, &[]).len(), 0);
+        assert_eq!(dirty_rects(None, &current, &[]).len(), 1);
+
+        current.put(10, 10, Color::BLACK);
+        current.put(70, 10, Color::BLACK);
+        current.put(199, 99, Color::BLACK);
+        let rects = dirty_rects(Some(&previous), &current, &[]);
+        assert_eq!(rects.len(), 2);
+        assert_eq!((rects[0].x(), rects[0].y(), rects[0].width(), rects[0].height()), (0, 0, 128, 64));
+        assert_eq!((rects[1].x(), rects[1].y(), rects[1].width(), rects[1].height()), (192, 64, 8, 36));
+
+        let rects = dirty_rects(Some(&previous), &previous, &[Rect::new(130, 70, 2, 2)]);
+        assert_eq!(rects.len(), 1);
+        assert_eq!((rects[0].x(), rects[0].y(), rects[0].width(), rects[0].height()), (128, 64, 64, 36));
+
+        let resized = Bitmap::rect(100, 100, Color::WHITE);
+        let rects = dirty_rects(Some(&previous), &resized, &[]);
+        assert_eq!(rects.len(), 1);
+        assert_eq!((rects[0].width(), rects[0].height()), (100, 100));
+    }
+}
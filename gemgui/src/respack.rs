@@ -1,12 +1,15 @@
+use std::io::Write;
 use std::path::Path;
+use std::sync::OnceLock;
 
 
-#[allow(dead_code)]
-fn html_metadata(toml_path: &str)->Option<String> {
+// package.metadata of Cargo.toml
+fn package_metadata(toml_path: &str) -> Option<serde_json::Value> {
     let path = std::env::var("CARGO_MANIFEST_DIR").unwrap();
     let meta = match cargo_metadata::MetadataCommand::new()
     .manifest_path(toml_path)
     .current_dir(&path)
+    .no_deps()
     .exec()  {
         Ok(o) => Some(o),
         Err(e) => {
@@ -14,9 +17,17 @@ fn html_metadata(toml_path: &str)->Option<String> {
             None
         }
     }?;
-    
-    let root = meta.root_package().unwrap();
-    Some(String::from(root.metadata["html"].as_str()?))
+
+    let toml_path = std::fs::canonicalize(Path::new(&path).join(toml_path)).ok()?;
+    let root = meta.packages.into_iter()
+        .find(|p| std::fs::canonicalize(&p.manifest_path).ok().as_ref() == Some(&toml_path))?;
+    Some(root.metadata)
+}
+
+#[allow(dead_code)]
+fn html_metadata(toml_path: &str)->Option<String> {
+    let metadata = package_metadata(toml_path)?;
+    Some(String::from(metadata["html"].as_str()?))
 }
 
 fn get_extension_from_filename(filename: &str) -> Option<&str> {
@@ -54,6 +65,415 @@ fn minify(name : &str, data : &[u8]) -> Vec<u8> {
     }
 }
 
+/// Compression of packed resources
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Compression {
+    /// Stored as is
+    #[default]
+    None,
+    /// Gzip, served with `Content-Encoding: gzip`
+    Gzip,
+    /// Brotli, served with `Content-Encoding: br`
+    Brotli,
+}
+
+/// Options for [pack_with]
+///
+/// Files are processed in order: includes, variables, image inlining, minify
+/// and compression. Bundles are concatenated before their processing.
+#[derive(Clone, Debug, Default)]
+pub struct PackOptions {
+    minify: bool,
+    compression: Compression,
+    includes: bool,
+    variables: bool,
+    bundles: Vec<(String, Vec<String>)>,
+    inline_limit: usize,
+    element_ids: bool,
+}
+
+impl PackOptions {
+    /// New options, no minify nor compression
+    pub fn new() -> PackOptions {
+        PackOptions::default()
+    }
+
+    /// Minify JS, JSON and CSS files
+    ///
+    /// # Arguments
+    ///
+    /// `minify` - apply minify
+    pub fn minify(mut self, minify: bool) -> Self {
+        self.minify = minify;
+        self
+    }
+
+    /// Pre-compress files
+    ///
+    /// A file is stored compressed only if that makes it smaller, e.g.
+    /// images are typically stored as is.
+    ///
+    /// # Arguments
+    ///
+    /// `compression` - compression
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Resolve `<!-- include file -->` directives in HTML files
+    ///
+    /// The file path is relative to the packed directory and included files
+    /// can have includes of their own.
+    ///
+    /// # Arguments
+    ///
+    /// `includes` - resolve includes
+    pub fn includes(mut self, includes: bool) -> Self {
+        self.includes = includes;
+        self
+    }
+
+    /// Substitute `{{VAR}}` placeholders in HTML, CSS, JS, JSON, SVG and text files
+    ///
+    /// A value is looked from the build environment and then from `package.metadata`
+    /// of the application `Cargo.toml`, e.g.
+    /// ```toml
+    /// [package.metadata]
+    /// TITLE = "My application"
+    /// ```
+    ///
+    /// # Arguments
+    ///
+    /// `variables` - substitute variables
+    pub fn variables(mut self, variables: bool) -> Self {
+        self.variables = variables;
+        self
+    }
+
+    /// Concatenate files as a new resource
+    ///
+    /// Bundled files are packed also as themselves.
+    ///
+    /// # Arguments
+    ///
+    /// `name` - name of the bundle, e.g. `"app.js"`
+    ///
+    /// `files` - files in the packed directory in order of concatenation
+    pub fn bundle(mut self, name: &str, files: &[&str]) -> Self {
+        self.bundles.push((name.to_string(), files.iter().map(|f| f.to_string()).collect()));
+        self
+    }
+
+    /// Inline images as data URIs
+    ///
+    /// Images referred in HTML `<img src="...">` or CSS `url(...)` are inlined if
+    /// their size is at most the given limit.
+    ///
+    /// # Arguments
+    ///
+    /// `max_size` - size limit in bytes, 0 does not inline
+    pub fn inline_images(mut self, max_size: usize) -> Self {
+        self.inline_limit = max_size;
+        self
+    }
+
+    /// Generate element ids of HTML files
+    ///
+    /// `id` attributes of packed HTML files are generated as constants in
+    /// an `element_ids` module, and as an `element_id!` macro that fails
+    /// compilation for an unknown id. Ids duplicated in a page or across
+    /// pages are reported as build warnings.
+    /// ```ignore
+    /// # #[cfg(never)] mod foo {
+    /// include!(concat!(env!("OUT_DIR"), "/generated.rs"));
+    /// # fn bar(ui: &UiRef) {
+    /// let content = ui.element(element_ids::CONTENT);
+    /// let content = ui.element(element_id!("content"));
+    /// # }
+    /// # }
+    /// ```
+    ///
+    /// # Arguments
+    ///
+    /// `element_ids` - generate ids
+    pub fn element_ids(mut self, element_ids: bool) -> Self {
+        self.element_ids = element_ids;
+        self
+    }
+}
+
+/// A resource packed in the binary
+///
+/// [pack] generates a `RESOURCES` array of these, see [filemap_from](crate::filemap_from)
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug)]
+pub struct PackedResource {
+    name: &'static str,
+    data: &'static [u8],
+    encoding: Option<&'static str>,
+    etag: &'static str,
+    modified: Option<u64>,
+}
+
+#[allow(dead_code)]
+impl PackedResource {
+    /// New packed resource, used by the generated code
+    ///
+    /// # Arguments
+    ///
+    /// `name` - file name
+    ///
+    /// `data` - file content as stored
+    ///
+    /// `encoding` - content encoding of stored data, e.g. `Some("gzip")`
+    ///
+    /// `etag` - hash of the content
+    ///
+    /// `modified` - modification time of file as seconds since UNIX epoch
+    pub const fn new(name: &'static str, data: &'static [u8], encoding: Option<&'static str>, etag: &'static str, modified: Option<u64>) -> PackedResource {
+        PackedResource{name, data, encoding, etag, modified}
+    }
+
+    /// File name
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Content as stored, i.e. compressed if [encoding](Self::encoding) is set
+    pub fn data(&self) -> &'static [u8] {
+        self.data
+    }
+
+    /// Content encoding
+    pub fn encoding(&self) -> Option<&'static str> {
+        self.encoding
+    }
+
+    /// Hash of the content
+    pub fn etag(&self) -> &'static str {
+        self.etag
+    }
+
+    /// Modification time of file as seconds since UNIX epoch
+    pub fn modified(&self) -> Option<u64> {
+        self.modified
+    }
+}
+
+// FNV-1a, stable over builds and platforms
+pub (crate) fn content_hash(data: &[u8]) -> String {
+    let hash = data.iter().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    });
+    format!("{hash:016x}")
+}
+
+// <!-- include file --> directives, stack is used to detect recursion
+fn resolve_includes(directory: &Path, text: &str, stack: &mut Vec<String>) -> Result<String, String> {
+    static RE_INCLUDE: OnceLock<regex::Regex> = OnceLock::new();
+    let re_include = RE_INCLUDE.get_or_init(|| regex::Regex::new(r"<!--\s*include\s+(\S+?)\s*-->").unwrap());
+    let mut result = String::new();
+    let mut last = 0;
+    for capture in re_include.captures_iter(text) {
+        let directive = capture.get(0).unwrap();
+        let file = &capture[1];
+        if stack.iter().any(|f| f == file) {
+            return Err(format!("recursive include {file:#?}"));
+        }
+        let included = std::fs::read_to_string(directory.join(file))
+            .map_err(|e| format!("cannot include {file:#?}: {e}"))?;
+        stack.push(file.to_string());
+        let included = resolve_includes(directory, &included, stack)?;
+        stack.pop();
+        result.push_str(&text[last..directive.start()]);
+        result.push_str(&included);
+        last = directive.end();
+    }
+    result.push_str(&text[last..]);
+    Ok(result)
+}
+
+// {{VAR}} placeholders, all undefined are reported
+fn substitute<F>(text: &str, mut lookup: F) -> Result<String, String>
+where F: FnMut(&str) -> Option<String> {
+    static RE_VAR: OnceLock<regex::Regex> = OnceLock::new();
+    let re_var = RE_VAR.get_or_init(|| regex::Regex::new(r"\{\{\s*([A-Za-z_][A-Za-z_0-9]*)\s*\}\}").unwrap());
+    let mut missing = Vec::new();
+    let result = re_var.replace_all(text, |capture: &regex::Captures| {
+        lookup(&capture[1]).unwrap_or_else(|| {
+            missing.push(format!("{{{{{}}}}}", &capture[1]));
+            String::new()
+        })
+    }).to_string();
+    if missing.is_empty() {
+        Ok(result)
+    } else {
+        Err(format!("undefined variables {}", missing.join(", ")))
+    }
+}
+
+fn image_mime(name: &str) -> Option<&'static str> {
+    match get_extension_from_filename(name)?.to_ascii_lowercase().as_str() {
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "gif" => Some("image/gif"),
+        "svg" => Some("image/svg+xml"),
+        "webp" => Some("image/webp"),
+        "avif" => Some("image/avif"),
+        "bmp" => Some("image/bmp"),
+        "ico" => Some("image/x-icon"),
+        _ => None,
+    }
+}
+
+// <img src="..."> and url(...) references to local images as data URIs
+fn inline_images(directory: &Path, text: &str, max_size: usize) -> Result<String, String> {
+    static RE_IMAGE: OnceLock<regex::Regex> = OnceLock::new();
+    let re_image = RE_IMAGE.get_or_init(|| regex::Regex::new(r#"(<img\b[^>]*?\bsrc\s*=\s*["'])([^"']+)(["'])|(url\(\s*["']?)([^"')]+?)(["']?\s*\))"#).unwrap());
+    let mut missing = Vec::new();
+    let result = re_image.replace_all(text, |capture: &regex::Captures| {
+        let (open, file, close) = match capture.get(2) {
+            Some(file) => (&capture[1], file.as_str(), &capture[3]),
+            None => (&capture[4], &capture[5], &capture[6]),
+        };
+        let is_local = !(file.contains(':') || file.starts_with('/') || file.starts_with('#'));
+        let data_uri = image_mime(file).filter(|_| is_local).and_then(|mime| {
+            match std::fs::read(directory.join(file)) {
+                Ok(data) if data.len() <= max_size => Some(format!("data:{mime};base64,{}", base64::encode(data))),
+                Ok(_) => None,
+                Err(e) => {
+                    missing.push(format!("{file:#?}: {e}"));
+                    None
+                }
+            }
+        });
+        format!("{open}{}{close}", data_uri.as_deref().unwrap_or(file))
+    }).to_string();
+    if missing.is_empty() {
+        Ok(result)
+    } else {
+        Err(format!("cannot inline {}", missing.join(", ")))
+    }
+}
+
+// Pipeline of a file content, errors are listed
+fn process(directory: &Path, name: &str, content: Vec<u8>, options: &PackOptions, lookup: &mut dyn FnMut(&str) -> Option<String>) -> Result<Vec<u8>, Vec<String>> {
+    let ext = get_extension_from_filename(name).unwrap_or_default().to_ascii_lowercase();
+    let is_html = matches!(ext.as_str(), "html" | "htm");
+    let is_text = is_html || matches!(ext.as_str(), "css" | "js" | "mjs" | "json" | "svg" | "txt" | "xml");
+    if !is_text || !(options.includes || options.variables || options.inline_limit > 0) {
+        return Ok(content);
+    }
+    let mut text = String::from_utf8(content).map_err(|e| vec!(format!("{name}: {e}")))?;
+    let mut errors = Vec::new();
+    if options.includes && is_html {
+        match resolve_includes(directory, &text, &mut vec!(name.to_string())) {
+            Ok(t) => text = t,
+            Err(e) => errors.push(format!("{name}: {e}")),
+        }
+    }
+    if options.variables {
+        match substitute(&text, &mut *lookup) {
+            Ok(t) => text = t,
+            Err(e) => errors.push(format!("{name}: {e}")),
+        }
+    }
+    if options.inline_limit > 0 && (is_html || ext == "css") {
+        match inline_images(directory, &text, options.inline_limit) {
+            Ok(t) => text = t,
+            Err(e) => errors.push(format!("{name}: {e}")),
+        }
+    }
+    if errors.is_empty() {
+        Ok(text.into_bytes())
+    } else {
+        Err(errors)
+    }
+}
+
+// values of id attributes
+fn html_ids(html: &str) -> Vec<String> {
+    static RE_ID: OnceLock<regex::Regex> = OnceLock::new();
+    let re_id = RE_ID.get_or_init(|| regex::Regex::new(r#"\sid\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'>]+))"#).unwrap());
+    re_id.captures_iter(html)
+        .filter_map(|capture| capture.get(1).or(capture.get(2)).or(capture.get(3)))
+        .map(|id| id.as_str().trim().to_string())
+        .filter(|id| !id.is_empty())
+        .collect()
+}
+
+// name with characters that are not valid in a Rust identifier replaced by underscores
+fn rust_identifier(name: &str) -> String {
+    static RE_CHARS: OnceLock<regex::Regex> = OnceLock::new();
+    static RE_FIRST: OnceLock<regex::Regex> = OnceLock::new();
+    let re_chars = RE_CHARS.get_or_init(|| regex::Regex::new(r"[^A-Za-z_0-9]").unwrap());
+    let re_first = RE_FIRST.get_or_init(|| regex::Regex::new(r"^[^A-Za-z_]").unwrap());
+    let result = re_chars.replace_all(name, "_");
+    re_first.replace_all(&result, "_").into_owned()
+}
+
+// element_ids module and element_id! macro lines, and warnings of duplicates
+fn element_id_lines(pages: &[(String, Vec<String>)]) -> (Vec<String>, Vec<String>) {
+    let mut ids: std::collections::BTreeMap<&str, Vec<&str>> = std::collections::BTreeMap::new();
+    let mut warnings = Vec::new();
+    for (page, page_ids) in pages {
+        let mut seen = std::collections::HashSet::new();
+        let mut duplicates = std::collections::HashSet::new();
+        for id in page_ids {
+            if !seen.insert(id) {
+                if duplicates.insert(id) {
+                    warnings.push(format!("id {id:#?} is duplicated in {page}"));
+                }
+                continue;
+            }
+            ids.entry(id).or_default().push(page);
+        }
+    }
+    for (id, id_pages) in &ids {
+        if id_pages.len() > 1 {
+            warnings.push(format!("id {id:#?} is in several pages: {}", id_pages.join(", ")));
+        }
+    }
+
+    let mut constants: std::collections::HashMap<String, &str> = std::collections::HashMap::new();
+    let mut lines = vec!(
+        "/// Element ids of packed HTML files".to_string(),
+        "#[allow(dead_code)]".to_string(),
+        "mod element_ids {".to_string());
+    for (id, id_pages) in &ids {
+        let rust_name = rust_identifier(id).to_ascii_uppercase();
+        if let Some(other) = constants.get(&rust_name) {
+            warnings.push(format!("ids {other:#?} and {id:#?} are both {rust_name}, only the first is generated"));
+            continue;
+        }
+        lines.push(format!("    /// In {}", id_pages.join(", ")));
+        lines.push(format!("    pub const {rust_name}: &str = {id:?};"));
+        constants.insert(rust_name, id);
+    }
+    lines.push("}".to_string());
+    lines.push("#[allow(unused_macros)]".to_string());
+    lines.push("macro_rules! element_id {".to_string());
+    for id in ids.keys() {
+        lines.push(format!("    ({id:?}) => {{{id:?}}};"));
+    }
+    lines.push("    ($id:literal) => {compile_error!(concat!(\"Element id \", stringify!($id), \" is not in HTML resources\"))};".to_string());
+    lines.push("}".to_string());
+    (lines, warnings)
+}
+
+fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn brotli(data: &[u8]) -> Vec<u8> {
+    let mut encoder = brotli::CompressorWriter::new(Vec::new(), 4096, 11, 22);
+    encoder.write_all(data).unwrap();
+    encoder.into_inner()
+}
+
 /// Pack a folder as a resources
 /// Assumed to be called at build.rs, i.e. build time only
 /// Generates a source file "generated.rs" that can be 
@@ -99,43 +519,242 @@ fn minify(name : &str, data : &[u8]) -> Vec<u8> {
 /// ´try_minify´ -  May to apply minify / compress.
 /// 
 pub fn pack<PathStr>(directory: PathStr, try_minify: bool) 
+where PathStr: AsRef<Path> {
+    pack_with(directory, &PackOptions::new().minify(try_minify));
+}
+
+/// Pack a folder as a resources with options
+///
+/// As [pack], but e.g. files can be pre-compressed
+/// ```ignore
+///  # #[cfg(never)] mod foo {
+/// fn main() {
+///     println!("cargo:rerun-if-changed=gui");
+///     gemgui::respack::pack_with("gui", &PackOptions::new().compression(Compression::Gzip));
+/// }
+/// # }
+/// ```
+/// Files are included as raw bytes with `include_bytes!` and decompressed
+/// only if a client does not accept the encoding.
+///
+/// A missing include, variable, bundled file or inlined image fails the build.
+///
+///  # Arguments
+///
+/// ´directory´ - Directory to read in resources. Does not read sub directories.
+///
+/// ´options´ - Pack options
+///
+pub fn pack_with<PathStr>(directory: PathStr, options: &PackOptions)
 where PathStr: AsRef<Path> {
     let out_dir = std::env::var_os("OUT_DIR").unwrap();
     let dest_path = std::path::Path::new(&out_dir).join("generated.rs");
-    let mut lines = Vec::new();
-    let mut names = Vec::new();
-    let dir = std::fs::read_dir(directory).unwrap();
-    for entry in dir {
+    let pack_dir = std::path::Path::new(&out_dir).join("respack");
+    std::fs::create_dir_all(&pack_dir).unwrap();
+    let directory = directory.as_ref();
+    let modified_secs = |metadata: std::io::Result<std::fs::Metadata>| metadata.and_then(|m| m.modified()).ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs());
+    let mut sources = Vec::new();
+    let mut errors = Vec::new();
+    for entry in std::fs::read_dir(directory).unwrap() {
         let file = entry.unwrap();
         if file.file_type().unwrap().is_file() {
-            let content_raw = std::fs::read(file.path()).unwrap();
             let name = file.file_name().into_string().unwrap(); 
             if name.starts_with('.') {
                 continue; // no hidden files (mainly due .DS_Store in mac)
             }
-            let content = if try_minify {
-                minify(&name, &content_raw)
-            } else  {
-                content_raw
-            };
-            let coded = base64::encode(content);
-            let re = regex::Regex::new(r"[^A-Za-z_0-9]").unwrap();
-            let result = re.replace_all(&name, "_");
-            let re = regex::Regex::new(r"^[^A-Za-z_]").unwrap();
-            let result = re.replace_all(&result, "_");
-            let rust_name = result.to_ascii_uppercase();
-
-            let line = format!("const {}: &str = {:#?};", &rust_name, coded);
-            lines.push(line);
-            names.push((name.to_owned(), rust_name.to_string()));
+            let content = std::fs::read(file.path()).unwrap();
+            sources.push((name, content, modified_secs(file.metadata())));
         }
     }
+    for (name, files) in &options.bundles {
+        if sources.iter().any(|(n, _, _)| n == name) {
+            errors.push(format!("{name}: bundle overrides a file"));
+            continue;
+        }
+        let separator: &[u8] = if get_extension_from_filename(name) == Some("js") {b";\n"} else {b"\n"};
+        let mut content = Vec::new();
+        let mut modified = None;
+        for file in files {
+            let path = directory.join(file);
+            match std::fs::read(&path) {
+                Ok(data) => {
+                    if !content.is_empty() {
+                        content.extend_from_slice(separator);
+                    }
+                    content.extend(data);
+                    modified = modified.max(modified_secs(std::fs::metadata(&path)));
+                },
+                Err(e) => errors.push(format!("{name}: cannot bundle {file:#?}: {e}")),
+            }
+        }
+        sources.push((name.clone(), content, modified));
+    }
+
+    let metadata = std::cell::OnceCell::new();
+    let mut lookup = |var: &str| {
+        if let Ok(value) = std::env::var(var) {
+            println!("cargo:rerun-if-env-changed={var}");
+            return Some(value);
+        }
+        let metadata = metadata.get_or_init(|| package_metadata("Cargo.toml"));
+        match metadata.as_ref()?.get(var)? {
+            serde_json::Value::String(s) => Some(s.clone()),
+            value => Some(value.to_string()),
+        }
+    };
 
-    lines.push("const RESOURCES: &[(&str, &str)] = &[".to_string());
-    for pair in names {
-        lines.push(format!("({:#?}, {}),", pair.0, pair.1));
+    let mut lines = Vec::new();
+    let mut names = Vec::new();
+    let mut pages = Vec::new();
+    for (name, content_raw, modified) in sources {
+        let content_raw = match process(directory, &name, content_raw, options, &mut lookup) {
+            Ok(content) => content,
+            Err(e) => {
+                errors.extend(e);
+                continue;
+            }
+        };
+        if options.element_ids && matches!(get_extension_from_filename(&name), Some("html" | "htm")) {
+            pages.push((name.clone(), html_ids(&String::from_utf8_lossy(&content_raw))));
+        }
+        let content = if options.minify {
+            minify(&name, &content_raw)
+        } else  {
+            content_raw
+        };
+        let etag = content_hash(&content);
+        let compressed = match options.compression {
+            Compression::Gzip => Some((gzip(&content), "gzip")),
+            Compression::Brotli => Some((brotli(&content), "br")),
+            Compression::None => None,
+        };
+        let (content, encoding) = match compressed {
+            Some((compressed, encoding)) if compressed.len() < content.len() => (compressed, Some(encoding)),
+            _ => (content, None),
+        };
+        let packed_path = pack_dir.join(&name);
+        std::fs::write(&packed_path, content).unwrap();
+        let rust_name = rust_identifier(&name).to_ascii_uppercase();
+
+        let line = format!("const {}: &[u8] = include_bytes!({:?});", &rust_name, packed_path.to_str().unwrap());
+        lines.push(line);
+        names.push((name.to_owned(), rust_name, encoding, etag, modified));
+    }
+
+    if !errors.is_empty() {
+        panic!("Resource packing failed:\n{}", errors.join("\n"));
+    }
+
+    lines.push("const RESOURCES: &[gemgui::respack::PackedResource] = &[".to_string());
+    for (name, rust_name, encoding, etag, modified) in names {
+        lines.push(format!("gemgui::respack::PackedResource::new({name:?}, {rust_name}, {encoding:?}, {etag:?}, {modified:?}),"));
     }
     lines.push("];".to_string());
+    if options.element_ids {
+        pages.sort();
+        let (id_lines, warnings) = element_id_lines(&pages);
+        lines.extend(id_lines);
+        for warning in warnings {
+            println!("cargo:warning={warning}");
+        }
+    }
     println!("{} --> {:?}", lines.len(), lines);
     std::fs::write(dest_path, lines.join("\n")).unwrap();
 }
+
+
+#[cfg(test)]
+mod tests {
+
+    use crate::Resource;
+
+    use super::*;
+
+    #[test]
+    fn test_packed_resource() {
+        let content = b"<html>Lorem ipsum, Lorem ipsum, Lorem ipsum, Lorem ipsum</html>".repeat(10);
+        assert_eq!(content_hash(b""), "cbf29ce484222325");
+        assert_ne!(content_hash(&content), content_hash(&content[1..]));
+
+        let compressed: &'static [u8] = Box::leak(gzip(&content).into_boxed_slice());
+        assert!(compressed.len() < content.len());
+        let etag: &'static str = Box::leak(content_hash(&content).into_boxed_str());
+        let packed = PackedResource::new("test.html", compressed, Some("gzip"), etag, Some(1_700_000_000));
+        let resource = Resource::from(&packed);
+        assert_eq!(resource.encoded(), Some(("gzip", compressed)));
+        assert_eq!(resource.content().unwrap(), &content[..]);
+        assert_eq!(resource.etag(), Resource::new(content.clone()).etag());
+
+        let compressed: &'static [u8] = Box::leak(brotli(&content).into_boxed_slice());
+        assert!(compressed.len() < content.len());
+        let resource = Resource::from(&PackedResource::new("test.html", compressed, Some("br"), etag, None));
+        assert_eq!(resource.encoded(), Some(("br", compressed)));
+        assert_eq!(resource.content().unwrap(), &content[..]);
+
+        // corrupted data is an error, stored data can still be served as is
+        let resource = Resource::from(&PackedResource::new("test.html", b"garbage", Some("gzip"), etag, None));
+        assert!(resource.content().is_err());
+        assert_eq!(resource.encoded(), Some(("gzip", &b"garbage"[..])));
+        assert_eq!(Resource::new(content).encoded(), None);
+    }
+
+    #[test]
+    fn test_pipeline() {
+        let dir = std::env::temp_dir().join(format!("respack_test_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("parts")).unwrap();
+        std::fs::write(dir.join("parts/head.html"), "<title>{{TITLE}}</title><!-- include parts/style.html -->").unwrap();
+        std::fs::write(dir.join("parts/style.html"), "<style>div {background: url('dot.png')}</style>").unwrap();
+        std::fs::write(dir.join("parts/loop.html"), "<!-- include parts/loop.html -->").unwrap();
+        std::fs::write(dir.join("dot.png"), b"\x89PNG").unwrap();
+        std::fs::write(dir.join("a.js"), "let a = 1").unwrap();
+        std::fs::write(dir.join("b.js"), "let b = 2").unwrap();
+
+        let html = "<html><!-- include parts/head.html --><img src=\"dot.png\"><img src='http://x/y.png'></html>";
+        let included = resolve_includes(&dir, html, &mut vec!()).unwrap();
+        assert!(included.starts_with("<html><title>{{TITLE}}</title><style>"));
+        assert!(resolve_includes(&dir, "<!-- include nosuch.html -->", &mut vec!()).unwrap_err().contains("nosuch.html"));
+        assert!(resolve_includes(&dir, "<!-- include parts/loop.html -->", &mut vec!()).unwrap_err().contains("recursive"));
+
+        let lookup = |var: &str| (var == "TITLE").then(|| "Hello".to_string());
+        assert_eq!(substitute("<title>{{ TITLE }}</title>", lookup).unwrap(), "<title>Hello</title>");
+        assert_eq!(substitute("{{TITLE}} {{FOO}} {{BAR}}", lookup).unwrap_err(), "undefined variables {{FOO}}, {{BAR}}");
+
+        let inlined = inline_images(&dir, &included, 100).unwrap();
+        assert_eq!(inlined.matches("data:image/png;base64,iVBORw==").count(), 2);
+        assert!(inlined.contains("'http://x/y.png'"));
+        assert_eq!(inline_images(&dir, &included, 2).unwrap(), included);
+        assert!(inline_images(&dir, "<img src=\"nosuch.png\">", 100).unwrap_err().contains("nosuch.png"));
+
+        let options = PackOptions::new().includes(true).variables(true).inline_images(100);
+        let mut lookup = lookup;
+        let processed = process(&dir, "index.html", html.as_bytes().to_vec(), &options, &mut lookup).unwrap();
+        assert!(String::from_utf8(processed).unwrap().starts_with("<html><title>Hello</title><style>div {background: url('data:image/png;"));
+        let errors = process(&dir, "index.html", b"{{FOO}}<!-- include nosuch.html -->".to_vec(), &options, &mut lookup).unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(process(&dir, "dot.png", b"{{FOO}}".to_vec(), &options, &mut lookup).unwrap(), b"{{FOO}}");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_element_ids() {
+        let html = r#"<div id="content" class=x><span  id='my-title'></span><p data-id="no" id=plain></div><a id="content">"#;
+        assert_eq!(html_ids(html), ["content", "my-title", "plain", "content"]);
+
+        let pages = [
+            ("a.html".to_string(), html_ids(html)),
+            ("b.html".to_string(), vec!("plain".to_string(), "my_title".to_string())),
+        ];
+        let (lines, warnings) = element_id_lines(&pages);
+        assert_eq!(warnings, [
+            "id \"content\" is duplicated in a.html",
+            "id \"plain\" is in several pages: a.html, b.html",
+            "ids \"my-title\" and \"my_title\" are both MY_TITLE, only the first is generated"]);
+        assert!(lines.contains(&"    pub const CONTENT: &str = \"content\";".to_string()));
+        assert!(lines.contains(&"    pub const MY_TITLE: &str = \"my-title\";".to_string()));
+        assert!(lines.contains(&"    /// In a.html, b.html".to_string()));
+        assert!(lines.contains(&"    (\"my_title\") => {\"my_title\"};".to_string()));
+    }
+}
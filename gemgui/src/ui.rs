@@ -1,4 +1,6 @@
 pub(crate) mod server;
+mod http;
+mod mime;
 mod utils;
 
 use crate::Menu;
@@ -6,6 +8,8 @@ use crate::Result;
 
 use core::fmt;
 use std::sync::Arc;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
 use std::sync::Mutex;
 
 use std::process::Command;
@@ -25,16 +29,21 @@ pub type SubscribeCallback = dyn FnMut(UiRef, Event) + Send + 'static;
 /// Time callback type.
 pub type TimerCallback = dyn FnMut(UiRef, TimerId) + Send + 'static;
 
+/// Animation frame callback type.
+pub type AnimationFrameCallback = dyn FnMut(UiRef, Duration) + Send + 'static;
+
 //pub type AsyncTimerCallback = dyn FnMut(UiRef, TimerId) ->
 // (dyn std::future::Future<Output = ()> + Send + 'static) + Send + 'static;
 
 use crate::Filemap;
+use crate::Resource;
 use crate::GemGuiError;
 use crate::JSMessageRx;
 
 use crate::default_error;
 use crate::event::Event;
 use crate::event::Properties;
+use crate::event::REMOVED;
 use crate::element::Element;
 
 use crate::ui_data::UiData;
@@ -53,6 +62,7 @@ include!(concat!(env!("OUT_DIR"), "/generated.rs"));
 pub (crate) static BATCH_BEGIN: &str = "batch_begin";
 pub (crate) static BATCH_END: &str = "batch_end";  
 pub (crate) static CLOSE_REQUEST: &str = "close_request";
+static BINARY_QUERY: u32 = 0xBBB;
 
 
 // ##known issues:## 
@@ -120,9 +130,15 @@ impl Target {
 }
 
 pub (crate) mod private {
+    use std::sync::Arc;
+    use std::sync::atomic::AtomicUsize;
     use crate::ui_data::UiDataRef;
     pub trait UserInterface {    
         fn ui(&self) -> &UiDataRef;
+        // batches open in this call chain, if tracked
+        fn batch_depth(&self) -> Option<&Arc<AtomicUsize>> {
+            None
+        }
     }
 }
 
@@ -147,16 +163,49 @@ pub trait Ui : private::UserInterface {
     /// Start a batch
     /// Batches let collect multiple commands as singe request. That may speed up their processing and 
     /// avoid UI flickering on complex UI modification sequences.
+    /// 
+    /// Batches can be nested, each `batch_begin` has to be paired with a `batch_end`. See [batch](Self::batch)
+    /// for a scoped alternative.
     fn batch_begin(&self) {
+        if let Some(depth) = self.batch_depth() {
+            depth.fetch_add(1, Ordering::SeqCst);
+        }
         UiData::batch_begin(self.ui())
     }
 
     /// End a batch
-    /// The collected batch is immediately sent to UI.
+    /// The collected batch is immediately sent to UI when the outermost batch ends.
     fn batch_end(&self) {
+        if let Some(depth) = self.batch_depth() {
+            end_depth(depth);
+        }
         UiData::batch_end(self.ui())
     }
 
+    /// Start a scoped batch
+    /// 
+    /// The batch ends when the returned guard is dropped, also on early return. 
+    /// 
+    /// # Example
+    /// 
+    /// ```
+    /// # use gemgui::ui_ref::UiRef;
+    /// # use gemgui::ui::Ui;
+    /// fn some_function(ui: UiRef) {
+    ///     let _batch = ui.batch();
+    ///     ui.element("foo").set_html("Foo");
+    ///     ui.element("bar").set_html("Bar");
+    /// } // batch is sent here
+    /// ```
+    /// 
+    /// # Return
+    /// 
+    /// Batch guard
+    fn batch(&self) -> Batch {
+        self.batch_begin();
+        Batch{ui: self.ui().clone(), depth: self.batch_depth().cloned()}
+    }
+
     /// Set logging
     /// 
     /// # Arguments
@@ -203,6 +252,10 @@ pub trait Ui : private::UserInterface {
     /// # Arguments
     /// 
     /// ´resource_name´ - name of the resource
+    /// 
+    /// # Return
+    /// 
+    /// Resource content, None if not found or if it cannot be decompressed
     fn resource(&self, resource_name: &str) -> Option<Box<[u8]>> {
         UiData::resource(self.ui(), resource_name)
     }
@@ -311,15 +364,62 @@ pub trait Ui : private::UserInterface {
         UiData::periodic_async(self.ui(), period, async_func)
     }
 
+    /// Set callback called on each UI animation frame
+    /// 
+    /// Frames are synchronized to UI refresh rate using `requestAnimationFrame`. A new frame is
+    /// not requested until the callback of the previous one has returned, hence a slow callback
+    /// lowers the frame rate instead of piling up requests. Only one callback is active, calling 
+    /// this again replaces the previous one.
+    /// 
+    /// # Arguments
+    /// 
+    /// `callback` - Callback function called on each frame
+    /// 
+    /// # Callback
+    /// 
+    /// `UiRef`- Reference to UI
+    /// 
+    /// `Duration` - frame timestamp, time since UI page was loaded
+    fn on_animation_frame<CB>(&self, callback: CB)
+    where CB: FnMut(UiRef, Duration) + Send + 'static {
+        UiData::set_animation_frame(self.ui(), Some(Box::new(callback)))
+    }
+
+    /// Stop animation frame callbacks, see [on_animation_frame](Self::on_animation_frame)
+    fn cancel_animation_frame(&self) {
+        UiData::set_animation_frame(self.ui(), None)
+    }
+
 }
 
 
-/// Ui instance
+/// Batch guard, see [Ui::batch]
+#[must_use = "batch ends when the guard is dropped"]
+pub struct Batch {
+    ui: UiDataRef,
+    depth: Option<Arc<AtomicUsize>>,
+}
+
+impl Drop for Batch {
+    fn drop(&mut self) {
+        if let Some(depth) = &self.depth {
+            end_depth(depth);
+        }
+        UiData::batch_end(&self.ui);
+    }
+}
+
+fn end_depth(depth: &AtomicUsize) {
+    let _ = depth.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |d| d.checked_sub(1));
+}
+
 
+/// Ui instance
 pub struct Gui  {
     ui: UiDataRef,
     index_html : String,
     subscription_receiver: ChannelReceiver<String>,
+    binary_receiver: ChannelReceiver<Vec<u8>>,
     timer_receiver:  ChannelReceiver<TimerId>,
     start_cmd: Option<(String, Vec<String>)>,
     server: server::WSServer,
@@ -334,7 +434,7 @@ impl fmt::Debug for Gui {
 
         let ui = self.ui.lock().unwrap();
         let empty: (String, Vec<String>) = ("".to_string(), vec!());
-        let (cmd, params) = if self.start_cmd.is_some() {self.start_cmd.as_ref().unwrap()} else {&empty};
+        let (cmd, params) = if let Some(start_cmd) = &self.start_cmd {start_cmd} else {&empty};
         f.debug_struct("Gui")
          .field("ui", &ui)
          .field("server", &self.server)
@@ -358,19 +458,17 @@ impl Ui for Gui { }
 
 impl Gui {
     /// Create a UI
-
     pub fn new(user_map : Filemap, index_html: &str, port: u16) -> Result<Self> {
         if ! port_scanner::local_port_available(port) {
             return GemGuiError::error(format!("Port {port} is not available"));
         }
         let mut filemap = user_map;
         for resource in RESOURCES {
-            let res = base64::decode(resource.1).unwrap();
-            let key =  resource.0.to_string();
+            let key =  resource.name().to_string();
             if filemap.contains_key(&key) {
                 eprintln!("Warning: {:#?} already in resources", &key);
             }
-            filemap.insert(key, res);
+            filemap.insert(key, resource.into());
         }
         
         if ! filemap.contains_key(index_html) {
@@ -380,8 +478,9 @@ impl Gui {
         let filemap = Arc::new(Mutex::new(filemap));
   
         let (subscription_sender, subscription_receiver) = mpsc::channel(32);
+        let (binary_sender, binary_receiver) = mpsc::channel(32);
         let (timer_sender, timer_receiver) = mpsc::channel(32);
-        let server = server::new(filemap.clone(), port, subscription_sender.clone());
+        let server = server::new(filemap.clone(), port, subscription_sender.clone(), binary_sender);
 
         let(start_notifee, start_notify) = watch::channel(State::Init);
 
@@ -399,6 +498,7 @@ impl Gui {
             ui: Arc::new(Mutex::new(ui)),
             index_html: index_html.to_string(),
             subscription_receiver,
+            binary_receiver,
             timer_receiver,
             start_cmd,
             server,
@@ -466,6 +566,37 @@ impl Gui {
     }
 
 
+    /// Set Cache-Control of served files
+    /// 
+    /// By default files are served with `no-cache`, i.e. browser revalidates them
+    /// using ETag and does not re-download unless they are changed. If several patterns
+    /// match, the latest set applies.
+    /// 
+    /// # Arguments
+    /// 
+    /// `pattern` - file name pattern, `*` matches any characters and `?` a single one, e.g. `"*.png"`
+    /// 
+    /// `policy` - Cache-Control value, e.g. `"max-age=3600"`
+    pub fn set_cache_control(&mut self, pattern: &str, policy: &str) -> Result<()> {
+        self.server.set_cache_control(pattern, policy)
+    }
+
+
+    /// Set MIME type of served files
+    /// 
+    /// Overrides the built-in type of an extension. Files without a known
+    /// extension get their type from their content.
+    /// 
+    /// # Arguments
+    /// 
+    /// `extension` - file extension, e.g. `"glsl"`
+    /// 
+    /// `mime` - MIME type, e.g. `"text/plain;charset=utf-8"`
+    pub fn set_mime_type(&mut self, extension: &str, mime: &str) -> Result<()> {
+        self.server.set_mime_type(extension, mime)
+    }
+
+
     /// Set python (pywebview) UI
     /// 
     /// # Arguments
@@ -495,9 +626,11 @@ impl Gui {
                 }
             //}
 
-            let py_src = RESOURCES.iter().find(|r| r.0 == "pyclient.py").unwrap().1;
-            let py_src = base64::decode(py_src).unwrap();
-            let py_src = String::from_utf8_lossy(&py_src);
+            let py_src = Resource::from(RESOURCES.iter().find(|r| r.name() == "pyclient.py").unwrap());
+            let Ok(py_src) = py_src.content() else {
+                return false;
+            };
+            let py_src = String::from_utf8_lossy(py_src);
 
             let mut params = vec!(
                 "-c".to_string(),
@@ -510,8 +643,8 @@ impl Gui {
                 format!("--gempyre-flags={flags}"));
 
             let menu = menu.into();
-            if menu.is_some() {
-                params.push(format!("--gempyre-menu={}", menu.unwrap().to_string()));
+            if let Some(menu) = menu {
+                params.push(format!("--gempyre-menu={}", menu.to_string()));
             }    
 
             let path = py.unwrap().to_str().unwrap().to_string();
@@ -590,6 +723,9 @@ impl Gui {
                                     self.exit(); // send exit to all windows - then go
                                     break; },  
                                 "event" => self.event_handler(m),
+                                "removed" => self.removed_handler(&msg),
+                                "create_failed" => self.create_failed_handler(&msg),
+                                "animation_frame" => self.animation_frame_handler(&msg),
                                 "query" => self.query_handler(&msg),
                                 "error" => self.error_handler(&msg),
                                 "extension_response" => self.extension_response_handler(&msg),
@@ -602,6 +738,10 @@ impl Gui {
                         }
                     }
                 },
+                // binary data from UI
+                Some(data) = self.binary_receiver.recv() => {
+                    self.binary_handler(data)
+                },
                 // wait timer 
                 Some(timer_msg) = self.timer_receiver.recv() => {
                     self.timer_handler(timer_msg)
@@ -613,9 +753,8 @@ impl Gui {
     }
 
     fn error_handler(&mut self, msg: &str) {
-          match &mut self.on_error_cb {
-            Some(f) => f(UiRef::new(self.ui.clone()), msg.to_string()),
-            None => (),
+        if let Some(f) = &mut self.on_error_cb {
+            f(UiRef::new(self.ui.clone()), msg.to_string());
         }
         eprintln!("Ui Error {msg:#?}")
     }
@@ -653,6 +792,45 @@ impl Gui {
         fun(UiRef::new(self.ui.clone()), Event::new(self.ui.clone(), element, prop));
     }
 
+    // UI has removed elements that had subscriptions, drop their callbacks
+    fn removed_handler(&mut self, raw: &str) {
+        let mut js: serde_json::Value = serde_json::from_str(raw).unwrap();
+        let removed = match crate::value_to_string_list(js["elements"].take()) {
+            Some(removed) => removed,
+            None => return,
+        };
+        for id in removed {
+            let Some(subscriptions) = UiData::remove_element(&self.ui, &id) else {
+                continue;
+            };
+            if let Some(rc) = subscriptions.get(REMOVED) {
+                let mut fun = rc.lock().unwrap();
+                fun(UiRef::new(self.ui.clone()), Event::new(self.ui.clone(), id.clone(), Properties::new()));
+            }
+        }
+    }
+
+    // UI could not create a built element tree, drop subscriptions set for it
+    fn create_failed_handler(&mut self, raw: &str) {
+        let mut js: serde_json::Value = serde_json::from_str(raw).unwrap();
+        if let Some(ids) = crate::value_to_string_list(js["elements"].take()) {
+            for id in ids {
+                UiData::drop_subscriptions(&self.ui, &id);
+            }
+        }
+    }
+
+    // a requested frame, next one is requested only after callback is done
+    fn animation_frame_handler(&mut self, raw: &str) {
+        let js: serde_json::Value = serde_json::from_str(raw).unwrap();
+        let timestamp = js["timestamp"].as_f64().unwrap_or(0.0);
+        if let Some(rc) = UiData::take_animation_frame(&self.ui) {
+            let mut fun = rc.lock().unwrap();
+            fun(UiRef::new(self.ui.clone()), Duration::from_secs_f64(timestamp.max(0.0) / 1000.0));
+        }
+        UiData::request_animation_frame(&self.ui);
+    }
+
     fn query_handler(&mut self, raw: &str) {
         let mut js: serde_json::Value = serde_json::from_str(raw).unwrap();
         let query_value = String::from(js["query_value"].as_str().unwrap()); // otherwise we cannot take later as mutable
@@ -664,7 +842,34 @@ impl Gui {
                 r.send(value).unwrap_or_else(|e| {panic!("Cannot send query: {e}")});
             },
             None =>  {
-                eprintln!("No query listener for {query_id}");
+                // a failed binary query is responded as a normal query, dropping sender cancels it
+                if UiData::get_binary_query_sender(&mut self.ui, query_id).is_none() {
+                    eprintln!("No query listener for {query_id}");
+                }
+            }
+        };
+    }
+
+    // header is type, query id length in bytes and query id padded to words, rest is the value
+    fn binary_handler(&mut self, data: Vec<u8>) {
+        let word = |pos: usize| data.get(pos..pos + 4).map(|w| u32::from_le_bytes(w.try_into().unwrap()));
+        if word(0) != Some(BINARY_QUERY) {
+            eprintln!("Unexpected binary message");
+            return;
+        }
+        let id_len = word(4).unwrap_or(0) as usize;
+        let value_pos = 8 + id_len.div_ceil(4) * 4;
+        if data.len() < value_pos {
+            eprintln!("Invalid binary message");
+            return;
+        }
+        let query_id = String::from_utf8_lossy(&data[8..8 + id_len]).to_string();
+        match UiData::get_binary_query_sender(&mut self.ui, &query_id) {
+            Some(r) => {
+                r.send(data[value_pos..].to_vec()).unwrap_or_else(|_| {panic!("Cannot send binary query")});
+            },
+            None =>  {
+                eprintln!("No binary query listener for {query_id}");
             }
         };
     }
@@ -690,10 +895,9 @@ impl Gui {
     fn start_handler(&mut self) {
         if ! UiData::is_started(&self.ui) {
             UiData::set_started(&self.ui);
-            match &mut self.on_start_cb {
-                Some(cb) => cb(UiRef::new(self.ui.clone())),
-                None => (),
-            };
+            if let Some(cb) = &mut self.on_start_cb {
+                cb(UiRef::new(self.ui.clone()));
+            }
             self.on_start_notifee.send(State::Running).unwrap_or_else(|_| panic!("Cannot set ready"));
         }
     }
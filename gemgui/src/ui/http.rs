@@ -0,0 +1,262 @@
+use std::ops::Range;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use warp::http::{HeaderMap, Response, StatusCode};
+use warp::http::header::{
+    ACCEPT_ENCODING,
+    ACCEPT_RANGES,
+    CACHE_CONTROL,
+    CONTENT_ENCODING,
+    CONTENT_RANGE,
+    CONTENT_TYPE,
+    ETAG,
+    IF_MODIFIED_SINCE,
+    IF_NONE_MATCH,
+    IF_RANGE,
+    LAST_MODIFIED,
+    RANGE,
+    VARY};
+
+use crate::Resource;
+
+// Cache-Control if no rule is matching, browser revalidates with ETag
+pub (crate) static DEFAULT_CACHE_CONTROL: &str = "no-cache";
+
+#[derive(Debug, PartialEq)]
+enum ByteRange {
+    Full,
+    Partial(Range<usize>),
+    Unsatisfiable,
+}
+
+/// Response for a GET request of a resource
+///
+/// Encoded data is sent if client accepts it, conditional requests are responded
+/// with 304 and byte ranges with 206.
+pub (crate) fn respond(resource: &Resource, mime: &str, cache_control: &str, headers: &HeaderMap) -> Response<Vec<u8>> {
+    let header = |name| headers.get(name).and_then(|v: &warp::http::HeaderValue| v.to_str().ok());
+    let range = header(RANGE);
+
+    // ranges are served from the identity content
+    let (data, encoding, range) = match resource.encoded() {
+        Some((encoding, data)) if range.is_none() && accepts_encoding(header(ACCEPT_ENCODING), encoding) =>
+            (data, Some(encoding), range),
+        Some((encoding, data)) => match resource.content() {
+            Ok(content) => (content, None, range),
+            // stored data is all there is
+            Err(e) => {
+                eprintln!("{e}");
+                (data, Some(encoding), None)
+            },
+        },
+        None => (resource.content().unwrap_or_default(), None, range),
+    };
+
+    let etag = match encoding {
+        Some(encoding) => format!("\"{}-{encoding}\"", resource.etag()),
+        None => format!("\"{}\"", resource.etag()),
+    };
+    let modified = resource.modified().map(http_seconds);
+
+    let mut response = Response::builder()
+        .header(ETAG, &etag)
+        .header(CACHE_CONTROL, cache_control)
+        .header(ACCEPT_RANGES, "bytes");
+    if let Some(modified) = modified {
+        response = response.header(LAST_MODIFIED, httpdate::fmt_http_date(UNIX_EPOCH + std::time::Duration::from_secs(modified)));
+    }
+    if resource.encoded().is_some() {
+        response = response.header(VARY, "accept-encoding");
+    }
+
+    let not_modified = match header(IF_NONE_MATCH) {
+        Some(if_none_match) => etag_matches(if_none_match, &etag),
+        None => match (header(IF_MODIFIED_SINCE).and_then(|d| httpdate::parse_http_date(d).ok()), modified) {
+            (Some(since), Some(modified)) => modified <= http_seconds(since),
+            _ => false,
+        }
+    };
+    if not_modified {
+        return response.status(StatusCode::NOT_MODIFIED).body(Vec::new()).unwrap();
+    }
+
+    let response = response.header(CONTENT_TYPE, mime);
+
+    // If-Range with an outdated validator asks a full content
+    let range = match header(IF_RANGE) {
+        Some(if_range) if !(if_range == etag || modified.is_some() && httpdate::parse_http_date(if_range).ok().map(http_seconds) == modified) => None,
+        _ => range,
+    };
+
+    match byte_range(range, data.len()) {
+        ByteRange::Full => {
+            let response = match encoding {
+                Some(encoding) => response.header(CONTENT_ENCODING, encoding),
+                None => response,
+            };
+            response.body(data.to_vec())
+        },
+        ByteRange::Partial(range) =>
+            response
+            .status(StatusCode::PARTIAL_CONTENT)
+            .header(CONTENT_RANGE, format!("bytes {}-{}/{}", range.start, range.end - 1, data.len()))
+            .body(data[range].to_vec()),
+        ByteRange::Unsatisfiable =>
+            response
+            .status(StatusCode::RANGE_NOT_SATISFIABLE)
+            .header(CONTENT_RANGE, format!("bytes */{}", data.len()))
+            .body(Vec::new()),
+    }.unwrap()
+}
+
+// HTTP dates have a second resolution
+fn http_seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+// Weak comparison of If-None-Match list
+fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    let etag = opaque(etag);
+    if_none_match.split(',').any(|tag| tag.trim() == "*" || opaque(tag) == etag)
+}
+
+// Single range only, others are served as a full content as allowed by RFC 9110
+fn byte_range(range: Option<&str>, len: usize) -> ByteRange {
+    let Some(spec) = range.and_then(|r| r.trim().strip_prefix("bytes=")) else {
+        return ByteRange::Full;
+    };
+    if spec.contains(',') {
+        return ByteRange::Full;
+    }
+    let Some((start, end)) = spec.split_once('-') else {
+        return ByteRange::Full;
+    };
+    let (start, end) = (start.trim(), end.trim());
+    let range = if start.is_empty() {
+        match end.parse::<usize>() {
+            Ok(0) => return ByteRange::Unsatisfiable,
+            Ok(suffix) => len.saturating_sub(suffix)..len,
+            Err(_) => return ByteRange::Full,
+        }
+    } else {
+        let Ok(start) = start.parse::<usize>() else {
+            return ByteRange::Full;
+        };
+        let end = match end.parse::<usize>() {
+            _ if end.is_empty() => len,
+            Ok(end) if end >= start => (end + 1).min(len),
+            _ => return ByteRange::Full,
+        };
+        start..end
+    };
+    if range.start >= len {
+        ByteRange::Unsatisfiable
+    } else {
+        ByteRange::Partial(range)
+    }
+}
+
+// Is encoding listed in Accept-Encoding header and not refused with q=0
+fn accepts_encoding(accept_encoding: Option<&str>, encoding: &str) -> bool {
+    let Some(accept_encoding) = accept_encoding else {
+        return false;
+    };
+    accept_encoding.split(',').any(|item| {
+        let mut parts = item.split(';');
+        let name = parts.next().unwrap_or_default().trim();
+        let refused = parts.any(|p| p.trim().strip_prefix("q=")
+            .and_then(|q| q.trim().parse::<f32>().ok()) == Some(0.));
+        (name.eq_ignore_ascii_case(encoding) || name == "*") && !refused
+    })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::time::Duration;
+
+    use warp::http::HeaderValue;
+
+    use super::*;
+
+    fn request(headers: &[(warp::http::header::HeaderName, &str)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (name, value) in headers {
+            map.insert(name, HeaderValue::from_str(value).unwrap());
+        }
+        map
+    }
+
+    #[test]
+    fn test_accepts_encoding() {
+        assert!(accepts_encoding(Some("gzip, deflate, br"), "gzip"));
+        assert!(accepts_encoding(Some("br;q=1.0, GZIP;q=0.5"), "gzip"));
+        assert!(accepts_encoding(Some("*"), "gzip"));
+        assert!(!accepts_encoding(Some("gzip;q=0"), "gzip"));
+        assert!(!accepts_encoding(Some("deflate, br"), "gzip"));
+        assert!(!accepts_encoding(None, "gzip"));
+        assert!(accepts_encoding(Some("gzip, deflate, br"), "br"));
+        assert!(!accepts_encoding(Some("gzip, br;q=0"), "br"));
+    }
+
+    #[test]
+    fn test_byte_range() {
+        assert_eq!(byte_range(None, 100), ByteRange::Full);
+        assert_eq!(byte_range(Some("bytes=0-9"), 100), ByteRange::Partial(0..10));
+        assert_eq!(byte_range(Some("bytes=90-"), 100), ByteRange::Partial(90..100));
+        assert_eq!(byte_range(Some("bytes=90-200"), 100), ByteRange::Partial(90..100));
+        assert_eq!(byte_range(Some("bytes=-10"), 100), ByteRange::Partial(90..100));
+        assert_eq!(byte_range(Some("bytes=-200"), 100), ByteRange::Partial(0..100));
+        assert_eq!(byte_range(Some("bytes=100-"), 100), ByteRange::Unsatisfiable);
+        assert_eq!(byte_range(Some("bytes=-0"), 100), ByteRange::Unsatisfiable);
+        assert_eq!(byte_range(Some("bytes=0-1,5-6"), 100), ByteRange::Full);
+        assert_eq!(byte_range(Some("bytes=9-0"), 100), ByteRange::Full);
+        assert_eq!(byte_range(Some("items=0-9"), 100), ByteRange::Full);
+    }
+
+    #[test]
+    fn test_respond() {
+        let content: Vec<u8> = (0..100).collect();
+        let modified = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let resource = Resource::new(content.clone()).with_modified(modified);
+        let etag = format!("\"{}\"", resource.etag());
+
+        let response = respond(&resource, "video/mp4", "no-cache", &request(&[]));
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.body(), &content);
+        assert_eq!(response.headers()[ETAG], etag.as_str());
+        assert_eq!(response.headers()[CACHE_CONTROL], "no-cache");
+        assert_eq!(response.headers()[LAST_MODIFIED], "Tue, 14 Nov 2023 22:13:20 GMT");
+
+        let response = respond(&resource, "video/mp4", "no-cache", &request(&[(IF_NONE_MATCH, &format!("W/\"x\", {etag}"))]));
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert!(response.body().is_empty());
+
+        let response = respond(&resource, "video/mp4", "no-cache", &request(&[(IF_NONE_MATCH, "\"x\"")]));
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = respond(&resource, "video/mp4", "no-cache", &request(&[(IF_MODIFIED_SINCE, "Tue, 14 Nov 2023 22:13:20 GMT")]));
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+        let response = respond(&resource, "video/mp4", "no-cache", &request(&[(RANGE, "bytes=10-19")]));
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(response.headers()[CONTENT_RANGE], "bytes 10-19/100");
+        assert_eq!(response.body(), &content[10..20]);
+
+        let response = respond(&resource, "video/mp4", "no-cache", &request(&[(RANGE, "bytes=10-19"), (IF_RANGE, "\"x\"")]));
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = respond(&resource, "video/mp4", "no-cache", &request(&[(RANGE, "bytes=200-")]));
+        assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(response.headers()[CONTENT_RANGE], "bytes */100");
+
+        // undecodable data is served as stored
+        let packed = crate::respack::PackedResource::new("x.js", b"garbage", Some("br"), "0", None);
+        let resource = Resource::from(&packed);
+        let response = respond(&resource, "text/javascript", "no-cache", &request(&[(RANGE, "bytes=0-1")]));
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[CONTENT_ENCODING], "br");
+        assert_eq!(response.body(), b"garbage");
+    }
+}
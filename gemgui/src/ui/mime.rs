@@ -0,0 +1,216 @@
+// MIME type if nothing else is known
+pub (crate) static DEFAULT_MIME: &str = "application/octet-stream";
+
+static MAP: phf::Map<&'static str, &'static str> = phf::phf_map! {
+    // text
+    "html" => "text/html;charset=utf-8",
+    "htm" => "text/html;charset=utf-8",
+    "xhtml" => "application/xhtml+xml;charset=utf-8",
+    "css" => "text/css;charset=utf-8",
+    "js" => "text/javascript;charset=utf-8",
+    "mjs" => "text/javascript;charset=utf-8",
+    "cjs" => "text/javascript;charset=utf-8",
+    "txt" => "text/plain;charset=utf-8",
+    "text" => "text/plain;charset=utf-8",
+    "log" => "text/plain;charset=utf-8",
+    "md" => "text/markdown;charset=utf-8",
+    "csv" => "text/csv;charset=utf-8",
+    "tsv" => "text/tab-separated-values;charset=utf-8",
+    "xml" => "application/xml;charset=utf-8",
+    "xsl" => "application/xml;charset=utf-8",
+    "json" => "application/json",
+    "jsonld" => "application/ld+json",
+    "map" => "application/json",
+    "webmanifest" => "application/manifest+json",
+    "ics" => "text/calendar;charset=utf-8",
+    "vtt" => "text/vtt;charset=utf-8",
+    "srt" => "application/x-subrip",
+    "yaml" => "application/yaml",
+    "yml" => "application/yaml",
+    "toml" => "application/toml",
+    "py" => "text/x-python;charset=utf-8",
+    "rs" => "text/x-rust;charset=utf-8",
+    // images
+    "ico" => "image/x-icon",
+    "cur" => "image/x-icon",
+    "png" => "image/png",
+    "apng" => "image/apng",
+    "jpg" => "image/jpeg",
+    "jpeg" => "image/jpeg",
+    "jfif" => "image/jpeg",
+    "pjpeg" => "image/jpeg",
+    "gif" => "image/gif",
+    "svg" => "image/svg+xml",
+    "svgz" => "image/svg+xml",
+    "webp" => "image/webp",
+    "avif" => "image/avif",
+    "bmp" => "image/bmp",
+    "tif" => "image/tiff",
+    "tiff" => "image/tiff",
+    "heic" => "image/heic",
+    "heif" => "image/heif",
+    "jxl" => "image/jxl",
+    "qoi" => "image/qoi",
+    // fonts
+    "woff" => "font/woff",
+    "woff2" => "font/woff2",
+    "ttf" => "font/ttf",
+    "otf" => "font/otf",
+    "ttc" => "font/collection",
+    "eot" => "application/vnd.ms-fontobject",
+    // audio
+    "mp3" => "audio/mpeg",
+    "wav" => "audio/wav",
+    "oga" => "audio/ogg",
+    "ogg" => "audio/ogg",
+    "opus" => "audio/opus",
+    "flac" => "audio/flac",
+    "aac" => "audio/aac",
+    "m4a" => "audio/mp4",
+    "weba" => "audio/webm",
+    "mid" => "audio/midi",
+    "midi" => "audio/midi",
+    // video
+    "mp4" => "video/mp4",
+    "m4v" => "video/mp4",
+    "webm" => "video/webm",
+    "ogv" => "video/ogg",
+    "mov" => "video/quicktime",
+    "avi" => "video/x-msvideo",
+    "mpeg" => "video/mpeg",
+    "mpg" => "video/mpeg",
+    "mkv" => "video/x-matroska",
+    "ts" => "video/mp2t",
+    "m3u8" => "application/vnd.apple.mpegurl",
+    "mpd" => "application/dash+xml",
+    // binaries and documents
+    "wasm" => "application/wasm",
+    "pdf" => "application/pdf",
+    "zip" => "application/zip",
+    "gz" => "application/gzip",
+    "tar" => "application/x-tar",
+    "7z" => "application/x-7z-compressed",
+    "bin" => "application/octet-stream",
+    "rtf" => "application/rtf",
+    "epub" => "application/epub+zip",
+    "doc" => "application/msword",
+    "docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+    "xls" => "application/vnd.ms-excel",
+    "xlsx" => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+    "ppt" => "application/vnd.ms-powerpoint",
+    "pptx" => "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+    "odt" => "application/vnd.oasis.opendocument.text",
+    "ods" => "application/vnd.oasis.opendocument.spreadsheet",
+    "glb" => "model/gltf-binary",
+    "gltf" => "model/gltf+json",
+};
+
+/// MIME type of a file extension
+pub (crate) fn extension_to_mime(extension: &str) -> Option<&'static str> {
+    MAP.get(extension.to_ascii_lowercase().as_str()).copied()
+}
+
+/// MIME type from a file content, see <https://mimesniff.spec.whatwg.org>
+pub (crate) fn sniff(data: &[u8]) -> &'static str {
+    static SIGNATURES: &[(&[u8], &str)] = &[
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"BM", "image/bmp"),
+        (b"\x00\x00\x01\x00", "image/x-icon"),
+        (b"%PDF-", "application/pdf"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"\x1f\x8b\x08", "application/gzip"),
+        (b"\x00asm", "application/wasm"),
+        (b"\x1a\x45\xdf\xa3", "video/webm"),
+        (b"OggS\x00", "audio/ogg"),
+        (b"ID3", "audio/mpeg"),
+        (b"fLaC", "audio/flac"),
+        (b"MThd\x00\x00\x00\x06", "audio/midi"),
+        (b"wOFF", "font/woff"),
+        (b"wOF2", "font/woff2"),
+        (b"\x00\x01\x00\x00", "font/ttf"),
+        (b"OTTO", "font/otf"),
+        (b"ttcf", "font/collection"),
+    ];
+    if let Some((_, mime)) = SIGNATURES.iter().find(|(signature, _)| data.starts_with(signature)) {
+        return mime;
+    }
+    if data.len() >= 12 && data.starts_with(b"RIFF") {
+        match &data[8..12] {
+            b"WEBP" => return "image/webp",
+            b"WAVE" => return "audio/wav",
+            b"AVI " => return "video/x-msvideo",
+            _ => (),
+        }
+    }
+    if data.len() >= 12 && &data[4..8] == b"ftyp" {
+        return match &data[8..12] {
+            b"avif" | b"avis" => "image/avif",
+            b"qt  " => "video/quicktime",
+            b"M4A " => "audio/mp4",
+            _ => "video/mp4",
+        };
+    }
+    if data.len() >= 2 && data[0] == 0xff && data[1] & 0xe0 == 0xe0 {
+        return "audio/mpeg"; // MPEG audio frame sync
+    }
+    sniff_text(data)
+}
+
+fn sniff_text(data: &[u8]) -> &'static str {
+    let data = data.strip_prefix(b"\xef\xbb\xbf").unwrap_or(data);
+    let head = &data[..data.len().min(1024)];
+    let text = match std::str::from_utf8(head) {
+        Ok(text) => text,
+        // a multibyte character may be cut at the end
+        Err(e) if e.error_len().is_none() => std::str::from_utf8(&head[..e.valid_up_to()]).unwrap(),
+        Err(_) => return DEFAULT_MIME,
+    };
+    if text.chars().any(|c| c.is_control() && !c.is_whitespace()) {
+        return DEFAULT_MIME;
+    }
+    let start = text.trim_start().to_ascii_lowercase();
+    if start.starts_with("<!doctype html") || start.starts_with("<html") || start.starts_with("<head") || start.starts_with("<body") {
+        "text/html;charset=utf-8"
+    } else if start.starts_with("<svg") || start.starts_with("<?xml") && start.contains("<svg") {
+        "image/svg+xml"
+    } else if start.starts_with("<?xml") {
+        "application/xml;charset=utf-8"
+    } else if (start.starts_with('{') || start.starts_with('[')) && serde_json::from_slice::<serde_json::Value>(data).is_ok() {
+        "application/json"
+    } else {
+        "text/plain;charset=utf-8"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_extension_to_mime() {
+        assert_eq!(extension_to_mime("woff2"), Some("font/woff2"));
+        assert_eq!(extension_to_mime("WASM"), Some("application/wasm"));
+        assert_eq!(extension_to_mime("mjs"), Some("text/javascript;charset=utf-8"));
+        assert_eq!(extension_to_mime("map"), Some("application/json"));
+        assert_eq!(extension_to_mime("nosuch"), None);
+    }
+
+    #[test]
+    fn test_sniff() {
+        assert_eq!(sniff(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"), "image/png");
+        assert_eq!(sniff(b"RIFF\0\0\0\0WEBPVP8 "), "image/webp");
+        assert_eq!(sniff(b"\0\0\0\x18ftypmp42\0\0\0\0"), "video/mp4");
+        assert_eq!(sniff(b"\0asm\x01\0\0\0"), "application/wasm");
+        assert_eq!(sniff(b"wOF2\0\x01\0\0"), "font/woff2");
+        assert_eq!(sniff(b"  <!DOCTYPE html><html></html>"), "text/html;charset=utf-8");
+        assert_eq!(sniff(b"<?xml version=\"1.0\"?><svg></svg>"), "image/svg+xml");
+        assert_eq!(sniff(b"{\"a\": [1, 2]}"), "application/json");
+        assert_eq!(sniff("Lorem ipsum, vino veritas ä".as_bytes()), "text/plain;charset=utf-8");
+        assert_eq!(sniff(b"\x01\x02\x03"), DEFAULT_MIME);
+        assert_eq!(sniff(b""), "text/plain;charset=utf-8");
+    }
+}
@@ -9,6 +9,7 @@ use futures::stream::StreamExt;
 
 use core::fmt;
 
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::sync::Mutex;
 
@@ -24,22 +25,31 @@ use tokio::sync::mpsc as MPSC;
 use futures::Future;
 
 use crate::Filemap;
+use crate::GemGuiError;
+use crate::Result;
 use crate::JSMessageTx;
 use crate::JSType;
 use crate::ui::BATCH_BEGIN;
 use crate::ui::BATCH_END;
 use crate::ui::CLOSE_REQUEST;
+use crate::ui::http;
+use crate::ui::http::DEFAULT_CACHE_CONTROL;
+use crate::ui::mime;
 use crate::ui::utils::get_extension_from_filename;
 use crate::ui_data::ROOT_ID;
 
 type MessageBuffer = Arc<Mutex<Vec<Message>>>;
+type CacheControl = Arc<Mutex<Vec<(regex::Regex, String)>>>;
 
 pub struct WSServer {
     filemap: Arc<Mutex<Filemap>>,
+    cache_control: CacheControl,
+    mime_types: Arc<Mutex<HashMap<String, String>>>,
     port: u16,
     client_tx: BroadcastSender<Message>,
     buffer: MessageBuffer,
-    subscription_sender: SubscriptionSender<String>
+    subscription_sender: SubscriptionSender<String>,
+    binary_sender: SubscriptionSender<Vec<u8>>,
 }
 
 
@@ -85,7 +95,7 @@ impl MsgTx {
             let obj = serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(&msg);
             if obj.is_err() 
             || !obj.as_ref().unwrap().contains_key("type") 
-            || obj.as_ref().unwrap()["type"].to_string() != format!("\"{CLOSE_REQUEST}\"") 
+            || obj.as_ref().unwrap()["type"] != CLOSE_REQUEST 
             || tx.receiver_count() != 0 {  
                 eprintln!("Channel error {e} on {msg} tx count: {}", tx.receiver_count());
             }
@@ -133,7 +143,7 @@ fn write_to_buffer(msg: Message, buffer: &MessageBuffer) {
     buf.push(msg);
 }
 
-pub(super) fn new(filemap: Arc<Mutex<Filemap>>, port: u16, subscription_sender: SubscriptionSender<String>) -> WSServer {
+pub(super) fn new(filemap: Arc<Mutex<Filemap>>, port: u16, subscription_sender: SubscriptionSender<String>, binary_sender: SubscriptionSender<Vec<u8>>) -> WSServer {
     let (client_tx, buffer_rx) = tokio::sync::broadcast::channel(64);
     // we need a buffer where to copy message before  ws is open
     let buffer = Arc::new(Mutex::new(Vec::new()));
@@ -142,16 +152,44 @@ pub(super) fn new(filemap: Arc<Mutex<Filemap>>, port: u16, subscription_sender:
 
     WSServer {
         filemap,
+        cache_control: Arc::new(Mutex::new(Vec::new())),
+        mime_types: Arc::new(Mutex::new(HashMap::new())),
         port,
         client_tx,
         buffer,
         subscription_sender,
+        binary_sender,
     }
 }
 
 
 impl WSServer {
 
+    pub (crate) fn set_cache_control(&self, pattern: &str, policy: &str) -> Result<()> {
+        if let Err(e) = warp::http::HeaderValue::from_str(policy) {
+            return GemGuiError::error(format!("Invalid Cache-Control {policy:#?}: {e}"));
+        }
+        // glob to regex, '*' any characters and '?' a single one
+        let pattern = regex::escape(pattern)
+            .replace(r"\*", ".*")
+            .replace(r"\?", ".");
+        let pattern = regex::Regex::new(&format!("^{pattern}$")).unwrap();
+        self.cache_control.lock().unwrap().push((pattern, policy.to_string()));
+        Ok(())
+    }
+
+    pub (crate) fn set_mime_type(&self, extension: &str, mime: &str) -> Result<()> {
+        let extension = extension.trim_start_matches('.');
+        if extension.is_empty() {
+            return GemGuiError::error("Empty file extension");
+        }
+        if let Err(e) = warp::http::HeaderValue::from_str(mime) {
+            return GemGuiError::error(format!("Invalid MIME type {mime:#?}: {e}"));
+        }
+        self.mime_types.lock().unwrap().insert(extension.to_ascii_lowercase(), mime.to_string());
+        Ok(())
+    }
+
     pub (crate) fn sender(&self) ->  MsgTx {
         MsgTx{tx: self.client_tx.clone()}
     }
@@ -206,13 +244,14 @@ impl WSServer {
         client_tx: BroadcastSender<Message>,
         buffer: MessageBuffer,
         subscription_sender: SubscriptionSender<String>,
+        binary_sender: SubscriptionSender<Vec<u8>>,
         exit_tx: MPSC::Sender<bool>,
         is_gui: bool) {
         // receiver - this server, from websocket client
         // sender - diff clients connected to this server
         let (mut sender, mut receiver) = websocket.split();
 
-        let mut do_buffer = false; // at start we buffer
+        let mut batch_depth = 0u32; // nested batches are sent when the outermost ends
 
         let mut client_rx = client_tx.subscribe();
 
@@ -224,6 +263,8 @@ impl WSServer {
                             if msg.is_text() {
                                 let txt = String::from(msg.to_str().unwrap());
                                 subscription_sender.send(txt).await.unwrap();
+                            } else if msg.is_binary() {
+                                binary_sender.send(msg.into_bytes().to_vec()).await.unwrap();
                             } else if msg.is_close() {
                                 // tell ui.rs to leave the loop - Json constant...
                                 if let Some(cf) = msg.close_frame() {
@@ -256,16 +297,16 @@ impl WSServer {
                                 let started = String::from("{\"type\": \"start_request\"}"); 
                                 subscription_sender.send(started).await.unwrap();
                             } else if msg.is_text() && msg.to_str().unwrap() == BATCH_BEGIN  {
-                                if ! do_buffer {
+                                if batch_depth == 0 {
                                     Self::clear_buffer(&buffer);
-                                    do_buffer = true;
                                 }
+                                batch_depth += 1;
                             } else if msg.is_text() && msg.to_str().unwrap() == BATCH_END  {
-                                if do_buffer {
+                                if batch_depth == 1 {
                                     Self::send_buffered(&mut sender, &buffer).await;
-                                    do_buffer = false;
                                 }
-                            } else if msg.is_text() && do_buffer {
+                                batch_depth = batch_depth.saturating_sub(1);
+                            } else if msg.is_text() && batch_depth > 0 {
                                 write_to_buffer(msg, &buffer);        
                             } else if msg.is_text() || is_gui { // binary data is not sent to extension
                                 sender.send(msg).await.unwrap_or_else(|e| eprintln!("Cannot send msg: {e}"));
@@ -287,6 +328,8 @@ impl WSServer {
             Fut: Future<Output = bool> + Send + 'static {
 
         let fm = self.filemap.clone();
+        let cache_control = self.cache_control.clone();
+        let mime_types = self.mime_types.clone();
         
         // Sigh there is not compile time warning while writing, this
         // but this is quite fragile, bad things happens if
@@ -294,17 +337,29 @@ impl WSServer {
         // how to add keys to paths?
         let  get_routes = 
         warp::get()
-        .and(warp::path::tail()
-        .map(move |path: warp::path::Tail|  {
+        .and(warp::path::tail())
+        .and(warp::header::headers_cloned())
+        .map(move |path: warp::path::Tail, headers: warp::http::HeaderMap|  {
             let name = path.as_str();
             let file_map = fm.lock().unwrap();
             assert!(file_map.contains_key(name), "Request not found: {name:#?}");
 
-            let mime = Self::file_to_mime(name).unwrap_or("octect-stream");
-            
-            warp::reply::with_header(file_map[name].clone(), "content-type", mime)
-            
-        }));
+            let resource = &file_map[name];
+            let mime = {
+                let mime_types = mime_types.lock().unwrap();
+                let ext = get_extension_from_filename(name).map(|ext| ext.to_ascii_lowercase());
+                match ext.as_deref() {
+                    Some(ext) => mime_types.get(ext).cloned().or(mime::extension_to_mime(ext).map(String::from)),
+                    None => None,
+                }.unwrap_or_else(|| mime::sniff(resource.content().unwrap_or_default()).to_string())
+            };
+            let cache_control = cache_control.lock().unwrap();
+            // latest added rule has precedence
+            let policy = cache_control.iter().rev()
+                .find(|(pattern, _)| pattern.is_match(name))
+                .map_or(DEFAULT_CACHE_CONTROL, |(_, policy)| policy.as_str());
+            http::respond(resource, &mime, policy, &headers)
+        });
 
         let (exit_tx, mut exit_rx) = MPSC::channel(32);
 
@@ -312,6 +367,7 @@ impl WSServer {
         let buffer = self.buffer.clone();
         let client_tx = self.client_tx.clone();
         let subscription_sender = self.subscription_sender.clone();
+        let binary_sender = self.binary_sender.clone();
         
         let ui_route = warp::ws()
         .and(warp::path("gemgui"))
@@ -320,10 +376,11 @@ impl WSServer {
             let buffer = buffer.clone();
             let client_tx = client_tx.clone();
             let subscription_sender = subscription_sender.clone();
+            let binary_sender = binary_sender.clone();
             let exit_tx = exit_tx.clone();
             let is_gui = name != "extension";
             ws.on_upgrade( move |websocket: WebSocket| {
-                Self::handle_ws_client(websocket, client_tx, buffer, subscription_sender, exit_tx, is_gui)
+                Self::handle_ws_client(websocket, client_tx, buffer, subscription_sender, binary_sender, exit_tx, is_gui)
             })
         });
 
@@ -353,30 +410,4 @@ impl WSServer {
 
     }
 
-    fn file_to_mime(filename: &str) -> Option<&str>{
-        let ext = get_extension_from_filename(filename)?;
-        let ext = ext.to_ascii_lowercase();
-        let ext = ext.as_str();
-
-        static MAP: phf::Map<&'static str, &'static str> = phf::phf_map! {
-            "html" => "text/html;charset=utf-8",
-            "css" => "text/css;charset=utf-8",
-            "js" => "text/javascript;charset=utf-8",
-            "txt" => "text/txt;charset=utf-8",
-            "ico" => "image/x-icon",
-            "png" => "image/png",
-            "jpg" => "image/jpeg",
-            "gif" => "image/gif",
-            "svg" => "image/svg+xml"
-        };
-
-        match MAP.get(ext) {
-            Some(v) => Some(*v),
-            None => None,
-        }
-    }
-
 } 
-
-
-
@@ -5,14 +5,14 @@ use std::process::{Command, Stdio};
 
 
 pub (crate) fn html_file_launch_cmd() -> Option<(String, Vec<String>)> {
-    if cfg!(target_os = "linux") || cfg!(target_os = "linux") {
+    if cfg!(target_os = "linux") {
         return Some(("x-www-browser".to_string(), Vec::new()));
     }
     if cfg!(target_os = "macos") {
         return Some(("open".to_string(), Vec::new()));
     }
     if cfg!(target_os = "windows") {
-        return Some(("cmd".to_string(), vec!("/C", "start /max").iter().map(|s|{s.to_string()}).collect()));
+        return Some(("cmd".to_string(), ["/C", "start /max"].iter().map(|s|{s.to_string()}).collect()));
     }
     eprintln!("Unknown OS");
     None
@@ -1,5 +1,6 @@
 
 use crate::Filemap;
+use crate::Resource;
 use crate::GemGuiError;
 use crate::JSMap;
 use crate::JSMessageRx;
@@ -14,6 +15,7 @@ use crate::event::Event;
 use crate::msgsender::MsgSender;
 use crate::ui::Target;
 use crate::ui::TimerCallback;
+use crate::ui::AnimationFrameCallback;
 use crate::ui::TimerId;
 use crate::ui::ChannelSender;
 use crate::JSMessageTx;
@@ -27,7 +29,7 @@ use rand::Rng;
 use serde_json::Value;
 use tokio::sync::watch;
 use core::fmt;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::path;
 
 use std::sync::Arc;
@@ -43,7 +45,8 @@ pub (crate) type Timers = HashMap<TimerId,(Arc<Mutex<TimerCallback>>, oneshot::S
 // we put them in Rc, to be able to borrow it without keep Element locked
 // It is too easy to do deadlocks by having Elements locked while callback
 // is called. 
-pub (crate) type ElementMap = HashMap<String, HashMap<String, Arc<Mutex<SubscribeCallback>>>>;
+pub (crate) type Subscriptions = HashMap<String, Arc<Mutex<SubscribeCallback>>>;
+pub (crate) type ElementMap = HashMap<String, Subscriptions>;
 
 #[doc(hidden)] // to let trait be access, protected with sealed pattern
 pub type UiDataRef = Arc<Mutex<UiData>>; 
@@ -54,6 +57,9 @@ type QuerySender  = tokio::sync::oneshot::Sender<serde_json::Value>;
 type QueryReceiver  = tokio::sync::oneshot::Receiver<serde_json::Value>;
 
 type Queries = HashMap<String, QuerySender>;
+
+type BinaryQuerySender  = tokio::sync::oneshot::Sender<Vec<u8>>;
+type BinaryQueryReceiver  = tokio::sync::oneshot::Receiver<Vec<u8>>;
  
 //needed? #[derive(PartialEq)]
 pub (crate) enum State {
@@ -67,11 +73,16 @@ pub (crate) enum State {
 pub struct UiData {
     tx: MsgSender,
     pub (crate) elements: ElementMap,
+    // subscriptions of elements requested to be removed, until UI tells they are gone
+    removing: HashMap<String, VecDeque<Subscriptions>>,
     pub (crate) timers: Timers,
     pub (crate) timer_ids: TimerId,
     pub (crate) timer_sender: ChannelSender<TimerId>,
     started: bool,
     queries: Queries,
+    binary_queries: HashMap<String, BinaryQuerySender>,
+    animation_frame: Option<Arc<Mutex<AnimationFrameCallback>>>,
+    animation_frame_pending: bool,
     on_start_notify: watch::Receiver<State>,
     filemap: Arc<Mutex<Filemap>>,
     subscription_sender: SubscriptionSender,
@@ -101,10 +112,14 @@ impl UiData {
         UiData {
             tx,
             elements: HashMap::new(),
+            removing: HashMap::new(),
             timers: HashMap::new(),
             timer_ids: 1000,
             timer_sender,
             queries: HashMap::new(),
+            binary_queries: HashMap::new(),
+            animation_frame: None,
+            animation_frame_pending: false,
             started: false,
             on_start_notify,
             filemap,
@@ -118,13 +133,16 @@ impl UiData {
         if ! fm.contains_key(resource_name) {
             return None;
         }
-        let v = fm[resource_name].clone();
-        Some(v.try_into().unwrap())
+        fm[resource_name].content().ok().map(Into::into)
     }
 
     pub (crate) fn add_file<PathStr>(ui: &UiDataRef, path: PathStr) -> Result<String> 
     where PathStr: AsRef<path::Path>{
-        let content = std::fs::read(&path)?;
+        let content = Resource::new(std::fs::read(&path)?);
+        let content = match std::fs::metadata(&path)?.modified() {
+            Ok(modified) => content.with_modified(modified),
+            Err(_) => content,
+        };
         let path = path.as_ref();
         let  basename = path.file_stem().unwrap().to_str().unwrap().to_string();
         let  ext = path.extension().unwrap().to_str().unwrap();
@@ -144,8 +162,13 @@ impl UiData {
     }
      
     pub (crate) fn entered(ui: &UiDataRef) {
-        let ui = ui.lock().unwrap();
-        ui.tx.send(ENTERED.to_string());
+        {
+            let mut ui = ui.lock().unwrap();
+            ui.tx.send(ENTERED.to_string());
+            // a (re)loaded page has no frame request on flight
+            ui.animation_frame_pending = false;
+        }
+        Self::request_animation_frame(ui);
     }
 
     pub (crate) fn batch_begin(ui: &UiDataRef) {
@@ -253,6 +276,21 @@ impl UiData {
         ui.queries.remove(id)
     }
 
+    // binary queries share the id space with queries
+    pub (crate) fn new_binary_query(ui: &UiDataRef) -> (String, BinaryQueryReceiver) {
+        assert!(Self::is_started(ui), "Queries are not allowed until UI has started!");
+        let mut ui = ui.lock().unwrap();
+        let id = ui.random_query_id();
+        let (sender, receiver) = tokio::sync::oneshot::channel();
+        ui.binary_queries.insert(id.clone(), sender);
+        (id, receiver)
+    }
+
+    pub (crate) fn get_binary_query_sender(ui: &mut UiDataRef, id: &str) -> Option<BinaryQuerySender> {
+        let mut ui = ui.lock().unwrap();
+        ui.binary_queries.remove(id)
+    }
+
     fn append_timer<CB>(ui: &UiDataRef, callback: CB, sender: oneshot::Sender<u32>) -> u32
     where CB: FnMut(UiRef, TimerId) + Send + 'static  {
         let mut ui = ui.lock().unwrap();
@@ -352,6 +390,42 @@ impl UiData {
         }
     }
 
+    pub (crate) fn contains_element(ui: &UiDataRef, id: &str) -> bool {
+        let ui = ui.lock().unwrap();
+        ui.elements.contains_key(id)
+    }
+
+    pub (crate) fn set_animation_frame(ui: &UiDataRef, callback: Option<Box<AnimationFrameCallback>>) {
+        {
+            let mut ui = ui.lock().unwrap();
+            ui.animation_frame = callback.map(|cb| Arc::new(Mutex::new(cb)) as Arc<Mutex<AnimationFrameCallback>>);
+        }
+        Self::request_animation_frame(ui);
+    }
+
+    // a frame has arrived
+    pub (crate) fn take_animation_frame(ui: &UiDataRef) -> Option<Arc<Mutex<AnimationFrameCallback>>> {
+        let mut ui = ui.lock().unwrap();
+        ui.animation_frame_pending = false;
+        ui.animation_frame.clone()
+    }
+
+    // there is only one frame request on flight
+    pub (crate) fn request_animation_frame(ui: &UiDataRef) {
+        let mut ui = ui.lock().unwrap();
+        if ui.animation_frame.is_none() || ui.animation_frame_pending {
+            return;
+        }
+        ui.animation_frame_pending = true;
+        let msg =  JSMessageTx {
+            element: ROOT_ID,
+            _type: "animation_frame",
+            ..Default::default()
+        };
+        let json = serde_json::to_string(&msg).unwrap();
+        ui.tx.send(json);
+    }
+
     pub (crate) fn set_started(ui: &UiDataRef) {
         let mut ui = ui.lock().unwrap();
         debug_assert!(!ui.started);
@@ -375,14 +449,57 @@ impl UiData {
     where CB: FnMut(UiRef, Event) + Send + 'static,
       Str: Into<String>{
         let mut ui = ui_ref.lock().unwrap();
-        let handler_map = ui.elements.get_mut(id).unwrap();    
+        // element may have been removed from UI while Element was still kept around
+        let handler_map = ui.elements.entry(id.to_string()).or_default();    
         handler_map.insert(name.into(), Arc::new(Mutex::new(callback)));
     }
 
     pub(crate) fn remove_subscription(ui_ref: &UiDataRef, id: &str, name: &str) {
         let mut ui = ui_ref.lock().unwrap();
-        let handler_map = ui.elements.get_mut(id).unwrap();    
-        handler_map.remove(name);
+        if let Some(handler_map) = ui.elements.get_mut(id) {
+            handler_map.remove(name);
+        }
+    }
+
+    // Drops subscriptions of an element that never was created
+    pub(crate) fn drop_subscriptions(ui_ref: &UiDataRef, id: &str) {
+        if id == ROOT_ID {
+            return;
+        }
+        let mut ui = ui_ref.lock().unwrap();
+        ui.elements.remove(id);
+    }
+
+    // Element removal is requested, its subscriptions are set aside so that an element
+    // re-created with the same id keeps its own ones when UI tells the removal
+    pub(crate) fn detach_element(ui_ref: &UiDataRef, id: &str) {
+        if id == ROOT_ID {
+            return;
+        }
+        let mut ui = ui_ref.lock().unwrap();
+        let Some(subscriptions) = ui.elements.remove(id) else {
+            return;
+        };
+        // UI tells only removals of elements it has subscriptions
+        if subscriptions.keys().any(|name| name != "created") {
+            ui.removing.entry(id.to_string()).or_default().push_back(subscriptions);
+        }
+    }
+
+    // Drops all subscriptions of the element, called when UI tells that element is gone
+    pub(crate) fn remove_element(ui_ref: &UiDataRef, id: &str) -> Option<Subscriptions> {
+        if id == ROOT_ID {
+            return None;
+        }
+        let mut ui = ui_ref.lock().unwrap();
+        if let Some(detached) = ui.removing.get_mut(id) {
+            let subscriptions = detached.pop_front();
+            if detached.is_empty() {
+                ui.removing.remove(id);
+            }
+            return subscriptions;
+        }
+        ui.elements.remove(id)
     }
 
     pub (crate) fn call_subscription(ui_ref: &UiDataRef, id: &str, name: &str, properties: Properties) {
@@ -442,7 +559,7 @@ impl UiData {
     fn random_query_id(&self) -> String {
         loop {
             let name = Self::random("query");
-            if ! self.queries.contains_key(&name)  {
+            if ! self.queries.contains_key(&name) && ! self.binary_queries.contains_key(&name) {
                 return name;
             }
         }
@@ -1,10 +1,18 @@
 
+use std::sync::Arc;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+
+use futures::Future;
+
 use crate::Result;
 use crate::GemGuiError;
 use crate::JSMessageTx;
 use crate::JSType;
 use crate::element::Element;
 use crate::element::Elements;
+use crate::element_builder::ElementBuilder;
+use crate::element_builder::Insert;
 use crate::ui::Ui;
 use crate::ui::private;
 use crate::ui_data::ROOT_ID;
@@ -16,12 +24,17 @@ use crate::ui_data::UiData;
 /// Refence to UI
 pub struct UiRef {
     ui: UiDataRef,
+    batch_depth: Arc<AtomicUsize>,
 }
 
 impl private::UserInterface for UiRef {
     fn ui(&self) -> &UiDataRef {
         &self.ui
     }
+
+    fn batch_depth(&self) -> Option<&Arc<AtomicUsize>> {
+        Some(&self.batch_depth)
+    }
 }
 
 impl Ui for UiRef {}
@@ -38,7 +51,12 @@ API) - however only UiRef and Ui are the API
 impl UiRef {
 
     pub (crate) fn new(ui: UiDataRef) -> UiRef {
-        UiRef { ui }
+        UiRef { ui, batch_depth: Arc::new(AtomicUsize::new(0)) }
+    }
+
+    // reference within the same call chain, batches opened on either are seen by both
+    fn nested(&self) -> UiRef {
+        UiRef { ui: self.ui.clone(), batch_depth: self.batch_depth.clone() }
     }
 
     /// Get UI native pixel ratio
@@ -110,8 +128,7 @@ impl UiRef {
 
 
     fn contains_id(&self, key: &str) -> bool {
-        let ui = self.ui.lock().unwrap();
-        ui.elements.contains_key(key)
+        UiData::contains_element(&self.ui, key)
     }
     
     fn create_element(&self, id: &str, html_element: &str, parent: &Element) -> Result<Element> {
@@ -266,6 +283,122 @@ impl UiRef {
     }
     
 
+    /// Create a new element with its attributes, styles, children and subscriptions
+    /// 
+    /// The whole element tree is created with a single request. If any id of the tree already
+    /// exists in UI, nothing is created, an error is reported and the tree's subscriptions are dropped.
+    /// 
+    /// # Example
+    /// 
+    /// ```
+    /// # use gemgui::ui_ref::UiRef;
+    /// # use crate::gemgui::ui::Ui;
+    /// # use gemgui::element_builder::{ElementBuilder, Insert};
+    ///   fn some_function(ui: UiRef) {
+    ///     let header = ui.element("header"); 
+    ///     let paragraph = ElementBuilder::new("p")
+    ///         .id("intro")
+    ///         .style("color", "blue")
+    ///         .text("Lorem ipsum");
+    ///     ui.build_element(paragraph, Insert::After(&header)).unwrap();
+    /// }
+    /// ```
+    /// 
+    ///  # Arguments
+    /// 
+    /// `builder` - element description
+    /// 
+    /// `position` - where element is inserted
+    /// 
+    /// # Return
+    /// 
+    /// Elements created in depth first order, i.e. the built element is the first. Please note that
+    /// element creation may not be completed yet.
+    pub fn build_element(&self, builder: ElementBuilder, position: Insert) -> Result<Elements> {
+        let (tree, elements) = builder.build(&self.ui)?;
+        let msg =  JSMessageTx {
+            element: position.element().id(),
+            _type: "create",
+            elements: Some(&tree),
+            position: Some(position.value()),
+            ..Default::default()
+        };
+        UiData::send(&self.ui, msg);
+        Ok(elements)
+    }
+
+    /// Create a new element with its attributes, styles, children and subscriptions
+    /// 
+    /// See more information [build_element](UiRef::build_element)
+    /// 
+    ///  # Arguments
+    /// 
+    /// `builder` - element description
+    /// 
+    /// `position` - where element is inserted
+    /// 
+    /// # Return
+    /// 
+    /// Elements created in depth first order, i.e. the built element is the first. 
+    pub async fn build_element_async(&self, builder: ElementBuilder, position: Insert<'_>) -> Result<Elements> {
+        // since subscribe function "could" be called multiple times oneshot cannot be used
+        let (tx, mut rx) = tokio::sync::mpsc::channel::<bool>(1);
+        let builder = builder.subscribe_async("created",
+            |_, _| async move {
+                tx.send(true).await.expect("Fatal error");
+            });
+        let elements = self.build_element(builder, position)?;
+        match rx.recv().await {
+            Some(_) => Ok(elements),
+            None =>   GemGuiError::error(format!("Element {} not constructed", elements[0].id()))
+        }
+    }
+
+    /// Wait until UI has processed all preceding requests
+    /// 
+    /// E.g. after a batch has ended, this returns when the batch is applied in UI. Please note that
+    /// if called within a batch, this returns only after the batch ends.
+    pub async fn flush(&self) -> Result<()> {
+        self.query("", "ping", &vec![]).await?;
+        Ok(())
+    }
+
+    /// Run a function as a batch
+    /// 
+    /// All requests made in `async_func` are sent as a single batch, and this returns when UI has applied it.
+    /// If called within an outer batch of this UiRef, or of the UiRef given to an enclosing `batch_async`,
+    /// the requests are sent only when the outermost batch ends, and this returns without waiting for UI.
+    /// Batches opened by other tasks are not nested ones. See [batch](Ui::batch).
+    /// 
+    /// # Example
+    /// 
+    /// ```
+    /// # use gemgui::ui_ref::UiRef;
+    /// # use gemgui::ui::Ui;
+    /// async fn some_function(ui: UiRef) {
+    ///     ui.batch_async(|ui| async move {
+    ///         ui.element("foo").set_html("Foo");
+    ///         ui.element("bar").set_html("Bar");
+    ///     }).await.unwrap();
+    /// }
+    /// ```
+    /// 
+    /// # Arguments
+    /// 
+    /// `async_func` - function executed within a batch
+    pub async fn batch_async<CB, Fut>(&self, async_func: CB) -> Result<()>
+    where CB: FnOnce(UiRef) -> Fut,
+    Fut: Future<Output = ()> {
+        {
+            let _batch = self.batch();
+            async_func(self.nested()).await;
+        }
+        if self.batch_depth.load(Ordering::SeqCst) > 0 {
+            return Ok(()); // a ping would wait for the outer batch that cannot end meanwhile
+        }
+        self.flush().await
+    }
+
     pub (crate) async fn query(&self, target: &str, name: &str, query_params: &Vec<String>) -> Result<JSType> {
         Self::do_query(&self.ui, target, name, query_params).await
     }
@@ -295,4 +428,24 @@ impl UiRef {
         }        
     }
 
+    // query where UI responds with a binary message
+    pub (crate) async fn binary_query(&self, target: &str, name: &str, query_params: &Vec<String>) -> Result<Vec<u8>> {
+        let (id, receiver) = UiData::new_binary_query(&self.ui);
+        let msg =  JSMessageTx {
+            element: target,
+            _type: "query",
+            query_id: Some(&id),
+            query: Some(name),
+            query_params: Some(query_params),
+            ..Default::default()
+        };
+
+        UiData::send(&self.ui, msg);
+
+        match receiver.await {
+            Ok(v) => Ok(v),
+            Err(e) => GemGuiError::error(format!("Query error {e}"))
+        }
+    }
+
 }
@@ -66,7 +66,7 @@ pub fn headless_params(log: bool) -> Vec<String> {
 pub fn system_chrome() -> Option<(String, Vec<String>)> {
     if cfg!(target_os = "linux")  {
         for browser in vec!("chromium-chrome", "google-chrome").into_iter() {
-            if which(&browser).is_ok() {
+            if which(browser).is_ok() {
                 return Some((String::from(browser), Vec::new()));
             }
         }
@@ -93,7 +93,7 @@ pub fn system_chrome() -> Option<(String, Vec<String>)> {
 }
 
 fn kill_command(cmd: &str) -> String {
-    return format!(r#"for pid in $(pgrep -f "{}"); do 
+    format!(r#"for pid in $(pgrep -f "{}"); do 
     for child in $(pgrep -P $pid -d " "); do 
         kill -TERM $child; 
     done; 
@@ -108,8 +108,8 @@ fn kill_command(cmd: &str) -> String {
 pub fn kill_headless() -> bool {
     let cmd: (&str, Vec<String>) = 
     if cfg!(target_os = "windows") {
-        (("powershell.exe",
-        vec!(r#"powershell.exe -command "Get-CimInstance -ClassName Win32_Process -Filter 'CommandLine LIKE ''%--headless%'' | %{Stop-Process -Id $_.ProcessId}""#.into())))
+        ("powershell.exe",
+        vec!(r#"powershell.exe -command "Get-CimInstance -ClassName Win32_Process -Filter 'CommandLine LIKE ''%--headless%'' | %{Stop-Process -Id $_.ProcessId}""#.into()))
     } else if cfg!(target_os = "macos") {
         // old ("pkill", vec!(r#"-f \"(chrome)?(--headless)\""#))
         // ("pkill", vec!("-P", "-f", r#"Google Chrome.*headless"#))
@@ -1,4 +1,6 @@
 use gemgui::element::Element;
+use gemgui::animation::{AnimationOptions, Fill, Keyframe};
+use gemgui::element_builder::{ElementBuilder, Insert};
 use gemgui::graphics::color::{name_to_rgb, style_to_name};
 
 use gemgui::ui_ref::UiRef;
@@ -383,7 +385,7 @@ async fn test_subscribe() {
         let prop = event.property_str("class").unwrap();
         assert_eq!(prop, "some_class");
         ui.exit();
-    }, &vec!("class")); // actually monitors mutations of content
+    }, &["class"]); // actually monitors mutations of content
     ui.on_start(|ui| ui.element("content").set_style("color", "green"));  
     ui.run().await.unwrap();
 }
@@ -771,8 +773,8 @@ async fn add_element_async() {
         let el = ui.add_element_async("P", &ui.root()).await.unwrap();
         assert!(ui.exists(el.id()).await.unwrap());
         let id = el.id();
-        assert!(id != "");
-        assert_eq!(ui.element(&id).element_type().await.unwrap(), "p");    
+        assert!(!id.is_empty());
+        assert_eq!(ui.element(id).element_type().await.unwrap(), "p");    
         ui.exit();
     });
     ui.run().await.unwrap();
@@ -785,7 +787,7 @@ async fn add_element() {
     ui.on_start_async(move |ui| async move {
         ui.add_element("P", &ui.root(), |ui: UiRef, el: Element| {
             let id = el.id();
-            assert!(id != "");
+            assert!(!id.is_empty());
             ui.exit();
         }).unwrap();
     });
@@ -825,13 +827,30 @@ async fn add_element_with_id() {
 async fn add_resource() {
     let ui = setup();
     let path = Path::new("./tests/ext/walruses.jpeg");
-    assert_eq!("walruses.jpeg", ui.add_resource(&path).unwrap());
+    assert_eq!("walruses.jpeg", ui.add_resource(path).unwrap());
     assert!(ui.resource("walruses.jpeg").is_some());
     // add again is possible - name changes as follows
-    assert_eq!("walruses.1.jpeg", ui.add_resource(&path).unwrap());
+    assert_eq!("walruses.1.jpeg", ui.add_resource(path).unwrap());
     assert!(ui.resource("walruses.1.jpeg").is_some());
 }
 
+#[tokio::test]
+#[serial]
+async fn cache_control() {
+    let mut ui = setup();
+    ui.set_cache_control("*.jpeg", "max-age=3600").unwrap();
+    assert!(ui.set_cache_control("*", "no-cache\nbad").is_err());
+}
+
+#[tokio::test]
+#[serial]
+async fn mime_type() {
+    let mut ui = setup();
+    ui.set_mime_type(".glsl", "text/plain;charset=utf-8").unwrap();
+    assert!(ui.set_mime_type("", "text/plain").is_err());
+    assert!(ui.set_mime_type("x", "text/plain\n").is_err());
+}
+
 #[tokio::test]
 #[serial]
 async fn no_exists() {
@@ -873,3 +892,263 @@ async fn dynamic_exits() {
     ui.run().await.unwrap();
 }
 
+
+#[tokio::test]
+#[serial]
+async fn test_remove_subscriptions() {
+    let mut ui = setup();
+    ui.on_start_async(|ui| async move {
+        let p = ui.element("paramount");
+        p.subscribe(gemgui::event::CLICK, |_, _| {});
+        p.subscribe(gemgui::event::REMOVED, |ui, ev| {
+            assert_eq!(ev.element().id(), "paramount");
+            ui.exit();
+        });
+        // replaces paramount
+        ui.element("another_content").set_html("<p>Replaced</p>");
+    });
+    ui.run().await.unwrap();
+}
+
+#[tokio::test]
+#[serial]
+async fn test_remove_element_subscriptions() {
+    let mut ui = setup();
+    ui.on_start_async(|ui| async move {
+        let el = ui.add_element_async("div", &ui.root()).await.unwrap();
+        el.subscribe(gemgui::event::REMOVED, |ui, _| ui.exit());
+        el.remove();
+    });
+    ui.run().await.unwrap();
+}
+
+#[tokio::test]
+#[serial]
+async fn test_build_element() {
+    let mut ui = setup();
+    ui.on_start_async(|ui| async move {
+        let list = ElementBuilder::new("ul")
+            .id("built_list")
+            .class("built")
+            .attribute("title", "Built")
+            .style("color", "red")
+            .child(ElementBuilder::new("li").text("Foo"))
+            .child(ElementBuilder::new("li").text("Bar"));
+        let elements = ui.build_element_async(list, Insert::Append(&ui.root())).await.unwrap();
+        assert_eq!(elements.len(), 3);
+        assert_eq!(elements[0].id(), "built_list");
+        assert!(ui.exists("built_list").await.unwrap());
+        let children = elements[0].children().await.unwrap();
+        assert_eq!(children.len(), 2);
+        assert_eq!(children[1].html().await.unwrap(), "Bar");
+        let classes = ui.by_class("built").await.unwrap();
+        assert_eq!(classes.len(), 1);
+        let attributes = elements[0].attributes().await.unwrap();
+        assert_eq!(attributes["title"], "Built");
+        ui.exit();
+    });
+    ui.run().await.unwrap();
+}
+
+#[tokio::test]
+#[serial]
+async fn test_build_element_before() {
+    let mut ui = setup();
+    ui.on_start_async(|ui| async move {
+        let parent = ui.add_element_async("div", &ui.root()).await.unwrap();
+        let last = ui.add_element_async("span", &parent).await.unwrap();
+        let first = ElementBuilder::new("span").id("first_span");
+        ui.build_element_async(first, Insert::Before(&last)).await.unwrap();
+        let children = parent.children().await.unwrap();
+        assert_eq!(children.len(), 2);
+        assert_eq!(children[0].id(), "first_span");
+        assert_eq!(children[1].id(), last.id());
+        let bad = ElementBuilder::new("span").id("first_span");
+        assert!(ui.build_element(bad, Insert::After(&last)).is_err());
+        ui.exit();
+    });
+    ui.run().await.unwrap();
+}
+
+#[tokio::test]
+#[serial]
+async fn test_batch_nested() {
+    let mut ui = setup();
+    ui.on_start_async(|ui| async move {
+        let el = ui.add_element_async("div", &ui.root()).await.unwrap();
+        {
+            let _outer = ui.batch();
+            {
+                let _inner = ui.batch();
+                el.set_html("inner");
+            }
+            el.set_html("outer");
+        }
+        ui.flush().await.unwrap();
+        assert_eq!(el.html().await.unwrap(), "outer");
+        let id = el.id().to_string();
+        ui.batch_async(|ui| async move {
+            ui.element(&id).set_html("async");
+        }).await.unwrap();
+        assert_eq!(el.html().await.unwrap(), "async");
+        ui.exit();
+    });
+    ui.run().await.unwrap();
+}
+
+#[tokio::test]
+#[serial]
+async fn test_animation_frame() {
+    let mut ui = setup();
+    ui.on_start(|ui| {
+        let mut frames = 0;
+        let mut last = Duration::ZERO;
+        ui.on_animation_frame(move |ui, timestamp| {
+            assert!(timestamp >= last);
+            last = timestamp;
+            frames += 1;
+            if frames == 10 {
+                ui.cancel_animation_frame();
+                ui.exit();
+            }
+        });
+    });
+    ui.run().await.unwrap();
+}
+
+#[tokio::test]
+#[serial]
+async fn test_animate() {
+    let mut ui = setup();
+    ui.on_start_async(|ui| async move {
+        let el = ui.add_element_async("div", &ui.root()).await.unwrap();
+        let animation = el.animate(&[
+            Keyframe::new().style("opacity", "1"),
+            Keyframe::new().style("opacity", "0")],
+            &AnimationOptions::new(Duration::from_millis(100)).fill(Fill::Forwards));
+        animation.finished().await.unwrap();
+        let styles = el.styles(&["opacity"]).await.unwrap();
+        assert_eq!(styles["opacity"], "0");
+        let animation = el.animate(&[
+            Keyframe::new().style("width", "10px"),
+            Keyframe::new().style("width", "20px")],
+            &AnimationOptions::new(Duration::from_secs(10)));
+        animation.pause();
+        animation.cancel();
+        assert!(animation.finished().await.is_err());
+        // an infinite animation ends with its element
+        let animation = el.animate(&[
+            Keyframe::new().style("opacity", "1"),
+            Keyframe::new().style("opacity", "0")],
+            &AnimationOptions::new(Duration::from_millis(100)).infinite());
+        el.remove();
+        assert!(animation.finished().await.is_err());
+        ui.exit();
+    });
+    ui.run().await.unwrap();
+}
+
+#[tokio::test]
+#[serial]
+async fn test_transition_end() {
+    let mut ui = setup();
+    ui.on_start_async(|ui| async move {
+        let el = ui.add_element_async("div", &ui.root()).await.unwrap();
+        el.set_style("width", "10px");
+        el.set_style("transition", "width 0.1s");
+        el.subscribe_transition_end(|ui, ev| {
+            assert_eq!(ev.property_name(), "width");
+            ui.exit();
+        });
+        ui.after(Duration::from_millis(100), move |_, _| el.set_style("width", "20px"));
+    });
+    ui.run().await.unwrap();
+}
+
+#[tokio::test]
+#[serial]
+async fn test_remove_recreated_subscriptions() {
+    let mut ui = setup();
+    ui.on_start_async(|ui| async move {
+        let el = ui.add_element_with_id_async("recreated", "div", &ui.root()).await.unwrap();
+        el.subscribe(gemgui::event::REMOVED, |_, _| {});
+        el.remove();
+        // re-created before UI tells the first one is removed
+        let el = ui.add_element_with_id("recreated", "div", &ui.root(), |_: UiRef, _: Element| {}).unwrap();
+        let removing = gemgui::Value::new(false);
+        let is_removing = removing.clone();
+        el.subscribe(gemgui::event::REMOVED, move |ui, _| {
+            assert!(is_removing.cloned());
+            ui.exit();
+        });
+        assert!(ui.exists("recreated").await.unwrap());
+        removing.assign(true);
+        el.remove();
+    });
+    ui.run().await.unwrap();
+}
+
+#[tokio::test]
+#[serial]
+async fn test_build_element_existing_child() {
+    let mut ui = setup();
+    ui.on_error(|_, _| {}); // error is expected
+    ui.on_start_async(|ui| async move {
+        // child id exists in HTML only
+        let tree = ElementBuilder::new("div").id("built_parent")
+            .child(ElementBuilder::new("span").id("hidden_image"));
+        assert!(ui.build_element_async(tree, Insert::Append(&ui.root())).await.is_err());
+        assert!(!ui.exists("built_parent").await.unwrap());
+        ui.exit();
+    });
+    ui.run().await.unwrap();
+}
+
+#[tokio::test]
+#[serial]
+async fn test_batch_async_nested() {
+    let mut ui = setup();
+    ui.on_start_async(|ui| async move {
+        let el = ui.add_element_async("div", &ui.root()).await.unwrap();
+        let id = el.id().to_string();
+        {
+            let _outer = ui.batch();
+            ui.batch_async(|ui| async move {
+                ui.element(&id).set_html("nested");
+            }).await.unwrap();
+            el.set_html("outer");
+        }
+        ui.flush().await.unwrap();
+        assert_eq!(el.html().await.unwrap(), "outer");
+        ui.exit();
+    });
+    ui.run().await.unwrap();
+}
+
+#[tokio::test]
+#[serial]
+async fn test_batch_async_concurrent() {
+    let mut ui = setup();
+    ui.on_start_async(|ui| async move {
+        let el = ui.add_element_async("div", &ui.root()).await.unwrap();
+        let ended = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let outer = ui.batch();
+        // a batch of an unrelated task waits for UI, i.e. until the outer batch has ended
+        let task = {
+            let (el, ended) = (el.clone(), ended.clone());
+            tokio::spawn(async move {
+                el.ui().batch_async(|_| async move {
+                    el.set_html("task");
+                }).await.unwrap();
+                assert!(ended.load(std::sync::atomic::Ordering::SeqCst));
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        ended.store(true, std::sync::atomic::Ordering::SeqCst);
+        drop(outer);
+        task.await.unwrap();
+        assert_eq!(el.html().await.unwrap(), "task");
+        ui.exit();
+    });
+    ui.run().await.unwrap();
+}
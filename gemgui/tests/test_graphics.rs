@@ -3,7 +3,7 @@ use std::{time::Duration};
 use serial_test::serial;
 use crate::tests::setup;
 
-use gemgui::{graphics::{canvas::{Canvas, DrawNotify}, context::{TextBaseLine, Context2D, TextAlign}, color as Color, bitmap::Bitmap}, Rect, ui_ref::UiRef, ui::Ui};
+use gemgui::{graphics::{canvas::{Canvas, Compression, DrawNotify}, context::{TextBaseLine, Context2D, TextAlign, Gradient, LineCap, LineJoin, CompositeOperation, SmoothingQuality}, color as Color, bitmap::{Bitmap, BitmapData}, animated::AnimatedBitmap}, Rect, ui_ref::UiRef, ui::Ui};
 
 
 #[path="./tests.rs"]
@@ -137,6 +137,45 @@ async fn test_context() {
     ui.run().await.unwrap();
 }
 
+#[tokio::test]
+#[serial]
+async fn test_context_styles() {
+    let mut ui = setup();
+    let rect = Rect::new(0.0, 0.0, 100., 100.);
+    let gradient = Gradient::linear(0., 0., 100., 0.)
+        .color_stop(0., Color::RED)
+        .color_stop(1., Color::BLUE);
+    let mut ctx = Context2D::new();
+    ctx.save()
+    .fill_gradient(&gradient)
+    .stroke_gradient(&Gradient::conic(0., 50., 50.).color_stop(0.5, Color::GREEN))
+    .set_line_dash(&[4., 2.])
+    .line_dash_offset(1.)
+    .line_cap(LineCap::Square)
+    .line_join(LineJoin::Round)
+    .miter_limit(5.)
+    .global_alpha(0.5)
+    .global_composite_operation(CompositeOperation::Multiply)
+    .shadow_color(Color::BLACK)
+    .shadow_blur(2.)
+    .shadow_offset(1., 1.)
+    .set_transform(1., 0., 0., 1., 0., 0.)
+    .transform(1., 0., 0., 1., 5., 5.)
+    .begin_path()
+    .round_rect(&rect, &[5., 10.])
+    .clip()
+    .filter("blur(1px)")
+    .image_smoothing(false)
+    .image_smoothing_quality(SmoothingQuality::High)
+    .fill_rect(&rect)
+    .reset_transform()
+    .restore();
+    let canvas = Canvas::new(&ui.element("canvas"));
+    canvas.draw_context(&ctx);
+    canvas.on_draw(|ui| ui.exit(), DrawNotify::Kick);
+    ui.run().await.unwrap();
+}
+
 
 #[tokio::test]
 #[serial]
@@ -333,3 +372,172 @@ async fn test_bitmap_draw17() {
 async fn test_bitmap_draw18() {
     bitmap_test(500, 5, 10, 10).await
 }
+
+async fn compressed_bitmap_test(compression: Compression) {
+    let mut ui = setup();
+    let t = timeout(5000);
+    let mut canvas = Canvas::new(&ui.element("canvas"));
+    canvas.set_compression(compression);
+    let mut bmp = Bitmap::rect(200, 200, Color::CYAN);
+    bmp.put(10, 10, Color::RED);
+    canvas.on_draw(move |ui| {
+        ui.exit();
+    }, DrawNotify::NoKick);
+    canvas.draw_bitmap(&bmp);
+    ui.run().await.unwrap();
+    t.abort();
+}
+
+#[tokio::test]
+#[serial]
+async fn test_bitmap_rle() {
+    compressed_bitmap_test(Compression::Rle).await
+}
+
+#[tokio::test]
+#[serial]
+async fn test_bitmap_deflate() {
+    compressed_bitmap_test(Compression::Deflate).await
+}
+
+#[tokio::test]
+#[serial]
+async fn test_bitmap_png() {
+    compressed_bitmap_test(Compression::Png).await
+}
+
+#[tokio::test]
+#[serial]
+async fn test_canvas_size() {
+    let mut ui = setup();
+    ui.on_start_async(|ui| async move {
+        let canvas = Canvas::new(&ui.element("canvas"));
+        canvas.set_size(200, 100);
+        let ratio = ui.device_pixel_ratio().await.unwrap();
+        let (width, height) = canvas.size().await.unwrap();
+        assert_eq!(width, (200. * ratio).round() as u32);
+        assert_eq!(height, (100. * ratio).round() as u32);
+        let metrics = canvas.measure_text("20px serif", "Hello").await.unwrap();
+        assert!(metrics.width > 0.);
+        let wider = canvas.measure_text("20px serif", "Hello World").await.unwrap();
+        assert!(wider.width > metrics.width);
+        ui.exit();
+    });
+    ui.run().await.unwrap();
+}
+
+#[tokio::test]
+#[serial]
+async fn test_get_image_data() {
+    let mut ui = setup();
+    ui.on_start_async(|ui| async move {
+        let canvas = Canvas::new(&ui.element("canvas"));
+        let mut bitmap = Bitmap::rect(40, 30, Color::RED);
+        bitmap.put(5, 6, Color::BLUE);
+        canvas.paint(0, 0, &bitmap);
+        let data = canvas.get_image_data(Rect::new(0, 0, 40, 30)).await.unwrap();
+        assert_eq!((data.width(), data.height()), (40, 30));
+        assert_eq!(data.get(5, 6), Color::BLUE);
+        assert_eq!(data.get(39, 29), Color::RED);
+        let data = canvas.get_image_data(Rect::new(5, 6, 2, 1)).await.unwrap();
+        assert_eq!(data.get(0, 0), Color::BLUE);
+        assert_eq!(data.get(1, 0), Color::RED);
+        assert!(canvas.get_image_data(Rect::new(0, 0, 0, 10)).await.is_err());
+        let png = canvas.to_png().await.unwrap();
+        let image = Bitmap::from_image_bytes(&png).unwrap();
+        assert_eq!(image.get(5, 6), Color::BLUE);
+        ui.exit();
+    });
+    ui.run().await.unwrap();
+}
+
+#[tokio::test]
+#[serial]
+async fn test_layers() {
+    let mut ui = setup();
+    ui.on_start_async(|ui| async move {
+        let canvas = Canvas::new(&ui.element("canvas"));
+        let background = canvas.create_layer("background");
+        let overlay = canvas.create_layer("overlay");
+        assert_eq!(overlay.name(), "overlay");
+        assert_ne!(background.id(), overlay.id());
+        background.paint(0, 0, &Bitmap::rect(40, 30, Color::RED));
+        let mut context = Context2D::new();
+        context.fill_style("blue").fill_rect(&Rect::new(10., 10., 5., 5.));
+        overlay.draw_context(&context);
+        canvas.composite(&[(&background, CompositeOperation::SourceOver), (&overlay, CompositeOperation::SourceOver)]);
+        let data = canvas.get_image_data(Rect::new(0, 0, 40, 30)).await.unwrap();
+        assert_eq!(data.get(0, 0), Color::RED);
+        assert_eq!(data.get(12, 12), Color::BLUE);
+        // layers persist, only background is changed
+        background.paint(0, 0, &Bitmap::rect(40, 30, Color::GREEN));
+        canvas.composite(&[(&background, CompositeOperation::SourceOver), (&overlay, CompositeOperation::SourceOver)]);
+        let data = canvas.get_image_data(Rect::new(0, 0, 40, 30)).await.unwrap();
+        assert_eq!(data.get(0, 0), Color::GREEN);
+        assert_eq!(data.get(12, 12), Color::BLUE);
+        overlay.remove();
+        ui.exit();
+    });
+    ui.run().await.unwrap();
+}
+
+#[tokio::test]
+#[serial]
+async fn test_playback_removed() {
+    let mut ui = setup();
+    ui.on_start_async(|ui| async move {
+        let element = ui.add_element_async("canvas", &ui.root()).await.unwrap();
+        let canvas = Canvas::new(&element);
+        let frames = vec!((Bitmap::rect(4, 4, Color::RED), Duration::from_millis(20)), (Bitmap::rect(4, 4, Color::BLUE), Duration::from_millis(20)));
+        let animation = AnimatedBitmap::new(frames);
+        let playback = canvas.play(&animation, 0, 0, None);
+        assert!(playback.is_playing());
+        element.remove();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(!playback.is_playing());
+        // guard stops when dropped
+        let element = ui.add_element_async("canvas", &ui.root()).await.unwrap();
+        let playback = Canvas::new(&element).play(&animation, 0, 0, None).stop_on_drop();
+        assert!(playback.is_playing());
+        drop(playback);
+        ui.exit();
+    });
+    ui.run().await.unwrap();
+}
+
+#[tokio::test]
+#[serial]
+async fn test_compressed_read_back() {
+    let mut ui = setup();
+    ui.on_start_async(|ui| async move {
+        let mut canvas = Canvas::new(&ui.element("canvas"));
+        canvas.set_compression(Compression::Png);
+        let mut bmp = Bitmap::rect(40, 30, Color::CYAN);
+        bmp.put(10, 10, Color::RED);
+        canvas.paint(0, 0, &bmp);
+        // query is served only after the tile is decoded and painted
+        let data = canvas.get_image_data(Rect::new(0, 0, 40, 30)).await.unwrap();
+        assert_eq!(data.get(0, 0), Color::CYAN);
+        assert_eq!(data.get(10, 10), Color::RED);
+        ui.exit();
+    });
+    ui.run().await.unwrap();
+}
+
+#[tokio::test]
+#[serial]
+async fn test_layers_compressed() {
+    let mut ui = setup();
+    ui.on_start_async(|ui| async move {
+        let canvas = Canvas::new(&ui.element("canvas"));
+        let mut background = canvas.create_layer("background");
+        background.set_compression(Compression::Deflate);
+        background.paint(0, 0, &Bitmap::rect(40, 30, Color::RED));
+        canvas.composite(&[(&background, CompositeOperation::SourceOver)]);
+        let data = canvas.get_image_data(Rect::new(0, 0, 40, 30)).await.unwrap();
+        assert_eq!(data.get(20, 20), Color::RED);
+        background.remove();
+        ui.exit();
+    });
+    ui.run().await.unwrap();
+}
@@ -36,8 +36,7 @@ pub (crate) fn setup () -> gemgui::ui::Gui {
         }
         let mut ui = gemgui::ui::Gui::new(fm, "tests.html", port).unwrap();
         let chrome = chrome::system_chrome();
-        if chrome.is_some() {
-            let (cmd, cmd_params) = chrome.unwrap();
+        if let Some((cmd, cmd_params)) = chrome {
             let mut params = cmd_params.clone();
             params.extend([
                 "--disable-logging".into(),